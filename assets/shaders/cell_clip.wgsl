@@ -0,0 +1,27 @@
+#define_import_path cell_automata::clip
+
+// Clipping and exploding set from the main world, see `CellClipUniform`
+// Each cell shader binds its own `CellClip` uniform and passes it in
+struct CellClip {
+    plane: vec4<f32>,    // xyz = normal, w = offset of the slice plane
+    box_min: vec4<f32>,  // w = 1 keeps the cells inside the clip box, -1 the ones outside, 0 all of them
+    box_max: vec4<f32>,
+    explode: vec4<f32>,  // x = gap, y = chunk size (0 = octants) of the exploded view
+};
+
+// Whether the cell centered at `position` is cut away by the slice plane or the clip box
+fn clipped(clip: CellClip, position: vec3<f32>) -> bool {
+    let inside = all(position >= clip.box_min.xyz) && all(position <= clip.box_max.xyz);
+    return dot(position, clip.plane.xyz) > clip.plane.w
+        || (clip.box_min.w > 0.0 && !inside)
+        || (clip.box_min.w < 0.0 && inside);
+}
+
+// How far the exploded view moves the cell centered at `position`, away from the grid center
+fn exploded_offset(clip: CellClip, position: vec3<f32>) -> vec3<f32> {
+    if clip.explode.y > 0.0 {
+        // Every chunk moves by its own (centered) chunk coordinates, so neighbors end up a gap apart
+        return (floor(position / clip.explode.y) + 0.5) * clip.explode.x;
+    }
+    return sign(position) * clip.explode.x * 0.5;
+}
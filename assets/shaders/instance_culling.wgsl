@@ -0,0 +1,55 @@
+// Frustum planes of the camera views, see `CellCullingUniform`
+struct CellCulling {
+    planes: array<vec4<f32>, 24>,  // 6 per view, xyz = inward normal, w = distance
+    view_count: u32,
+};
+
+// Floats per instance, matching `InstanceData` (position + scale, color, glow)
+const INSTANCE_FLOATS: u32 = 9u;
+const WORKGROUP_SIZE: u32 = 64u;
+
+@group(0) @binding(0) var<uniform> culling: CellCulling;
+// Plain floats, as a struct with vectors would be padded past the 36 bytes of each instance
+@group(0) @binding(1) var<storage, read> instances: array<f32>;
+@group(0) @binding(2) var<storage, read_write> visible: array<f32>;
+// Indirect draw arguments, the instance count second in both the indexed and non-indexed layouts
+@group(0) @binding(3) var<storage, read_write> draw_args: array<atomic<u32>>;
+
+// Whether a cell of `scale` centered at `position` is on the inner side of all planes of a view
+fn in_view(view: u32, position: vec3<f32>, scale: f32) -> bool {
+    // Bounding sphere of the cell, with some margin for the bigger cell shapes
+    let radius = scale + 0.5;
+    for (var i = 0u; i < 6u; i += 1u) {
+        let plane = culling.planes[view * 6u + i];
+        if dot(plane.xyz, position) + plane.w < -radius {
+            return false;
+        }
+    }
+    return true;
+}
+
+@compute @workgroup_size(64)
+fn cull(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    // Big grids spread over a second dimension past the workgroup count limit
+    let index = id.x + id.y * groups.x * WORKGROUP_SIZE;
+    if index >= arrayLength(&instances) / INSTANCE_FLOATS {
+        return;
+    }
+    let start = index * INSTANCE_FLOATS;
+    let position = vec3<f32>(instances[start], instances[start + 1u], instances[start + 2u]);
+    let scale = instances[start + 3u];
+
+    // Without views (while the exploded view moves cells around) every cell is kept
+    var keep = culling.view_count == 0u;
+    for (var view = 0u; view < culling.view_count && !keep; view += 1u) {
+        keep = in_view(view, position, scale);
+    }
+    if !keep {
+        return;
+    }
+
+    let slot = atomicAdd(&draw_args[1], 1u) * INSTANCE_FLOATS;
+    for (var i = 0u; i < INSTANCE_FLOATS; i += 1u) {
+        visible[slot + i] = instances[start + i];
+    }
+}
@@ -1,7 +1,15 @@
 #import bevy_pbr::{
+    fog,
     mesh_functions,
+    mesh_view_bindings as view_bindings,
+    mesh_view_types,
+    shadows,
     view_transformations::position_world_to_clip,
 }
+#import cell_automata::clip::{CellClip, clipped, exploded_offset}
+
+// Clipping set from the main world, see `CellClipUniform`
+@group(3) @binding(0) var<uniform> clip: CellClip;
 
 struct Vertex {
     @builtin(instance_index) instance_index: u32,
@@ -12,11 +20,16 @@ struct Vertex {
     // Instance attributes
     @location(3) i_pos_scale: vec4<f32>,  // xyz = position, w = scale
     @location(4) i_color: vec4<f32>,      // rgba = color
+    @location(5) i_glow: f32,             // emissive brightness
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) glow: f32,
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_normal: vec3<f32>,
+    @location(4) uv: vec2<f32>,
 };
 
 @vertex
@@ -27,18 +40,71 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     let instance_pos = vertex.i_pos_scale.xyz;
     let instance_scale = vertex.i_pos_scale.w;
 
+    // Clipped cells are cut away whole, collapsing all their vertices onto one point
+    if clipped(clip, instance_pos) {
+        out.clip_position = vec4<f32>(0.0);
+        return out;
+    }
+
     // Apply scale and position to vertex
     let scaled_pos = vertex.position * instance_scale;
-    let world_position = scaled_pos + instance_pos;
+    let world_position = scaled_pos + instance_pos + exploded_offset(clip, instance_pos);
 
     // Transform to clip space
     out.clip_position = position_world_to_clip(world_position);
     out.color = vertex.i_color;
+    out.glow = vertex.i_glow;
+    out.world_position = world_position;
+    // Cells are only scaled and moved, so normals stay as they are
+    out.world_normal = vertex.normal;
+    out.uv = vertex.uv;
 
     return out;
 }
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    // Glowing cells go past full brightness, where bloom picks them up
+    var color = vec4<f32>(in.color.rgb * (1.0 + in.glow), in.color.a);
+
+#ifdef CELL_OUTLINES
+    // Each cube face spans the whole UV square, so its edges are where either coordinate nears 0 or 1.
+    // Screen-space derivatives keep the lines about the same width in pixels up close and far away
+    let edge = min(in.uv, 1.0 - in.uv) / max(fwidth(in.uv), vec2<f32>(1e-4));
+    let outline = 1.0 - smoothstep(0.5, 1.5, min(edge.x, edge.y));
+    color = vec4<f32>(color.rgb * (1.0 - 0.6 * outline), color.a);
+#endif
+
+    // With a shadow casting light, faces turned away from it or shadowed by other cells darken
+    if view_bindings::lights.n_directional_lights > 0u {
+        let light = view_bindings::lights.directional_lights[0];
+        var shadow = 1.0;
+        if (light.flags & mesh_view_types::DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) != 0u {
+            let view_z = dot(vec4<f32>(
+                view_bindings::view.view_from_world[0].z,
+                view_bindings::view.view_from_world[1].z,
+                view_bindings::view.view_from_world[2].z,
+                view_bindings::view.view_from_world[3].z
+            ), vec4<f32>(in.world_position, 1.0));
+            shadow = shadows::fetch_directional_shadow(0u, vec4<f32>(in.world_position, 1.0), in.world_normal, view_z);
+        }
+        let lit = max(dot(normalize(in.world_normal), light.direction_to_light), 0.0) * shadow;
+        // Shadowed faces keep some of their color so the structure stays readable
+        color = vec4<f32>(color.rgb * mix(0.35, 1.0, lit), color.a);
+    }
+
+    // Far cells fade into the camera's DistanceFog, if it has one
+    let fog_params = view_bindings::fog;
+    let distance = length(in.world_position - view_bindings::view.world_position);
+    let scattering = vec3<f32>(0.0);
+    if fog_params.mode == mesh_view_types::FOG_MODE_LINEAR {
+        return fog::linear_fog(fog_params, color, distance, scattering);
+    } else if fog_params.mode == mesh_view_types::FOG_MODE_EXPONENTIAL {
+        return fog::exponential_fog(fog_params, color, distance, scattering);
+    } else if fog_params.mode == mesh_view_types::FOG_MODE_EXPONENTIAL_SQUARED {
+        return fog::exponential_squared_fog(fog_params, color, distance, scattering);
+    } else if fog_params.mode == mesh_view_types::FOG_MODE_ATMOSPHERIC {
+        return fog::atmospheric_fog(fog_params, color, distance, scattering);
+    }
+    return color;
 }
@@ -0,0 +1,28 @@
+#import bevy_render::view::View
+#import cell_automata::clip::{CellClip, clipped, exploded_offset}
+
+// Only the view uniform of the light's shadow view, the cell shader's full view bindings aren't bound here
+@group(0) @binding(0) var<uniform> view: View;
+
+// Same clipping as the cell shader, so cut away cells don't cast shadows either
+@group(1) @binding(0) var<uniform> clip: CellClip;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+
+    // Instance attributes
+    @location(3) i_pos_scale: vec4<f32>,  // xyz = position, w = scale
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> @builtin(position) vec4<f32> {
+    if clipped(clip, vertex.i_pos_scale.xyz) {
+        return vec4<f32>(0.0);
+    }
+    let world_position = vertex.position * vertex.i_pos_scale.w + vertex.i_pos_scale.xyz
+        + exploded_offset(clip, vertex.i_pos_scale.xyz);
+    var clip_position = view.clip_from_world * vec4<f32>(world_position, 1.0);
+    // Cells behind the light's near plane still cast onto the ones in front of it
+    clip_position.z = min(clip_position.z, 1.0);
+    return clip_position;
+}
@@ -0,0 +1,61 @@
+//! Compare the Linear, Morton and Halo cell layouts on large grids, sequential and on all cores,
+//! with and without packed neighbor counting
+//! Run with: cargo run --release --example cell_layout [size...]
+use conway_3d::grid::{CellLayout, Grid};
+use conway_3d::rule::Rule;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::{Duration, Instant};
+
+const STEPS: u32 = 10;
+
+fn main() {
+    let sizes: Vec<i32> = std::env::args().skip(1).filter_map(|arg| arg.parse().ok()).collect();
+    let sizes = if sizes.is_empty() { vec![128, 256] } else { sizes };
+    // Builder keeps churning a random fill, so every step has plenty of neighbor updates
+    let rule = Rule::builder();
+
+    for size in sizes {
+        println!("{}³ grid, {} steps of {}", size, STEPS, rule);
+        let layouts = [CellLayout::Linear, CellLayout::Morton, CellLayout::Halo { width: 1 }];
+        let runs = layouts.into_iter().flat_map(|layout| [(layout, false, true), (layout, false, false), (layout, true, true)]);
+        for (layout, parallel, packed) in runs {
+            // Fill before switching layouts so both simulate the same cells
+            let mut grid = Grid::new(size);
+            grid.fill_random_states(rule.states + 1, &mut StdRng::seed_from_u64(1));
+            let mut grid = grid.with_layout(layout).with_parallel(parallel).with_packed_counting(packed);
+            if grid.layout() != layout {
+                println!("  {:?}: not supported for this size", layout);
+                continue;
+            }
+
+            let start = Instant::now();
+            grid.recount_neighbors(&rule);
+            let recount = start.elapsed();
+
+            let mut rng = StdRng::seed_from_u64(2);
+            let (mut phase1, mut phase2, mut changed) = (Duration::ZERO, Duration::ZERO, 0);
+            for _ in 0..STEPS {
+                let start = Instant::now();
+                let changes = grid.update_states(&rule, &mut rng);
+                phase1 += start.elapsed();
+                changed += changes.spawns.len() + changes.deaths.len();
+                let start = Instant::now();
+                grid.apply_changes(&rule, &changes);
+                phase2 += start.elapsed();
+            }
+
+            println!(
+                "  {:?}{}{}: recount {:7.1}ms, phase 1 {:6.1}ms/step, phase 2 {:6.1}ms/step, {} changes/step, {} living cells",
+                layout,
+                if parallel { " parallel" } else { "" },
+                if packed { "" } else { " unpacked" },
+                recount.as_secs_f64() * 1000.0,
+                phase1.as_secs_f64() * 1000.0 / STEPS as f64,
+                phase2.as_secs_f64() * 1000.0 / STEPS as f64,
+                changed / STEPS as usize,
+                grid.cell_count(),
+            );
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Seed the grid from a formula with `Grid::seed_with` and run it headlessly
+//! Run with: cargo run --release --example gyroid_seed
+use bevy::math::IVec3;
+use conway_3d::grid::Grid;
+use conway_3d::rule::Rule;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const SIZE: i32 = 64;
+
+fn main() {
+    let rule = Rule::builder();
+    let mut grid = Grid::new(SIZE);
+
+    // Thin gyroid sheet inside a ball, everything else stays dead
+    let scale = std::f32::consts::TAU * 3.0 / SIZE as f32;
+    grid.seed_with(&rule, |pos: IVec3| {
+        let p = pos.as_vec3() * scale;
+        let gyroid = p.x.sin() * p.y.cos() + p.y.sin() * p.z.cos() + p.z.sin() * p.x.cos();
+        let inside = (pos - IVec3::splat(SIZE / 2)).length_squared() < (SIZE / 3).pow(2);
+        if inside && gyroid.abs() < 0.15 { rule.birth_state() } else { 0 }
+    });
+
+    let mut rng = StdRng::seed_from_u64(1);
+    println!("{} living cells after seeding with {}", grid.cell_count(), rule);
+    for generation in 1..=20 {
+        grid.step(&rule, &mut rng);
+        if generation % 5 == 0 {
+            println!("generation {:2}: {} living cells", generation, grid.cell_count());
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use crate::rendering::{InstanceData, InstanceMaterialData};
+
+/// Longest time between generations animations stretch over, so an edit after a pause doesn't grow in
+/// for seconds
+const MAX_INTERVAL: f32 = 0.5;
+
+/// Newborn cells grow from nothing and dead cells shrink away over part of the time between generations,
+/// interpolated every rendered frame instead of popping in and out. Surviving cells can blend their
+/// colors and sizes over from the previous generation the same way
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CellAnimation {
+    /// Fraction of the time between generations the animations take, from 0 (off) to 1
+    pub fraction: f32,
+    /// Also blend surviving cells' colors and sizes, e.g. as they decay through their states
+    pub blend: bool,
+    /// Instances of the latest generation as built
+    #[serde(skip)]
+    target: Vec<InstanceData>,
+    /// Whether each of `target` was just born
+    #[serde(skip)]
+    born: Vec<bool>,
+    /// Each of `target` as the previous generation built it, itself for newborns (or without `blend`)
+    #[serde(skip)]
+    from: Vec<InstanceData>,
+    /// Instances of cells that just died, as they were last shown
+    #[serde(skip)]
+    dying: Vec<InstanceData>,
+    /// Elapsed time when the latest generation came in
+    #[serde(skip)]
+    started: Option<f32>,
+    /// Seconds between the last two generations
+    #[serde(skip)]
+    interval: f32,
+    /// Whether the latest animations are still running
+    #[serde(skip)]
+    running: bool,
+}
+
+impl Default for CellAnimation {
+    fn default() -> Self {
+        Self {
+            fraction: 0.8,
+            blend: true,
+            target: Vec::new(),
+            born: Vec::new(),
+            from: Vec::new(),
+            dying: Vec::new(),
+            started: None,
+            interval: 0.05,
+            running: false,
+        }
+    }
+}
+
+/// Key of an instance's cell, exact for the half-cell positions of even-sized grids
+fn cell_key(instance: &InstanceData) -> IVec3 {
+    (instance.position * 2.0).round().as_ivec3()
+}
+
+impl CellAnimation {
+    /// Animate from the instances of the previous generation to `instances` starting at `now`
+    pub fn start(&mut self, instances: Vec<InstanceData>, now: f32) {
+        let previous: HashMap<IVec3, &InstanceData> = self.target.iter().map(|instance| (cell_key(instance), instance)).collect();
+        let current: HashSet<IVec3> = instances.iter().map(cell_key).collect();
+        // The very first generation shows as it is
+        self.born = match self.started {
+            Some(_) => instances.iter().map(|instance| !previous.contains_key(&cell_key(instance))).collect(),
+            None => vec![false; instances.len()],
+        };
+        self.from = instances
+            .iter()
+            .map(|instance| match previous.get(&cell_key(instance)) {
+                Some(&&before) if self.blend => before,
+                _ => *instance,
+            })
+            .collect();
+        self.dying = previous.iter().filter(|(key, _)| !current.contains(key)).map(|(_, &instance)| *instance).collect();
+        if let Some(started) = self.started {
+            self.interval = (now - started).min(MAX_INTERVAL);
+        }
+        self.started = Some(now);
+        self.target = instances;
+        self.running = true;
+    }
+
+    /// Progress of the latest animations at `now`, eased from 0 to 1
+    pub fn progress(&self, now: f32) -> f32 {
+        let elapsed = now - self.started.unwrap_or(now);
+        let duration = self.fraction * self.interval;
+        let t = if duration > 0.0 { (elapsed / duration).clamp(0.0, 1.0) } else { 1.0 };
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Instances to show at `progress`: newborns scaled up by it, the dying scaled down until they're gone
+    /// and survivors blended from how they were shown before
+    pub fn frame(&self, progress: f32) -> Vec<InstanceData> {
+        let grown = self.target.iter().zip(&self.from).zip(&self.born).map(|((&instance, &from), &born)| InstanceData {
+            scale: if born { instance.scale * progress } else { from.scale.lerp(instance.scale, progress) },
+            color: std::array::from_fn(|channel| from.color[channel].lerp(instance.color[channel], progress)),
+            ..instance
+        });
+        let shrunk = self.dying.iter().filter(|_| progress < 1.0).map(|&instance| InstanceData { scale: instance.scale * (1.0 - progress), ..instance });
+        grown.chain(shrunk).collect()
+    }
+}
+
+/// Pick up newly built instances as the next generation and show the frame of its animations, after
+/// anything that rebuilds the instances
+pub fn animate_cells(mut animation: ResMut<CellAnimation>, mut instance_query: Query<&mut InstanceMaterialData>, time: Res<Time>) {
+    let Ok(mut instances) = instance_query.single_mut() else { return };
+    let now = time.elapsed_secs();
+    if instances.is_changed() {
+        animation.start(instances.0.clone(), now);
+    }
+    if !animation.running {
+        return;
+    }
+    let progress = animation.progress(now);
+    // Showing a frame doesn't count as new instances
+    instances.bypass_change_detection().0 = animation.frame(progress);
+    animation.running = progress < 1.0;
+}
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
+use bevy::camera::{ScalingMode, Viewport};
 use bevy::input::mouse::MouseMotion;
-use bevy::window::{CursorGrabMode, CursorOptions};
+use bevy::post_process::bloom::Bloom;
+use bevy::window::{CursorGrabMode, CursorOptions, PrimaryWindow, WindowResized};
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct FlyCamera {
@@ -119,3 +122,97 @@ pub fn toggle_wireframe(
         wireframe_config.global = !wireframe_config.global;
     }
 }
+
+/// Quad-view layout inspecting the cells like a CAD model: the fly camera shrinks to the top left quarter of
+/// the window and top, front and side orthographic views of the grid center fill the others
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuadView {
+    /// Shown at startup, F2 toggles it
+    pub enabled: bool,
+    /// Height of the world the orthographic views fit, best a bit more than the grid size
+    pub extent: f32,
+}
+
+impl Default for QuadView {
+    fn default() -> Self {
+        Self { enabled: false, extent: 80.0 }
+    }
+}
+
+/// Orthographic camera of the quad view, with its quarter of the window counted from the top left
+#[derive(Component)]
+pub struct OrthoView(UVec2);
+
+/// Spawn the top, front and side cameras of the quad view, drawing only while it's enabled. They take the
+/// fly camera's bloom and fog, so the four views look alike and share its HDR target
+pub fn spawn_quad_view(
+    mut commands: Commands,
+    quad: Res<QuadView>,
+    fly_camera: Query<(Option<&Bloom>, Option<&DistanceFog>), With<FlyCamera>>,
+) {
+    const DISTANCE: f32 = 500.0;
+
+    let (bloom, fog) = fly_camera.iter().next().unwrap_or_default();
+
+    let views = [
+        (UVec2::new(1, 0), Vec3::Y * DISTANCE, Vec3::NEG_Z),
+        (UVec2::new(0, 1), Vec3::Z * DISTANCE, Vec3::Y),
+        (UVec2::new(1, 1), Vec3::X * DISTANCE, Vec3::Y),
+    ];
+    for (order, (quarter, position, up)) in views.into_iter().enumerate() {
+        let camera = commands.spawn((
+            OrthoView(quarter),
+            Camera3d::default(),
+            Camera {
+                order: order as isize + 1,
+                is_active: quad.enabled,
+                // The fly camera cleared the whole window before drawing its quarter
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            Projection::from(OrthographicProjection {
+                scaling_mode: ScalingMode::FixedVertical { viewport_height: quad.extent },
+                far: DISTANCE * 2.0,
+                ..OrthographicProjection::default_3d()
+            }),
+            Transform::from_translation(position).looking_at(Vec3::ZERO, up),
+        )).id();
+        if let Some(bloom) = bloom {
+            commands.entity(camera).insert(bloom.clone());
+        }
+        if let Some(fog) = fog {
+            commands.entity(camera).insert(fog.clone());
+        }
+    }
+}
+
+/// Press F2 to toggle the quad view, which is laid out again whenever the window resizes
+pub fn layout_quad_view(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut resized: MessageReader<WindowResized>,
+    mut quad: ResMut<QuadView>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut fly_camera: Query<&mut Camera, (With<FlyCamera>, Without<OrthoView>)>,
+    mut ortho_cameras: Query<(&mut Camera, &OrthoView)>,
+) {
+    if keys.just_pressed(KeyCode::F2) {
+        quad.enabled = !quad.enabled;
+        println!("Quad view {}", if quad.enabled { "on" } else { "off" });
+    }
+    let resized = resized.read().count() > 0;
+    if !(quad.is_changed() || resized) {
+        return;
+    }
+
+    // A minimized window has no size, but viewports can't be empty
+    let half = (window.physical_size() / 2).max(UVec2::ONE);
+    let viewport = |quarter: UVec2| Viewport { physical_position: quarter * half, physical_size: half, ..default() };
+    for mut camera in &mut fly_camera {
+        camera.viewport = quad.enabled.then(|| viewport(UVec2::ZERO));
+    }
+    for (mut camera, OrthoView(quarter)) in &mut ortho_cameras {
+        camera.is_active = quad.enabled;
+        camera.viewport = Some(viewport(*quarter));
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::LazyLock;
+use crate::rule::{Rule, RuleError};
+
+/// Default catalog shipped with the binary
+const BUILTIN_CATALOG: &str = include_str!("../assets/rules.toml");
+
+static BUILTIN: LazyLock<RuleCatalog> = LazyLock::new(|| {
+    RuleCatalog::parse(BUILTIN_CATALOG).expect("assets/rules.toml is not a valid rule catalog")
+});
+
+static BUILTIN_REGISTRY: LazyLock<RuleRegistry> = LazyLock::new(|| RuleRegistry::from_catalog(RuleCatalog::builtin()));
+
+/// A named rule in a catalog file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(flatten)]
+    pub rule: Rule,
+}
+
+/// Collection of named rules loaded from a TOML catalog
+#[derive(Clone, Debug, Default, Resource, Serialize, Deserialize)]
+pub struct RuleCatalog {
+    #[serde(rename = "rule", default)]
+    pub entries: Vec<CatalogEntry>,
+}
+
+impl RuleCatalog {
+    /// The default catalog embedded from assets/rules.toml
+    pub fn builtin() -> &'static RuleCatalog {
+        &BUILTIN
+    }
+
+    /// Load a catalog from a TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CatalogError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    /// Parse catalog TOML, rejecting rules that can't run (see `Rule::validate`)
+    pub fn parse(text: &str) -> Result<Self, CatalogError> {
+        let catalog: Self = toml::from_str(text).map_err(|e| CatalogError::Parse(e.to_string()))?;
+        for entry in &catalog.entries {
+            entry.rule.validate().map_err(|e| CatalogError::InvalidRule(entry.name.clone(), e))?;
+        }
+        Ok(catalog)
+    }
+
+    /// Write the catalog to a TOML file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CatalogError> {
+        let text = toml::to_string(self).map_err(|e| CatalogError::Parse(e.to_string()))?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Find a rule by name
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.entries.iter().find(|entry| entry.name == name).map(|entry| &entry.rule)
+    }
+
+    /// Names of all rules in catalog order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.name.as_str())
+    }
+
+    /// Add or replace a rule by name
+    pub fn insert(&mut self, entry: CatalogEntry) {
+        match self.entries.iter_mut().find(|existing| existing.name == entry.name) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Add all rules from another catalog, replacing rules with the same name
+    pub fn merge(&mut self, other: RuleCatalog) {
+        for entry in other.entries {
+            self.insert(entry);
+        }
+    }
+}
+
+/// Rules by name, for CLI, config and UI code that refers to rules like "amoeba" or "coral"
+/// Built from the built-in presets plus any loaded catalogs, later catalogs replacing earlier names
+#[derive(Clone, Debug, Default, Resource)]
+pub struct RuleRegistry {
+    rules: BTreeMap<String, Rule>,
+}
+
+impl RuleRegistry {
+    /// Registry of the built-in presets (assets/rules.toml)
+    pub fn builtin() -> &'static RuleRegistry {
+        &BUILTIN_REGISTRY
+    }
+
+    /// Registry of every rule in `catalog`
+    pub fn from_catalog(catalog: &RuleCatalog) -> Self {
+        let mut registry = Self::default();
+        registry.add_catalog(catalog);
+        registry
+    }
+
+    /// Register every rule of a catalog, replacing rules with the same name
+    pub fn add_catalog(&mut self, catalog: &RuleCatalog) {
+        for entry in &catalog.entries {
+            self.insert(entry.name.clone(), entry.rule.clone());
+        }
+    }
+
+    /// Register a rule under `name`, returning the rule it replaced
+    pub fn insert(&mut self, name: impl Into<String>, rule: Rule) -> Option<Rule> {
+        self.rules.insert(name.into(), rule)
+    }
+
+    /// Look up a rule by name
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.rules.get(name)
+    }
+
+    /// Look up a rule by name, with an error listing the available names when it's missing
+    pub fn resolve(&self, name: &str) -> Result<&Rule, String> {
+        self.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.names().collect();
+            format!("Unknown rule '{}'. Available rules: {}", name, names.join(", "))
+        })
+    }
+
+    /// Registered names in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Error returned when loading or saving a catalog fails
+#[derive(Debug)]
+pub enum CatalogError {
+    Io(std::io::Error),
+    /// The file wasn't a valid TOML rule catalog
+    Parse(String),
+    /// The named rule parsed but can't run
+    InvalidRule(String, RuleError),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Io(e) => write!(f, "could not read catalog: {}", e),
+            CatalogError::Parse(e) => write!(f, "invalid catalog: {}", e),
+            CatalogError::InvalidRule(name, e) => write!(f, "invalid rule '{}': {}", name, e),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+impl From<std::io::Error> for CatalogError {
+    fn from(e: std::io::Error) -> Self {
+        CatalogError::Io(e)
+    }
+}
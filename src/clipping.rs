@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResource;
+use serde::{Deserialize, Serialize};
+
+/// Plane cutting away every cell whose center lies on its far side, to look inside solid structures.
+/// Offsets are from the grid center, and the cut applies to instanced cells (not the merged surfaces)
+#[derive(Resource, ExtractResource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlicePlane {
+    pub enabled: bool,
+    /// Direction pointing into the cut-away side
+    pub normal: Vec3,
+    /// Distance of the plane from the grid center along `normal`
+    pub offset: f32,
+}
+
+impl Default for SlicePlane {
+    fn default() -> Self {
+        Self { enabled: false, normal: Vec3::Z, offset: 0.0 }
+    }
+}
+
+impl SlicePlane {
+    /// Normal and offset packed for the cell shaders, all zeros keeping every cell
+    pub fn plane(&self) -> Vec4 {
+        if self.enabled {
+            self.normal.normalize_or(Vec3::Z).extend(self.offset)
+        } else {
+            Vec4::ZERO
+        }
+    }
+}
+
+/// Press X to toggle the slice plane, hold U / J to push it along its normal and I / L to turn it around
+/// the vertical axis, with Ctrl to tilt it up and down instead (Shift would also move the camera down)
+pub fn adjust_slice_plane(time: Res<Time>, keys: Res<ButtonInput<KeyCode>>, mut slice: ResMut<SlicePlane>) {
+    const SPEED: f32 = 20.0;
+    const TURN_SPEED: f32 = 1.0;
+
+    if keys.just_pressed(KeyCode::KeyX) {
+        slice.enabled = !slice.enabled;
+        println!("Slice plane {}", if slice.enabled { "on" } else { "off" });
+    }
+    if !slice.enabled {
+        return;
+    }
+
+    let delta = time.delta_secs();
+    if keys.pressed(KeyCode::KeyU) {
+        slice.offset += SPEED * delta;
+    }
+    if keys.pressed(KeyCode::KeyJ) {
+        slice.offset -= SPEED * delta;
+    }
+
+    let turn = match (keys.pressed(KeyCode::KeyI), keys.pressed(KeyCode::KeyL)) {
+        (true, false) => TURN_SPEED * delta,
+        (false, true) => -TURN_SPEED * delta,
+        _ => return,
+    };
+    let normal = slice.normal.normalize_or(Vec3::Z);
+    let tilt = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    // Tilting turns around the horizontal axis across the normal, which doesn't exist for a level plane
+    let axis = if tilt { normal.cross(Vec3::Y).try_normalize().unwrap_or(Vec3::X) } else { Vec3::Y };
+    slice.normal = Quat::from_axis_angle(axis, turn) * normal;
+}
+
+/// Axis-aligned box only the cells inside of (or with `invert`, outside of) render, to isolate a
+/// sub-structure or look into a dense blob. Like the slice plane, it's centered on the grid center and cuts
+/// instanced cells only
+#[derive(Resource, ExtractResource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipBox {
+    pub enabled: bool,
+    pub center: Vec3,
+    pub size: Vec3,
+    /// Keep the cells outside the box instead, carving it out of the structure
+    pub invert: bool,
+}
+
+impl Default for ClipBox {
+    fn default() -> Self {
+        Self { enabled: false, center: Vec3::ZERO, size: Vec3::splat(32.0), invert: false }
+    }
+}
+
+impl ClipBox {
+    /// Corners packed for the cell shaders, with the w of `min` 1 to keep the inside, -1 to keep the outside
+    /// and 0 when off
+    pub fn corners(&self) -> (Vec4, Vec4) {
+        let mode = match (self.enabled, self.invert) {
+            (false, _) => 0.0,
+            (true, false) => 1.0,
+            (true, true) => -1.0,
+        };
+        let half_size = self.size.abs() * 0.5;
+        ((self.center - half_size).extend(mode), (self.center + half_size).extend(0.0))
+    }
+}
+
+/// Press F to toggle the clip box and Shift+F to keep the cells outside it instead. Hold Page Up / Page Down
+/// to grow or shrink it, the arrow keys to move it along X and Z and Home / End along Y
+pub fn adjust_clip_box(time: Res<Time>, keys: Res<ButtonInput<KeyCode>>, mut clip_box: ResMut<ClipBox>) {
+    const SPEED: f32 = 20.0;
+
+    if keys.just_pressed(KeyCode::KeyF) {
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            clip_box.invert = !clip_box.invert;
+            clip_box.enabled = true;
+        } else {
+            clip_box.enabled = !clip_box.enabled;
+        }
+        match (clip_box.enabled, clip_box.invert) {
+            (false, _) => println!("Clip box off"),
+            (true, false) => println!("Clip box keeping the cells inside"),
+            (true, true) => println!("Clip box keeping the cells outside"),
+        }
+    }
+    if !clip_box.enabled {
+        return;
+    }
+
+    let step = SPEED * time.delta_secs();
+    let axis = |negative: KeyCode, positive: KeyCode| keys.pressed(positive) as i32 as f32 - keys.pressed(negative) as i32 as f32;
+    let grow = axis(KeyCode::PageDown, KeyCode::PageUp);
+    clip_box.size = (clip_box.size + Vec3::splat(grow * step * 2.0)).max(Vec3::ONE);
+    let movement = Vec3::new(
+        axis(KeyCode::ArrowLeft, KeyCode::ArrowRight),
+        axis(KeyCode::End, KeyCode::Home),
+        axis(KeyCode::ArrowUp, KeyCode::ArrowDown),
+    );
+    clip_box.center += movement * step;
+}
+
+/// Pulls the grid apart into octants (or cubic chunks) moved away from the center, exposing the inner layers
+/// of dense structures while keeping their arrangement readable. Moves instanced cells only, after clipping,
+/// and painting still aims at where the cells would be unmoved
+#[derive(Resource, ExtractResource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExplodedView {
+    pub enabled: bool,
+    /// Space opened between neighboring octants or chunks
+    pub gap: f32,
+    /// Edge length of the chunks in cells, 0 for the eight octants around the center
+    pub chunk: f32,
+}
+
+impl Default for ExplodedView {
+    fn default() -> Self {
+        Self { enabled: false, gap: 12.0, chunk: 0.0 }
+    }
+}
+
+impl ExplodedView {
+    /// Gap and chunk size packed for the cell shaders, all zeros when off
+    pub fn params(&self) -> Vec4 {
+        if self.enabled {
+            Vec4::new(self.gap.max(0.0), self.chunk.max(0.0), 0.0, 0.0)
+        } else {
+            Vec4::ZERO
+        }
+    }
+}
+
+/// Press E to toggle the exploded view, hold Q to widen the gaps and Ctrl+Q to close them
+pub fn adjust_exploded_view(time: Res<Time>, keys: Res<ButtonInput<KeyCode>>, mut exploded: ResMut<ExplodedView>) {
+    const SPEED: f32 = 10.0;
+
+    if keys.just_pressed(KeyCode::KeyE) {
+        exploded.enabled = !exploded.enabled;
+        println!("Exploded view {}", if exploded.enabled { "on" } else { "off" });
+    }
+    if !exploded.enabled || !keys.pressed(KeyCode::KeyQ) {
+        return;
+    }
+    let direction = if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) { -1.0 } else { 1.0 };
+    exploded.gap = (exploded.gap + direction * SPEED * time.delta_secs()).max(0.0);
+}
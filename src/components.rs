@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use crate::grid::{Boundaries, Grid};
+use crate::rendering::InstanceMaterialData;
+use crate::rule::MOORE_NEIGHBORS;
+
+/// Disconnected living structures of a grid: living cells touching by a face, edge or corner belong to the
+/// same component, across wrapping edges too
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Components {
+    size: i32,
+    /// Component of each cell in position order (see `Grid::states`) plus one, 0 for dead cells
+    labels: Vec<u32>,
+    /// Living cells of each component, in the order their first cell comes in position order
+    sizes: Vec<usize>,
+}
+
+impl Components {
+    /// Label the grid's living cells
+    pub fn label(grid: &Grid) -> Self {
+        Self::from_states(&grid.states(), grid.size, grid.boundaries())
+    }
+
+    /// Label the living cells of `states` in position order of a grid of `size` with `boundaries`
+    pub fn from_states(states: &[u8], size: i32, boundaries: Boundaries) -> Self {
+        let side = size as usize;
+        let index = |pos: IVec3| pos.x as usize + pos.y as usize * side + pos.z as usize * side * side;
+        let mut labels = vec![0; states.len()];
+        let mut sizes = Vec::new();
+        let mut stack = Vec::new();
+        for start in 0..states.len() {
+            if states[start] == 0 || labels[start] != 0 {
+                continue;
+            }
+            sizes.push(0);
+            let label = sizes.len() as u32;
+            labels[start] = label;
+            stack.push(start);
+            while let Some(current) = stack.pop() {
+                sizes[label as usize - 1] += 1;
+                let pos = IVec3::new((current % side) as i32, (current / side % side) as i32, (current / side / side) as i32);
+                for offset in MOORE_NEIGHBORS {
+                    let next = pos + offset;
+                    let (Some(x), Some(y), Some(z)) =
+                        (boundaries.x.resolve(next.x, size), boundaries.y.resolve(next.y, size), boundaries.z.resolve(next.z, size))
+                    else {
+                        continue;
+                    };
+                    let next = index(IVec3::new(x, y, z));
+                    if states[next] != 0 && labels[next] == 0 {
+                        labels[next] = label;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        Self { size, labels, sizes }
+    }
+
+    /// Number of components
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Living cells of each component, indexed by their label
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// Living cells of the largest component, 0 for an empty grid
+    pub fn largest(&self) -> usize {
+        self.sizes.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Label (index into `sizes`) of the component the cell at `pos` belongs to, None for dead cells
+    pub fn label_at(&self, pos: IVec3) -> Option<usize> {
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(IVec3::splat(self.size)).any() {
+            return None;
+        }
+        let side = self.size as usize;
+        let label = self.labels[pos.x as usize + pos.y as usize * side + pos.z as usize * side * side];
+        label.checked_sub(1).map(|label| label as usize)
+    }
+}
+
+/// Color of the component with id (or label) `id`: golden angle steps keep consecutive ids far apart in hue
+pub fn component_color(id: u32) -> Color {
+    Color::hsl((id as f32 * 137.508) % 360.0, 0.8, 0.55)
+}
+
+/// Components followed from generation to generation, so each structure keeps its id (and color) while it
+/// moves, grows or shrinks. A component takes the id of the previous one it overlaps most: when a structure
+/// splits its largest piece keeps the id and the others get new ones, when several merge the largest
+/// overlap's id wins
+#[derive(Resource, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ComponentTracker {
+    components: Components,
+    /// Id of each component, indexed by label
+    ids: Vec<u32>,
+    next_id: u32,
+}
+
+impl ComponentTracker {
+    /// Follow the tracked components into `components`, the next labeling of the grid
+    pub fn track(&mut self, components: Components) {
+        let mut overlaps = HashMap::new();
+        if components.size == self.components.size {
+            for (&label, &previous) in components.labels.iter().zip(&self.components.labels) {
+                if label != 0 && previous != 0 {
+                    *overlaps.entry((label - 1, previous - 1)).or_insert(0usize) += 1;
+                }
+            }
+        }
+        // Largest overlaps first, ties broken by label so the ids don't depend on hash order
+        let mut overlaps: Vec<_> = overlaps.into_iter().collect();
+        overlaps.sort_unstable_by_key(|&((label, previous), overlap)| (std::cmp::Reverse(overlap), label, previous));
+
+        let mut ids = vec![None; components.count()];
+        let mut taken = vec![false; self.ids.len()];
+        for ((label, previous), _) in overlaps {
+            if ids[label as usize].is_none() && !taken[previous as usize] {
+                ids[label as usize] = Some(self.ids[previous as usize]);
+                taken[previous as usize] = true;
+            }
+        }
+        self.ids = ids
+            .into_iter()
+            .map(|id| {
+                id.unwrap_or_else(|| {
+                    self.next_id += 1;
+                    self.next_id
+                })
+            })
+            .collect();
+        self.components = components;
+    }
+
+    /// Id of the component the cell at `pos` belongs to, None for dead cells
+    pub fn id_at(&self, pos: IVec3) -> Option<u32> {
+        self.components.label_at(pos).map(|label| self.ids[label])
+    }
+}
+
+/// Follow the grid's components whenever it changes and give each living cell its component's color,
+/// over whatever colors the instances were last built with
+pub fn color_components(mut tracker: ResMut<ComponentTracker>, grid: Res<Grid>, mut instance_query: Query<&mut InstanceMaterialData>) {
+    if grid.is_changed() {
+        tracker.track(Components::label(&grid));
+    }
+    let grid_center = Vec3::splat((grid.size - 1) as f32 * 0.5);
+    for mut instances in &mut instance_query {
+        if !(grid.is_changed() || instances.is_changed()) {
+            continue;
+        }
+        // Recoloring doesn't count as a change, so unchanged instances aren't recolored every frame
+        for instance in instances.bypass_change_detection().0.iter_mut() {
+            let pos = (instance.position + grid_center).round().as_ivec3();
+            if let Some(id) = tracker.id_at(pos) {
+                instance.color = component_color(id).to_srgba().to_f32_array();
+            }
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::animation::CellAnimation;
+use crate::camera::QuadView;
+use crate::clipping::{ClipBox, ExplodedView, SlicePlane};
+use crate::culling::FrustumCulling;
+use crate::cycles::CycleDetector;
+use crate::cyclic::CyclicRule;
+use crate::domain::{Domain, Obstacle};
+use crate::editing::Brush;
+use crate::fog::CellFog;
+use crate::grid::{Boundaries, CellColors, CellLayout, UpdateMode, MAX_GRID_SIZE};
+use crate::history::History;
+use crate::hud::{PopulationGraph, SliceViewer};
+use crate::immigration::Immigration;
+use crate::lenia::LeniaRule;
+use crate::lod::CellLod;
+use crate::meshing::CellRenderer;
+use crate::reset::AutoReset;
+use crate::rule::{Rule, RuleError};
+use crate::schedule::RuleSchedule;
+use crate::seeding::{ImageSeed, SeedPattern};
+use crate::shadows::CellShadows;
+use crate::shapes::{CellMeshKind, CustomCellMesh};
+use crate::voxelize::MeshSeed;
+use crate::smoothlife::SmoothLifeRule;
+use crate::species::Ecosystem;
+use crate::zones::{RuleGradient, ZonedRules};
+
+/// Startup settings that can be stored in a RON or JSON file
+#[derive(Clone, Debug, Resource, Serialize, Deserialize)]
+pub struct SimConfig {
+    pub rule: Rule,
+    #[serde(default)]
+    pub colors: CellColors,
+    /// Shape every cell is drawn as, cubes when not set (K cycles through them)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell_mesh: Option<CellMeshKind>,
+    /// Mesh every cell is drawn as instead of `cell_mesh`, once loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_mesh: Option<CustomCellMesh>,
+    /// How cells are drawn, one instance per cell when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renderer: Option<CellRenderer>,
+    /// Fog fading far cells toward the background for a sense of depth, no fog when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fog: Option<CellFog>,
+    /// Light the cells cast shadows from onto each other, flat colors when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadows: Option<CellShadows>,
+    /// Starting slice plane cutting cells away to look inside, off until X is pressed when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slice: Option<SlicePlane>,
+    /// Starting clip box only the cells inside (or outside) of render, off until F is pressed when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clip_box: Option<ClipBox>,
+    /// Starting exploded view pulling the octants of the grid apart, off until E is pressed when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exploded: Option<ExplodedView>,
+    /// Top, front and side orthographic views next to the fly camera, off until F2 is pressed when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quad_view: Option<QuadView>,
+    /// Far cells drawn as points instead of full meshes, off until F3 is pressed when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lod: Option<CellLod>,
+    /// Cells outside the camera views left out of the drawing, a chunk at a time on the CPU or one by one on
+    /// the GPU, off until F4 is pressed when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub culling: Option<FrustumCulling>,
+    /// Cells per side of the grid (and of a continuous engine's field), 64 when not set. G grows and
+    /// shrinks it while running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<i32>,
+    /// Fraction of cells randomly flipped each step (0.0 = off), see `Temperature`
+    #[serde(default)]
+    pub temperature: f32,
+    /// Boundary mode per axis, wrapping everywhere by default
+    #[serde(default)]
+    pub boundaries: Boundaries,
+    /// Memory order of the grid cells, Morton or Halo can be faster on large grids
+    #[serde(default)]
+    pub layout: CellLayout,
+    /// Run simulation steps on all cores, see `Grid::with_parallel`
+    #[serde(default)]
+    pub parallel: bool,
+    /// Order in which cells update within a step, all at once by default
+    #[serde(default)]
+    pub update_mode: UpdateMode,
+    /// Seed for the starting cells and every random choice of the run, random when not set (--seed overrides it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Starting cells of single-rule and zoned grids, a center cluster when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_pattern: Option<SeedPattern>,
+    /// Picture extruded into single-rule and zoned grids as their starting cells, replacing `seed_pattern`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_image: Option<ImageSeed>,
+    /// MagicaVoxel `.vox` model placed in the grid center as starting cells, replacing `seed_pattern`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_vox: Option<PathBuf>,
+    /// glTF or OBJ mesh rasterized into the grid as starting cells once loaded, replacing `seed_pattern`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_mesh: Option<MeshSeed>,
+    /// Generations simulated per rendered frame, 1 when not set, see `StepsPerFrame`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps_per_frame: Option<u32>,
+    /// Container shape the cells grow in, the whole grid when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<Domain>,
+    /// Static geometry the cells grow around
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub obstacles: Vec<Obstacle>,
+    /// Whether obstacle cells count as living neighbors
+    #[serde(default)]
+    pub obstacles_count: bool,
+    /// Random cells injected every few generations to keep slowly dying rules going
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub immigration: Option<Immigration>,
+    /// Generations kept to rewind through, 1000 frames within 128 MB when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<History>,
+    /// How many generations back repeating states are looked for, 1000 when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<CycleDetector>,
+    /// Start the run over once it dies out or settles down, never when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_reset: Option<AutoReset>,
+    /// Brush painting cells where the camera aims, radius 3 and off until B is pressed when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brush: Option<Brush>,
+    /// Chart of the population over recent generations, 240 generations in the top left corner when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub population_graph: Option<PopulationGraph>,
+    /// Cross-section of one grid layer in the top right corner, the middle Z layer and hidden until Tab is
+    /// pressed when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slice_viewer: Option<SliceViewer>,
+    /// Cells growing in when born, shrinking away when they die and blending between generations, popping
+    /// in and out when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<CellAnimation>,
+    /// Multiple species sharing the grid, replacing `rule` in the simulation when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ecosystem: Option<Ecosystem>,
+    /// Cyclic (rock-paper-scissors) automaton run instead of `rule` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cyclic: Option<CyclicRule>,
+    /// Timeline of rules replacing `rule` as generations pass
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<RuleSchedule>,
+    /// Different rules in different regions of the grid, replacing `rule` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zones: Option<ZonedRules>,
+    /// Survival/birth counts of `rule` shifted across the grid, run as zones when `zones` isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<RuleGradient>,
+    /// Continuous 3D Lenia engine run instead of the discrete grid when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lenia: Option<LeniaRule>,
+    /// Continuous SmoothLife engine run instead of the discrete grid when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smoothlife: Option<SmoothLifeRule>,
+}
+
+impl SimConfig {
+    /// Load a config file, picking the format from the extension (.ron or .json)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let config: Self = match Format::from_path(path)? {
+            Format::Ron => ron::from_str(&text).map_err(|e| ConfigError::Parse(e.to_string()))?,
+            Format::Json => serde_json::from_str(&text).map_err(|e| ConfigError::Parse(e.to_string()))?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check every rule in the config can run (see `Rule::validate`), the grid size is in range, zones have
+    /// rules and immigration has a period
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.size.is_some_and(|size| !(1..=MAX_GRID_SIZE).contains(&size)) {
+            return Err(ConfigError::Parse(format!("size needs to be between 1 and {} cells", MAX_GRID_SIZE)));
+        }
+        if self.zones.as_ref().is_some_and(|zones| zones.rules.is_empty()) {
+            return Err(ConfigError::Parse("zones need at least one rule".to_string()));
+        }
+        if self.immigration.as_ref().is_some_and(|immigration| immigration.every == 0) {
+            return Err(ConfigError::Parse("immigration needs `every` of at least 1 generation".to_string()));
+        }
+        let invalid = |place: String| move |e| ConfigError::InvalidRule(place, e);
+        self.rule.validate().map_err(invalid("rule".to_string()))?;
+        for species in self.ecosystem.iter().flat_map(|ecosystem| &ecosystem.species) {
+            species.rule.validate().map_err(invalid(format!("species '{}'", species.name)))?;
+        }
+        for entry in self.schedule.iter().flat_map(|schedule| &schedule.entries) {
+            entry.rule.validate().map_err(invalid(format!("schedule rule at generation {}", entry.generation)))?;
+        }
+        for (zone, rule) in self.zones.iter().flat_map(|zones| &zones.rules).enumerate() {
+            rule.validate().map_err(invalid(format!("zone {} rule", zone)))?;
+        }
+        for (step, rule) in self.gradient.iter().flat_map(|gradient| gradient.zones(&self.rule).rules).enumerate() {
+            rule.validate().map_err(invalid(format!("gradient step {} rule", step)))?;
+        }
+        Ok(())
+    }
+
+    /// Save the config, picking the format from the extension (.ron or .json)
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let text = match Format::from_path(path)? {
+            Format::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|e| ConfigError::Parse(e.to_string()))?,
+            Format::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| ConfigError::Parse(e.to_string()))?,
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Command line options: `[config.ron|config.json] [--rule NAME] [--catalog rules.toml]... [--search out.toml] [--seed N]
+/// [--record run.rec] [--replay run.rec] [--stats stats.csv|stats.json]`
+#[derive(Clone, Debug, Default, Resource)]
+pub struct CliArgs {
+    /// Config file with rule and colors
+    pub config: Option<PathBuf>,
+    /// Catalog rule to run, overriding the config file rule
+    pub rule: Option<String>,
+    /// Extra catalogs merged over the built-in one
+    pub catalogs: Vec<PathBuf>,
+    /// Run a headless rule search writing results to this catalog instead of opening a window
+    pub search: Option<PathBuf>,
+    /// Seed for the starting cells and probabilistic rules, overriding the config file seed
+    pub seed: Option<u64>,
+    /// Record the run's changes to this file, see `Recorder`
+    pub record: Option<PathBuf>,
+    /// Play back a recorded run instead of simulating
+    pub replay: Option<PathBuf>,
+    /// Write each generation's stats to this CSV or JSON file as the run goes, see `StatsExport`
+    pub stats: Option<PathBuf>,
+}
+
+impl CliArgs {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| args.next().ok_or_else(|| format!("{} needs a value", flag));
+            match arg.as_str() {
+                "--rule" => parsed.rule = Some(value("--rule")?),
+                "--catalog" => parsed.catalogs.push(value("--catalog")?.into()),
+                "--search" => parsed.search = Some(value("--search")?.into()),
+                "--record" => parsed.record = Some(value("--record")?.into()),
+                "--replay" => parsed.replay = Some(value("--replay")?.into()),
+                "--stats" => parsed.stats = Some(value("--stats")?.into()),
+                "--seed" => {
+                    let seed = value("--seed")?;
+                    parsed.seed = Some(seed.parse().map_err(|_| format!("invalid seed '{}'", seed))?);
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                path if parsed.config.is_none() => parsed.config = Some(path.into()),
+                extra => return Err(format!("unexpected argument '{}'", extra)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+enum Format {
+    Ron,
+    Json,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ron") => Ok(Format::Ron),
+            Some("json") => Ok(Format::Json),
+            _ => Err(ConfigError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+}
+
+/// Error returned when loading or saving a config file fails
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    /// The file contents didn't match the expected structure
+    Parse(String),
+    /// The extension wasn't .ron or .json
+    UnsupportedFormat(String),
+    /// A rule in the config (named by the first field) parsed but can't run
+    InvalidRule(String, RuleError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "could not read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "invalid config: {}", e),
+            ConfigError::UnsupportedFormat(path) => {
+                write!(f, "unsupported config format '{}': expected .ron or .json", path)
+            }
+            ConfigError::InvalidRule(place, e) => write!(f, "invalid {}: {}", place, e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
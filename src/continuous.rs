@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use rand::Rng;
+use crate::grid::{CellColors, ColorMethod};
+use crate::rendering::{InstanceData, InstanceMaterialData};
+
+/// Cells with a value below this aren't rendered
+pub const VISIBLE_THRESHOLD: f32 = 0.1;
+
+/// Convolution kernel over a ball of offsets, with weights summing to 1
+#[derive(Clone, Debug)]
+pub struct Kernel {
+    offsets: Vec<IVec3>,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+    /// Weight each offset within `radius` by `weight(distance / radius)`, then normalize
+    /// Offsets with a zero weight are dropped so they cost nothing during convolution
+    pub fn from_fn(radius: i32, weight: impl Fn(f32) -> f32) -> Self {
+        let mut offsets = Vec::new();
+        let mut weights = Vec::new();
+        for z in -radius..=radius {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    let offset = IVec3::new(x, y, z);
+                    let distance = offset.as_vec3().length() / radius.max(1) as f32;
+                    let w = if distance <= 1.0 { weight(distance) } else { 0.0 };
+                    if w > 0.0 {
+                        offsets.push(offset);
+                        weights.push(w);
+                    }
+                }
+            }
+        }
+
+        let total: f32 = weights.iter().sum();
+        if total > 0.0 {
+            weights.iter_mut().for_each(|w| *w /= total);
+        }
+        Self { offsets, weights }
+    }
+
+    pub fn offsets(&self) -> &[IVec3] {
+        &self.offsets
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+}
+
+/// Grid of continuous cell values in 0..1 with toroidal boundaries, used by continuous engines
+/// (see `Simulation`) instead of the discrete `Grid`
+#[derive(Resource)]
+pub struct ContinuousGrid {
+    values: Vec<f32>, // Flat 1D array, same layout as Grid
+    pub size: i32,
+    generation: u64,
+}
+
+impl ContinuousGrid {
+    pub fn new(size: i32) -> Self {
+        Self {
+            values: vec![0.0; (size * size * size) as usize],
+            size,
+            generation: 0,
+        }
+    }
+
+    /// Number of generations simulated since the grid was created
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Convert 1D index to 3D position
+    #[inline]
+    fn index_to_pos(&self, index: usize) -> IVec3 {
+        let size = self.size;
+        IVec3::new(
+            (index as i32) % size,
+            (index as i32) / size % size,
+            (index as i32) / size / size
+        )
+    }
+
+    /// Weighted sum of each cell's surroundings
+    /// Costs one multiply-add per cell and kernel offset, so keep kernel radii small
+    pub fn convolve(&self, kernel: &Kernel) -> Vec<f32> {
+        let size = self.size as usize;
+        let mut out = vec![0.0; self.values.len()];
+
+        for (&offset, &weight) in kernel.offsets().iter().zip(kernel.weights()) {
+            let shift = offset.rem_euclid(IVec3::splat(self.size));
+            let (dx, dy, dz) = (shift.x as usize, shift.y as usize, shift.z as usize);
+            for z in 0..size {
+                let source_z = (z + dz) % size;
+                for y in 0..size {
+                    let source_y = (y + dy) % size;
+                    let row = &mut out[(z * size + y) * size..][..size];
+                    let source = &self.values[(source_z * size + source_y) * size..][..size];
+                    // Rows wrap around along X: the output row reads the source row rotated by dx
+                    let (head, tail) = row.split_at_mut(size - dx);
+                    for (out, &value) in head.iter_mut().zip(&source[dx..]) {
+                        *out += weight * value;
+                    }
+                    for (out, &value) in tail.iter_mut().zip(&source[..dx]) {
+                        *out += weight * value;
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Advance one generation, replacing each value with `update(index, value)` clamped to 0..1
+    pub fn update(&mut self, update: impl Fn(usize, f32) -> f32) {
+        for (index, value) in self.values.iter_mut().enumerate() {
+            *value = update(index, *value).clamp(0.0, 1.0);
+        }
+        self.generation += 1;
+    }
+
+    /// Fill a ball of `radius` around the center with random values
+    pub fn spawn_center_blob(&mut self, radius: i32, rng: &mut impl Rng) {
+        let center = IVec3::splat(self.size / 2);
+        for index in 0..self.values.len() {
+            if self.index_to_pos(index).distance_squared(center) <= radius * radius {
+                self.values[index] = rng.random();
+            }
+        }
+    }
+
+    /// Sum of all cell values
+    pub fn mass(&self) -> f32 {
+        self.values.iter().sum()
+    }
+
+    /// Count cells at or above VISIBLE_THRESHOLD
+    pub fn cell_count(&self) -> usize {
+        self.values.iter().filter(|&&value| value >= VISIBLE_THRESHOLD).count()
+    }
+
+    /// Build instance data for rendering, one cube per visible cell scaled by its value
+    /// Translucent colors keep full-size cubes and fade their alpha by value instead
+    pub fn build_instances(&self, colors: &CellColors) -> Vec<InstanceData> {
+        let grid_center = Vec3::splat((self.size - 1) as f32 * 0.5);
+        let max_distance = grid_center.length();
+        let mut instance_data = Vec::new();
+
+        for (index, &value) in self.values.iter().enumerate() {
+            if value < VISIBLE_THRESHOLD {
+                continue;
+            }
+            let position = self.index_to_pos(index).as_vec3() - grid_center;
+            let t = match colors.method {
+                ColorMethod::DistToCenter => position.length() / max_distance,
+                ColorMethod::Single | ColorMethod::Rainbow => 1.0,
+                // There are no discrete states, neighbors or species, so color by value
+                ColorMethod::StateLerp | ColorMethod::Neighbor | ColorMethod::Species | ColorMethod::Component | ColorMethod::Activity | ColorMethod::Palette | ColorMethod::Velocity => value,
+            };
+
+            let mut color = match colors.method {
+                ColorMethod::Rainbow => colors.rainbow(position, grid_center),
+                _ => colors.lerp_color(t),
+            }
+            .to_srgba();
+            if colors.translucent {
+                color.alpha = value * colors.opacity;
+            }
+
+            instance_data.push(InstanceData {
+                position,
+                scale: if colors.translucent { 1.0 } else { value },
+                color: color.to_f32_array(),
+                glow: 0.0,
+            });
+        }
+
+        instance_data
+    }
+}
+
+/// A continuous engine (Lenia, SmoothLife) that advances a ContinuousGrid, picked at startup
+pub trait Simulation: Resource {
+    /// Advance the grid one generation
+    fn step(&self, grid: &mut ContinuousGrid);
+
+    /// Kernel offsets summed per cell each step, shown in the performance printout
+    fn kernel_size(&self) -> usize;
+}
+
+/// Continuous counterpart of `simulate_step`, rendering through the same instance buffer
+pub fn simulate_continuous<S: Simulation>(
+    mut grid: ResMut<ContinuousGrid>,
+    simulation: Res<S>,
+    colors: Res<CellColors>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+    time: Res<Time>,
+    mut last_update: Local<f32>,
+) {
+    // Adjust this to control simulation speed (in seconds between updates)
+    const UPDATE_INTERVAL: f32 = 0.05;
+
+    if UPDATE_INTERVAL > 0.0 && time.elapsed_secs() - *last_update < UPDATE_INTERVAL {
+        return;
+    }
+    *last_update = time.elapsed_secs();
+
+    let step_start = std::time::Instant::now();
+    simulation.step(&mut grid);
+    let step_time = step_start.elapsed();
+
+    if let Ok(mut instances) = instance_query.single_mut() {
+        instances.0 = grid.build_instances(&colors);
+    }
+
+    println!(
+        "Generation {}: {:6.2}ms step ({} kernel offsets), mass {:.1}, {} visible cells",
+        grid.generation(),
+        step_time.as_secs_f64() * 1000.0,
+        simulation.kernel_size(),
+        grid.mass(),
+        grid.cell_count(),
+    );
+}
@@ -0,0 +1,111 @@
+use bevy::camera::primitives::{Aabb, CascadesFrusta, Frustum};
+use bevy::math::Affine3A;
+use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResource;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::clipping::ExplodedView;
+use crate::rendering::{BlendAlpha, InstanceData, InstanceMaterialData};
+
+/// Leave the cells outside every camera's view out of the instances uploaded each frame, testing them a
+/// chunk at a time. Zoomed into a corner of a big grid, most cells never reach the screen. Cells that can
+/// cast shadows into the views are kept too, and nothing is culled while the exploded view moves cells around
+#[derive(Resource, ExtractResource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrustumCulling {
+    /// On at startup, F4 toggles it
+    pub enabled: bool,
+    /// Edge length of the chunks tested against the views, in cells
+    pub chunk: i32,
+    /// Cull each cell in a compute pass instead, which compacts the visible ones on the GPU and draws them
+    /// indirectly. The instances are then only uploaded when they change, not as the camera moves.
+    /// Translucent cells keep being culled on the CPU, as the compaction doesn't keep their back to front
+    /// order. Shift+F4 switches between the two
+    pub gpu: bool,
+}
+
+impl Default for FrustumCulling {
+    fn default() -> Self {
+        Self { enabled: false, chunk: 16, gpu: false }
+    }
+}
+
+/// Instances of the visible chunks, uploaded instead of the entity's `InstanceMaterialData` while culling is on
+#[derive(Component, Default, Deref)]
+pub struct CulledInstances(pub Vec<InstanceData>);
+
+/// Press F4 to toggle frustum culling. Every frame after anything that rebuilt, animated or sorted the
+/// instances, the ones in chunks inside any active camera's view or shadow cascade are kept for the upload.
+/// Press Shift+F4 to hand the opaque cells over to the GPU culling pass
+pub fn cull_instances(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut culling: ResMut<FrustumCulling>,
+    exploded: Option<Res<ExplodedView>>,
+    cameras: Query<(&Camera, &Frustum)>,
+    lights: Query<(&DirectionalLight, &CascadesFrusta)>,
+    mut instance_query: Query<(Entity, &mut InstanceMaterialData, Option<&mut CulledInstances>, Has<BlendAlpha>)>,
+) {
+    if keys.just_pressed(KeyCode::F4) {
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            culling.gpu = !culling.gpu;
+            culling.enabled = true;
+        } else {
+            culling.enabled = !culling.enabled;
+        }
+        match (culling.enabled, culling.gpu) {
+            (false, _) => println!("Frustum culling off"),
+            (true, false) => println!("Frustum culling on the CPU"),
+            (true, true) => println!("Frustum culling on the GPU"),
+        }
+    }
+    let off = !culling.enabled || exploded.is_some_and(|exploded| exploded.enabled);
+    // Opaque cells left to the GPU culling pass are uploaded whole
+    let on_gpu = |blend_alpha: bool| culling.gpu && !blend_alpha;
+    for (entity, mut instances, culled, blend_alpha) in &mut instance_query {
+        if culled.is_some() && (off || on_gpu(blend_alpha)) {
+            commands.entity(entity).remove::<CulledInstances>();
+            // The whole instances are only extracted again once they count as changed
+            instances.set_changed();
+        }
+    }
+    if off {
+        return;
+    }
+
+    let frusta: Vec<&Frustum> = cameras.iter().filter(|(camera, _)| camera.is_active).map(|(_, frustum)| frustum).collect();
+    // Shadow casters sit anywhere between the light and the cascade, so like Bevy's own shadow culling the
+    // near planes of the cascades aren't tested
+    let cascades: Vec<&Frustum> = lights
+        .iter()
+        .filter(|(light, _)| light.shadows_enabled)
+        .flat_map(|(_, cascades)| cascades.frusta.values().flatten())
+        .collect();
+    let chunk = culling.chunk.max(1) as f32;
+    for (entity, instances, culled, blend_alpha) in &mut instance_query {
+        if on_gpu(blend_alpha) {
+            continue;
+        }
+        // Chunks are tested once each, with a cell of margin for cells bigger than one unit
+        let mut visible_chunks = HashMap::new();
+        let mut visible = |instance: &&InstanceData| {
+            let key = (instance.position / chunk).floor().as_ivec3();
+            *visible_chunks.entry(key).or_insert_with(|| {
+                let min = key.as_vec3() * chunk - 1.0;
+                let aabb = Aabb::from_min_max(min, min + chunk + 2.0);
+                frusta.iter().any(|frustum| frustum.intersects_obb(&aabb, &Affine3A::IDENTITY, true, true))
+                    || cascades.iter().any(|frustum| frustum.intersects_obb(&aabb, &Affine3A::IDENTITY, false, true))
+            })
+        };
+        match culled {
+            Some(mut culled) => {
+                culled.0.clear();
+                culled.0.extend(instances.iter().filter(&mut visible).copied());
+            }
+            None => {
+                let culled = CulledInstances(instances.iter().filter(&mut visible).copied().collect());
+                commands.entity(entity).insert(culled);
+            }
+        }
+    }
+}
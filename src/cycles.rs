@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use crate::grid::Grid;
+
+/// Repeating stretch of a run: the grid is back in a state it had `period` generations before
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cycle {
+    /// Generations between repeats, 1 for a still life
+    pub period: u64,
+    /// Generation at which the repeated state first appeared
+    pub start: u64,
+}
+
+/// How a run behaves in the end, judged from its repeating states
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Behavior {
+    /// No living cells left
+    Extinct,
+    /// Living cells that no longer change
+    StillLife,
+    /// Back in the same state every `period` generations
+    Oscillator { period: u64 },
+    /// No state repeated within the detector's window
+    Chaotic,
+}
+
+impl fmt::Display for Behavior {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Behavior::Extinct => write!(f, "extinct"),
+            Behavior::StillLife => write!(f, "a still life"),
+            Behavior::Oscillator { period } => write!(f, "an oscillator of period {}", period),
+            Behavior::Chaotic => write!(f, "chaotic"),
+        }
+    }
+}
+
+/// Sent when a run's behavior is first classified or changes, e.g. for automated rule exploration
+#[derive(Message, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BehaviorChanged {
+    pub behavior: Behavior,
+    pub generation: u64,
+}
+
+/// Watches the grid's state hash (see `Grid::state_hash`) each generation for a state seen before,
+/// which means the run entered a cycle. Hashes can collide, so a reported cycle is very likely, not certain
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CycleDetector {
+    /// Generations of hashes kept, the longest period that can be found; a run that repeats nothing
+    /// for this long counts as chaotic
+    pub window: usize,
+    /// Generation each recent hash was last seen at
+    #[serde(skip)]
+    seen: HashMap<u64, u64>,
+    /// Recent hashes and their generation, oldest first
+    #[serde(skip)]
+    recent: VecDeque<(u64, u64)>,
+    #[serde(skip)]
+    cycle: Option<Cycle>,
+    /// Generations observed since a state last repeated
+    #[serde(skip)]
+    unrepeated: usize,
+    #[serde(skip)]
+    behavior: Option<Behavior>,
+}
+
+impl Default for CycleDetector {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl CycleDetector {
+    pub fn new(window: usize) -> Self {
+        Self { window, seen: HashMap::new(), recent: VecDeque::new(), cycle: None, unrepeated: 0, behavior: None }
+    }
+
+    /// Cycle the run is in, None while its states keep changing
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    /// How the run behaves, None until it's been watched long enough to tell
+    pub fn behavior(&self) -> Option<Behavior> {
+        self.behavior
+    }
+
+    /// Forget every hash seen
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.recent.clear();
+        self.cycle = None;
+        self.unrepeated = 0;
+        self.behavior = None;
+    }
+
+    /// Record the grid's state at its generation, returning the run's behavior when it's newly classified
+    /// or changed. Going back in generations, e.g. loading a snapshot, starts over
+    pub fn observe(&mut self, grid: &Grid) -> Option<Behavior> {
+        let generation = grid.generation();
+        match self.recent.back() {
+            Some(&(_, last)) if generation <= last => self.clear(),
+            _ => {}
+        }
+
+        let hash = grid.state_hash();
+        let found = self.seen.insert(hash, generation).map(|start| Cycle { period: generation - start, start });
+        self.recent.push_back((hash, generation));
+        while self.recent.len() > self.window.max(1) {
+            if let Some((oldest, seen_at)) = self.recent.pop_front() {
+                if self.seen.get(&oldest) == Some(&seen_at) {
+                    self.seen.remove(&oldest);
+                }
+            }
+        }
+
+        let previous = self.cycle;
+        self.cycle = match (found, previous) {
+            // Each repeat of the same cycle is found again, it keeps the generation it started at
+            (Some(found), Some(cycle)) if found.period == cycle.period => Some(cycle),
+            (found, _) => found,
+        };
+        self.unrepeated = if self.cycle.is_some() { 0 } else { self.unrepeated + 1 };
+
+        // Dead cells don't add to the hash, so only an empty grid hashes to 0 (barring collisions)
+        let behavior = match self.cycle {
+            _ if hash == 0 => Some(Behavior::Extinct),
+            Some(Cycle { period: 1, .. }) => Some(Behavior::StillLife),
+            Some(Cycle { period, .. }) => Some(Behavior::Oscillator { period }),
+            None if self.unrepeated >= self.window.max(1) => Some(Behavior::Chaotic),
+            None => None,
+        };
+        let changed = behavior.filter(|_| behavior != self.behavior);
+        self.behavior = behavior;
+        changed
+    }
+}
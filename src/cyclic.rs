@@ -0,0 +1,37 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use crate::rule::NeighborMethod;
+
+/// Cyclic cellular automaton (rock-paper-scissors): every cell holds one of `states` states and is
+/// eaten by the next state (s + 1, wrapping to 0) once at least `threshold` neighbors hold it
+/// Started from random noise this forms expanding spiral waves
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CyclicRule {
+    /// Number of states in the cycle (at least 2)
+    pub states: u8,
+    /// Successor neighbors needed to advance a cell
+    pub threshold: u16,
+    /// Neighborhood type (weighted kernels sum successor weights)
+    pub neighbor_method: NeighborMethod,
+}
+
+impl CyclicRule {
+    pub fn new(states: u8, threshold: u16, neighbor_method: NeighborMethod) -> Self {
+        Self {
+            states: states.max(2),
+            threshold,
+            neighbor_method,
+        }
+    }
+
+    /// 3D spiral waves from random noise (6 states, 5 successors, Moore)
+    pub fn spirals() -> Self {
+        Self::new(6, 5, NeighborMethod::Moore)
+    }
+
+    /// The state that eats `state`
+    #[inline]
+    pub fn successor(&self, state: u8) -> u8 {
+        ((state as u16 + 1) % self.states.max(1) as u16) as u8
+    }
+}
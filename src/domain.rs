@@ -0,0 +1,93 @@
+use bevy::math::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+use crate::zones::Axis;
+
+/// Container shape cells can live in, cells outside stay dead and are never counted (see `Grid::with_domain`)
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Domain {
+    /// Ball around the grid center, radius a fraction of half the grid size
+    Sphere { radius: f32 },
+    /// Cylinder along `axis` through the grid center, radius a fraction of half the grid size
+    Cylinder { axis: Axis, radius: f32 },
+    /// Exactly these cell positions
+    Voxels(BTreeSet<(i32, i32, i32)>),
+}
+
+impl Domain {
+    /// Whether the cell at `pos` in a grid of `size` cells per side is inside the domain
+    pub fn contains(&self, pos: IVec3, size: i32) -> bool {
+        let center = Vec3::splat((size - 1) as f32 * 0.5);
+        let half = size as f32 * 0.5;
+        let offset = pos.as_vec3() - center;
+        match self {
+            Domain::Sphere { radius } => offset.length() <= radius * half,
+            Domain::Cylinder { axis, radius } => {
+                let across = match axis {
+                    Axis::X => offset.with_x(0.0),
+                    Axis::Y => offset.with_y(0.0),
+                    Axis::Z => offset.with_z(0.0),
+                };
+                across.length() <= radius * half
+            }
+            Domain::Voxels(voxels) => voxels.contains(&(pos.x, pos.y, pos.z)),
+        }
+    }
+}
+
+/// Static geometry the automaton grows around, see `Grid::place_obstacle`
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Obstacle {
+    /// One cell thick wall across `axis`, `position` a fraction (0..1) of the grid size
+    Plane { axis: Axis, position: f32 },
+    /// Solid box between two corner cells (inclusive)
+    Box { min: (i32, i32, i32), max: (i32, i32, i32) },
+    /// Exactly these cell positions, e.g. from `load_voxels`
+    Voxels(BTreeSet<(i32, i32, i32)>),
+}
+
+impl Obstacle {
+    /// Whether the cell at `pos` in a grid of `size` cells per side is part of the obstacle
+    pub fn contains(&self, pos: IVec3, size: i32) -> bool {
+        match self {
+            Obstacle::Plane { axis, position } => {
+                let layer = ((position * size as f32) as i32).clamp(0, size - 1);
+                let coord = match axis {
+                    Axis::X => pos.x,
+                    Axis::Y => pos.y,
+                    Axis::Z => pos.z,
+                };
+                coord == layer
+            }
+            Obstacle::Box { min, max } => {
+                let (min, max) = (IVec3::from(*min), IVec3::from(*max));
+                pos.cmpge(min).all() && pos.cmple(max).all()
+            }
+            Obstacle::Voxels(voxels) => voxels.contains(&(pos.x, pos.y, pos.z)),
+        }
+    }
+}
+
+/// Read voxel positions from a text file with one `x y z` triple per line
+/// Blank lines and lines starting with # are skipped
+pub fn load_voxels(path: impl AsRef<Path>) -> io::Result<BTreeSet<(i32, i32, i32)>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut voxels = BTreeSet::new();
+    for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let coords: Vec<i32> = line
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", number, e)))?;
+        let [x, y, z] = coords[..] else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: expected `x y z`", number)));
+        };
+        voxels.insert((x, y, z));
+    }
+    Ok(voxels)
+}
@@ -0,0 +1,503 @@
+use bevy::prelude::*;
+use bevy::input::mouse::MouseWheel;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::camera::FlyCamera;
+use crate::cyclic::CyclicRule;
+use crate::grid::{max_neighbors, max_state, CellColors, Grid, RayHit};
+use crate::rendering::InstanceMaterialData;
+use crate::rule::Rule;
+use crate::species::Ecosystem;
+use crate::zones::{Axis, ZonedRules};
+
+/// One interactive edit: every cell it changed with its state before and after
+#[derive(Clone, Debug, Default)]
+pub struct Edit {
+    /// What the edit was, e.g. "paint" or "paste", for messages
+    pub name: String,
+    cells: Vec<(IVec3, u8, u8)>,
+    /// Position of each cell in `cells`, so edits that touch a cell twice keep its first state
+    index: HashMap<IVec3, usize>,
+}
+
+impl Edit {
+    fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..default() }
+    }
+
+    fn add(&mut self, changed: Vec<(IVec3, u8, u8)>) {
+        for (pos, before, after) in changed {
+            match self.index.get(&pos) {
+                Some(&i) => self.cells[i].2 = after,
+                None => {
+                    self.index.insert(pos, self.cells.len());
+                    self.cells.push((pos, before, after));
+                }
+            }
+        }
+    }
+
+    /// Cells whose state the edit changed
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+/// Undo and redo stacks of interactive edits (painting, erasing, pasting), kept apart from the rewind
+/// history of the simulation (see `History`), so sculpting mistakes can be taken back while it runs
+#[derive(Resource)]
+pub struct EditHistory {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// Most edits kept to undo, the oldest are forgotten
+    pub limit: usize,
+    /// Whether the last edit is still open to `extend`
+    open: bool,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl EditHistory {
+    pub fn new(limit: usize) -> Self {
+        Self { undo: Vec::new(), redo: Vec::new(), limit, open: false }
+    }
+
+    /// Set `cells` in the grid as one undoable edit (see `Grid::edit_cells`), clearing what can be redone
+    /// Returns how many cells changed, edits that change nothing aren't kept
+    pub fn apply(&mut self, grid: &mut Grid, rules: &[Rule], name: impl Into<String>, cells: impl IntoIterator<Item = (IVec3, u8)>) -> usize {
+        self.open = false;
+        self.extend(grid, rules, name, cells)
+    }
+
+    /// Like `apply`, but adds to the last edit while it's open, so a whole brush stroke undoes at once
+    /// Call `finish` when the stroke ends
+    pub fn extend(&mut self, grid: &mut Grid, rules: &[Rule], name: impl Into<String>, cells: impl IntoIterator<Item = (IVec3, u8)>) -> usize {
+        let changed = grid.edit_cells(rules, cells);
+        let count = changed.len();
+        if count == 0 {
+            return 0;
+        }
+        if !self.open || self.undo.is_empty() {
+            self.undo.push(Edit::new(name));
+            if self.undo.len() > self.limit.max(1) {
+                self.undo.remove(0);
+            }
+            self.open = true;
+        }
+        self.undo.last_mut().expect("an edit was just pushed").add(changed);
+        self.redo.clear();
+        count
+    }
+
+    /// Close the last edit, the next `extend` starts a new one
+    pub fn finish(&mut self) {
+        self.open = false;
+    }
+
+    /// Put the cells of the last edit back, returning it
+    pub fn undo(&mut self, grid: &mut Grid, rules: &[Rule]) -> Option<&Edit> {
+        self.open = false;
+        let edit = self.undo.pop()?;
+        grid.edit_cells(rules, edit.cells.iter().map(|&(pos, before, _)| (pos, before)));
+        self.redo.push(edit);
+        self.redo.last()
+    }
+
+    /// Make the last undone edit again, returning it
+    pub fn redo(&mut self, grid: &mut Grid, rules: &[Rule]) -> Option<&Edit> {
+        self.open = false;
+        let edit = self.redo.pop()?;
+        grid.edit_cells(rules, edit.cells.iter().map(|&(pos, _, after)| (pos, after)));
+        self.undo.push(edit);
+        self.undo.last()
+    }
+}
+
+/// Press Ctrl+Z to undo the last edit, Ctrl+Y or Ctrl+Shift+Z to redo it
+/// Multi-species and cyclic grids keep their own neighbor caches, so they aren't edited
+#[allow(clippy::too_many_arguments)]
+pub fn undo_edits(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut edits: ResMut<EditHistory>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    colors: Res<CellColors>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+) {
+    if ecosystem.is_some() || cyclic.is_some() {
+        return;
+    }
+    let control = keys.pressed(KeyCode::ControlLeft) || keys.pressed(KeyCode::ControlRight);
+    let shift = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    let redo = keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ));
+    if !control || !(redo || keys.just_pressed(KeyCode::KeyZ)) {
+        return;
+    }
+
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let edit = if redo { edits.redo(&mut grid, rules) } else { edits.undo(&mut grid, rules) };
+    match edit {
+        Some(edit) => println!("{} {} ({} cells)", if redo { "Redid" } else { "Undid" }, edit.name, edit.len()),
+        None => println!("Nothing to {}", if redo { "redo" } else { "undo" }),
+    }
+    // The simulation may be paused, so show the result right away
+    if let Ok(mut instances) = instance_query.single_mut() {
+        instances.0 = grid.build_instances(&colors, max_state(rules, None, None), max_neighbors(rules, None, None));
+    }
+}
+
+/// Spherical brush that births or erases cells where the camera aims while a mouse button is held
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Brush {
+    /// Whether paint mode is on, B toggles it
+    pub enabled: bool,
+    /// Radius in cells, the mouse wheel changes it
+    pub radius: f32,
+    /// Distance of the brush center in front of the camera, Ctrl + mouse wheel changes it
+    pub reach: f32,
+    /// State painted cells are born at, clamped to their zone rule's states
+    pub state: u8,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self { enabled: false, radius: 3.0, reach: 40.0, state: u8::MAX }
+    }
+}
+
+impl Brush {
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_reach(mut self, reach: f32) -> Self {
+        self.reach = reach;
+        self
+    }
+
+    /// Brush center in grid positions for a camera at `transform`, cells render centered on the origin
+    pub fn center(&self, transform: &Transform, grid: &Grid) -> Vec3 {
+        transform.translation + transform.forward() * self.reach + Vec3::splat((grid.size - 1) as f32 * 0.5)
+    }
+
+    /// Positions of the cells within the brush around `center`
+    pub fn cells(&self, center: Vec3) -> impl Iterator<Item = IVec3> {
+        let radius = self.radius.max(0.0);
+        let min = (center - radius).ceil().as_ivec3();
+        let max = (center + radius).floor().as_ivec3();
+        (min.z..=max.z)
+            .flat_map(move |z| (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z))))
+            .filter(move |pos| pos.as_vec3().distance_squared(center) <= radius * radius)
+    }
+}
+
+/// Press B to toggle paint mode, then hold the left mouse button to birth cells under the brush and the
+/// right one to erase them; each stroke is one edit to undo. The mouse wheel sizes the brush, with Ctrl
+/// it moves the brush nearer or farther
+#[allow(clippy::too_many_arguments)]
+pub fn paint_cells(
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut wheel: MessageReader<MouseWheel>,
+    mut brush: ResMut<Brush>,
+    mut edits: ResMut<EditHistory>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    colors: Res<CellColors>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    camera: Query<&Transform, With<FlyCamera>>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+    mut gizmos: Gizmos,
+) {
+    if keys.just_pressed(KeyCode::KeyB) {
+        brush.enabled = !brush.enabled;
+        println!("Paint mode {}", if brush.enabled { "on" } else { "off" });
+    }
+    let scroll: f32 = wheel.read().map(|event| event.y.signum()).sum();
+    if !brush.enabled || ecosystem.is_some() || cyclic.is_some() {
+        return;
+    }
+    if scroll != 0.0 {
+        if keys.pressed(KeyCode::ControlLeft) || keys.pressed(KeyCode::ControlRight) {
+            brush.reach = (brush.reach + scroll * 2.0).max(1.0);
+        } else {
+            brush.radius = (brush.radius + scroll * 0.5).clamp(0.5, 32.0);
+        }
+    }
+    let Ok(transform) = camera.single() else { return };
+    let center = brush.center(transform, &grid);
+    let painting = buttons.pressed(MouseButton::Left);
+    let erasing = buttons.pressed(MouseButton::Right);
+    let color = if erasing { colors.death_color } else { colors.birth_color };
+    gizmos.sphere(Isometry3d::from_translation(transform.translation + transform.forward() * brush.reach), brush.radius, color);
+
+    if buttons.just_released(MouseButton::Left) || buttons.just_released(MouseButton::Right) {
+        edits.finish();
+    }
+    if painting == erasing {
+        return;
+    }
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let (name, state) = if painting { ("paint", brush.state) } else { ("erase", 0) };
+    let cells = brush.cells(center).map(|pos| (pos, state));
+    if edits.extend(&mut grid, rules, name, cells) > 0 {
+        if let Ok(mut instances) = instance_query.single_mut() {
+            instances.0 = grid.build_instances(&colors, max_state(rules, None, None), max_neighbors(rules, None, None));
+        }
+    }
+}
+
+/// Cell the camera aims at (the screen center, the cursor is grabbed), in grid positions
+pub fn aimed_cell(transform: &Transform, grid: &Grid) -> Option<RayHit> {
+    let origin = transform.translation + Vec3::splat((grid.size - 1) as f32 * 0.5);
+    grid.raycast(origin, *transform.forward(), f32::INFINITY)
+}
+
+/// Outside paint mode, outline the living cell the camera aims at; left click removes it, right click
+/// places a cell against the face it was hit on and middle click prints what the cell holds
+#[allow(clippy::too_many_arguments)]
+pub fn pick_cells(
+    buttons: Res<ButtonInput<MouseButton>>,
+    brush: Res<Brush>,
+    mut edits: ResMut<EditHistory>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    colors: Res<CellColors>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    camera: Query<&Transform, With<FlyCamera>>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+    mut gizmos: Gizmos,
+) {
+    if brush.enabled {
+        return;
+    }
+    let Ok(transform) = camera.single() else { return };
+    let Some(hit) = aimed_cell(transform, &grid) else { return };
+    let grid_center = Vec3::splat((grid.size - 1) as f32 * 0.5);
+    gizmos.cuboid(Transform::from_translation(hit.pos.as_vec3() - grid_center).with_scale(Vec3::splat(1.1)), colors.birth_color);
+
+    if buttons.just_pressed(MouseButton::Middle) {
+        if let Some(info) = grid.cell_info(hit.pos) {
+            println!("Cell {} at distance {:.1}: {:?}", hit.pos, hit.distance, info);
+        }
+        return;
+    }
+    let edit = match (buttons.just_pressed(MouseButton::Left), buttons.just_pressed(MouseButton::Right)) {
+        (true, false) => ("remove", hit.pos, 0),
+        (false, true) => match hit.before {
+            Some(before) => ("place", before, u8::MAX),
+            None => return,
+        },
+        _ => return,
+    };
+    // Species and cyclic grids keep their own neighbor caches, so their cells can only be inspected
+    if ecosystem.is_some() || cyclic.is_some() {
+        return;
+    }
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let (name, pos, state) = edit;
+    if edits.apply(&mut grid, rules, name, [(pos, state)]) > 0 {
+        if let Ok(mut instances) = instance_query.single_mut() {
+            instances.0 = grid.build_instances(&colors, max_state(rules, None, None), max_neighbors(rules, None, None));
+        }
+    }
+}
+
+/// Box of cell states copied out of a grid to paste elsewhere, e.g. to build composite seeds from fragments
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub struct Clipboard {
+    size: IVec3,
+    /// States in position order (x fastest)
+    states: Vec<u8>,
+}
+
+impl Clipboard {
+    /// Copy the box of cells from `min` to `max` (inclusive), cells past the grid edges copy as empty
+    pub fn copy(grid: &Grid, min: IVec3, max: IVec3) -> Self {
+        let (min, max) = (min.min(max), min.max(max));
+        let size = max - min + 1;
+        let mut states = Vec::with_capacity(size.element_product() as usize);
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    states.push(grid.get(IVec3::new(x, y, z)).unwrap_or(0));
+                }
+            }
+        }
+        Self { size, states }
+    }
+
+    /// Cells per side of the box
+    pub fn size(&self) -> IVec3 {
+        self.size
+    }
+
+    /// Living cells in the box
+    pub fn living(&self) -> usize {
+        self.states.iter().filter(|&&state| state > 0).count()
+    }
+
+    /// State at `pos` inside the box, 0 outside it
+    pub fn get(&self, pos: IVec3) -> u8 {
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(self.size).any() {
+            return 0;
+        }
+        self.states[(pos.x + self.size.x * (pos.y + self.size.y * pos.z)) as usize]
+    }
+
+    /// Turned `turns` quarter turns about `axis`, counterclockwise looking down the axis
+    pub fn rotated(&self, axis: Axis, turns: i32) -> Self {
+        let (_, size) = axis.turn(IVec3::ZERO, self.size, turns);
+        let mut rotated = Self { size, states: vec![0; self.states.len()] };
+        for (pos, state) in self.cells() {
+            let (pos, _) = axis.turn(pos, self.size, turns);
+            rotated.states[(pos.x + size.x * (pos.y + size.y * pos.z)) as usize] = state;
+        }
+        rotated
+    }
+
+    /// Mirrored along `axis`
+    pub fn mirrored(&self, axis: Axis) -> Self {
+        let mut mirrored = Self { size: self.size, states: vec![0; self.states.len()] };
+        for (mut pos, state) in self.cells() {
+            pos[axis.index()] = self.size[axis.index()] - 1 - pos[axis.index()];
+            mirrored.states[(pos.x + self.size.x * (pos.y + self.size.y * pos.z)) as usize] = state;
+        }
+        mirrored
+    }
+
+    /// Every cell of the box with its position inside it
+    fn cells(&self) -> impl Iterator<Item = (IVec3, u8)> + '_ {
+        let size = self.size;
+        self.states
+            .iter()
+            .enumerate()
+            .map(move |(i, &state)| (IVec3::new(i as i32 % size.x, i as i32 / size.x % size.y, i as i32 / size.x / size.y), state))
+    }
+
+    /// Cells to paste with the box's min corner at `corner`, for `Grid::edit_cells` or `EditHistory::apply`
+    /// Empty cells leave the grid as it is unless `overwrite`, which stamps the whole box
+    pub fn placed(&self, corner: IVec3, overwrite: bool) -> impl Iterator<Item = (IVec3, u8)> + '_ {
+        self.cells().filter(move |&(_, state)| overwrite || state > 0).map(move |(pos, state)| (corner + pos, state))
+    }
+}
+
+/// Cell copies and pastes center on: the aimed cell outside paint mode, the brush center in it (or when
+/// nothing is aimed at)
+fn edit_anchor(brush: &Brush, transform: &Transform, grid: &Grid) -> IVec3 {
+    match aimed_cell(transform, grid) {
+        Some(hit) if !brush.enabled => hit.pos,
+        _ => brush.center(transform, grid).round().as_ivec3(),
+    }
+}
+
+/// Press C to copy the cube of cells within the brush radius around the aimed cell, P to paste its living
+/// cells centered there (Shift+P stamps the whole cube, empty cells too). R turns the copy a quarter turn
+/// about the vertical axis, with Shift about x and with Ctrl about z
+#[allow(clippy::too_many_arguments)]
+pub fn copy_paste(
+    keys: Res<ButtonInput<KeyCode>>,
+    brush: Res<Brush>,
+    mut clipboard: ResMut<Clipboard>,
+    mut edits: ResMut<EditHistory>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    colors: Res<CellColors>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    camera: Query<&Transform, With<FlyCamera>>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+) {
+    let shift = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    let control = keys.pressed(KeyCode::ControlLeft) || keys.pressed(KeyCode::ControlRight);
+    if keys.just_pressed(KeyCode::KeyR) {
+        let axis = if shift { Axis::X } else if control { Axis::Z } else { Axis::Y };
+        *clipboard = clipboard.rotated(axis, 1);
+        println!("Turned the copy about {:?}, now {} cells", axis, clipboard.size());
+        return;
+    }
+    let (copy, paste) = (keys.just_pressed(KeyCode::KeyC), keys.just_pressed(KeyCode::KeyP));
+    if !(copy || paste) {
+        return;
+    }
+    let Ok(transform) = camera.single() else { return };
+    let anchor = edit_anchor(&brush, transform, &grid);
+
+    if copy {
+        let half = IVec3::splat(brush.radius.round() as i32);
+        *clipboard = Clipboard::copy(&grid, anchor - half, anchor + half);
+        println!("Copied {} living cells around {}", clipboard.living(), anchor);
+        return;
+    }
+    // Species and cyclic grids keep their own neighbor caches, so nothing is pasted into them
+    if ecosystem.is_some() || cyclic.is_some() || clipboard.size() == IVec3::ZERO {
+        return;
+    }
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let changed = edits.apply(&mut grid, rules, "paste", clipboard.placed(anchor - clipboard.size() / 2, shift));
+    println!("Pasted {} cells around {}", changed, anchor);
+    if changed > 0 {
+        if let Ok(mut instances) = instance_query.single_mut() {
+            instances.0 = grid.build_instances(&colors, max_state(rules, None, None), max_neighbors(rules, None, None));
+        }
+    }
+}
+
+/// Press O to turn the cube of cells within the brush radius around the aimed cell a quarter turn about
+/// the vertical axis, Shift+O to mirror it along x; with Ctrl the whole grid turns or mirrors instead
+#[allow(clippy::too_many_arguments)]
+pub fn transform_cells(
+    keys: Res<ButtonInput<KeyCode>>,
+    brush: Res<Brush>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    colors: Res<CellColors>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    camera: Query<&Transform, With<FlyCamera>>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+) {
+    if !keys.just_pressed(KeyCode::KeyO) || ecosystem.is_some() || cyclic.is_some() {
+        return;
+    }
+    let mirror = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    let whole = keys.pressed(KeyCode::ControlLeft) || keys.pressed(KeyCode::ControlRight);
+    let (min, max) = if whole {
+        (IVec3::ZERO, IVec3::splat(grid.size - 1))
+    } else {
+        let Ok(transform) = camera.single() else { return };
+        let anchor = edit_anchor(&brush, transform, &grid);
+        let half = IVec3::splat(brush.radius.round() as i32);
+        (anchor - half, anchor + half)
+    };
+
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    if mirror {
+        grid.mirror_region(rules, min, max, Axis::X);
+    } else {
+        grid.rotate_region(rules, min, max, Axis::Y, 1);
+    }
+    println!("{} {}", if mirror { "Mirrored" } else { "Turned" }, if whole { "the grid" } else { "the region" });
+    if let Ok(mut instances) = instance_query.single_mut() {
+        instances.0 = grid.build_instances(&colors, max_state(rules, None, None), max_neighbors(rules, None, None));
+    }
+}
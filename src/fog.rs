@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::grid::hex_color;
+
+/// Distance fog fading cells toward a color the further they are from the camera, so the far layers of a
+/// big grid recede instead of crowding the near ones
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CellFog {
+    /// Distance from the camera where cells start to fade
+    pub start: f32,
+    /// Distance where cells have faded into the fog completely
+    pub end: f32,
+    /// Color cells fade toward, best matching the background
+    #[serde(with = "hex_color")]
+    pub color: Color,
+}
+
+impl Default for CellFog {
+    fn default() -> Self {
+        Self { start: 80.0, end: 220.0, color: ClearColor::default().0 }
+    }
+}
+
+impl CellFog {
+    /// Fog component for the camera, which the cell shader and the merged surfaces both read
+    pub fn distance_fog(&self) -> DistanceFog {
+        DistanceFog { color: self.color, falloff: FogFalloff::Linear { start: self.start, end: self.end }, ..default() }
+    }
+}
@@ -1,11 +1,32 @@
 use bevy::prelude::*;
-use bevy::math::IVec3;
-use rand::Rng;
-use crate::rule::Rule;
+use bevy::math::{I64Vec3, IVec3};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::catalog::RuleCatalog;
+use crate::clipping::{ClipBox, ExplodedView, SlicePlane};
+use crate::components::{component_color, Components};
+use crate::cycles::{BehaviorChanged, CycleDetector};
+use crate::cyclic::CyclicRule;
+use crate::domain::{Domain, Obstacle};
+use crate::immigration::Region;
+use crate::lod::CellLod;
+use crate::packed::BitGrid;
+use crate::pattern::pattern_offsets;
+use crate::rule::{is_face_offset, AgeAction, NeighborMethod, Rule, VON_NEUMANN_NEIGHBORS};
+use crate::seeding::SeedPattern;
+use crate::shapes::{CellMeshKind, CustomCellMeshDrawn};
+use crate::species::Ecosystem;
+use crate::stats::SimStats;
+use crate::zones::{zone_rule, Axis, ZoneLayout, ZonedRules};
 use crate::rendering::InstanceMaterialData;
+use std::io;
+use std::path::Path;
 
 /// Color interpolation method for cells
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum ColorMethod {
     /// Interpolate color based on cell state (dead→alive)
     StateLerp,
@@ -15,116 +36,2510 @@ pub enum ColorMethod {
     Neighbor,
     /// Single color for all cells
     Single,
+    /// Each species' color from `species_colors`, fading towards death_color as cells decay
+    Species,
+    /// A distinct hue for each disconnected structure that it keeps as it moves (see `ComponentTracker`)
+    Component,
+    /// From death_color for cells that rarely changed to birth_color for the most active ones (see `Grid::activity`)
+    Activity,
+    /// Hue by position along `hue_axis` (or distance from the grid center), through `hue_range`
+    Rainbow,
+    /// Each state's own color from `palette`, without blending
+    Palette,
+    /// From birth_color for cells that just changed to death_color for those stable for a while (see
+    /// `cooling`), lighting up wavefronts
+    Velocity,
+}
+
+impl ColorMethod {
+    /// Whether the method colors cells by how often or how recently they changed, which the grid only
+    /// counts `with_activity`
+    pub fn reads_activity(self) -> bool {
+        matches!(self, ColorMethod::Activity | ColorMethod::Velocity)
+    }
+}
+
+/// How a living cell's cube size follows its state, from full size for newborns (the max state) towards
+/// zero as it decays
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum ScaleCurve {
+    /// Every living cell at full size
+    #[default]
+    Uniform,
+    /// Size in proportion to the state
+    Linear,
+    /// Size in proportion to the state raised to a power: below 1 keeps dying cells large for longer,
+    /// above 1 shrinks them quickly
+    Power(f32),
+    /// Smoothstep of the state, shrinking slowly at first and last
+    Smooth,
+}
+
+impl ScaleCurve {
+    /// Cube size of a cell in `state` when `max_state` is the newborn state
+    pub fn scale(self, state: u8, max_state: u8) -> f32 {
+        let t = (state as f32 / max_state.max(1) as f32).clamp(0.0, 1.0);
+        match self {
+            ScaleCurve::Uniform => 1.0,
+            ScaleCurve::Linear => t,
+            ScaleCurve::Power(exponent) => t.powf(exponent),
+            ScaleCurve::Smooth => t * t * (3.0 - 2.0 * t),
+        }
+    }
 }
 
 /// Cell data with persistent neighbor count for fast simulation
 #[derive(Clone, Copy)]
 struct Cell {
     value: u8,      // Current state (0 = dead, 1..max_state = alive)
-    neighbors: u8,  // Cached count of neighbors at max_state
+    species: u8,    // Species index in multi-species grids (kept after death), 0 otherwise
+    zone: u8,       // Zone index in zoned grids (see ZonedRules), 0 otherwise
+    ticks: u8,      // Generations spent in the current decaying state (see Rule::decay)
+    age: u16,       // Generations since the cell was born (see Rule::max_age)
+    neighbors: u16, // Cached (weighted) count of counted neighbors (see Rule::counts_as_neighbor), up to MAX_NEIGHBORS
+    faces: u8,      // Cached count of counted face-adjacent neighbors, only kept for two-shell rules (see Rule::shells)
+    obstacle: bool, // Static obstacle that is never born or dies (see Grid::place_obstacle)
 }
 
 impl Cell {
+    /// Empty cell outside any zone, species or obstacle
+    const DEAD: Cell = Cell { value: 0, species: 0, zone: 0, ticks: 0, age: 0, neighbors: 0, faces: 0, obstacle: false };
+
     fn is_dead(self) -> bool {
         self.value == 0
     }
+
+    /// Bring the cell to life in `state`, starting a new life with age 0
+    #[inline]
+    fn spawn(&mut self, state: u8) {
+        self.value = state;
+        self.ticks = 0;
+        self.age = 0;
+    }
+
+    /// Age a living cell by one generation, returning what its rule's age limit does to it
+    #[inline]
+    fn grow_older(&mut self, rule: &Rule) -> Option<AgeAction> {
+        self.age = self.age.saturating_add(1);
+        rule.expired(self.age)
+    }
+
+    /// Move one step down the rule's decay table, lingering in states that last several generations
+    #[inline]
+    fn decay(&mut self, rule: &Rule) {
+        // Cells that just left their birth state have no ticks yet and move on right away
+        if self.ticks > 0 && self.ticks < rule.decay_duration(self.value) {
+            self.ticks += 1;
+            return;
+        }
+        self.value = rule.next_decay_state(self.value);
+        self.ticks = 1;
+    }
+
+    /// Apply `rule` for one generation using the cached neighbor counts (or neighbor `pattern` for
+    /// non-totalistic rules), returning whether the cell started (true) or stopped (false) counting as a neighbor
+    #[inline]
+    fn step(&mut self, rule: &Rule, pattern: Option<u32>, rng: &mut (impl Rng + ?Sized)) -> Option<bool> {
+        let birth_state = rule.birth_state();
+        let counted = rule.counts_as_neighbor(self.value);
+        if self.is_dead() {
+            // Dead cell - check birth rule using CACHED neighbor count
+            let born = match pattern {
+                Some(pattern) => rule.should_birth_pattern(pattern),
+                None => rule.should_birth_counts(self.neighbors, self.faces),
+            };
+            if born && roll(rng, rule.birth_probability(self.neighbors)) {
+                self.spawn(birth_state);
+            }
+        } else {
+            // Living cell
+            // Past its max age a cell dies or decays regardless of its neighbors
+            let expired = self.grow_older(rule);
+            // Only cells in the birth state (max_state by default) can survive if they meet the survival rule
+            let survives = expired.is_none()
+                && self.value == birth_state
+                && match pattern {
+                    Some(pattern) => rule.should_survive_pattern(pattern),
+                    None => rule.should_survive_counts(self.neighbors, self.faces),
+                }
+                && roll(rng, rule.survival_probability(self.neighbors));
+            if expired == Some(AgeAction::Die) {
+                self.value = 0;
+            } else if !survives {
+                self.decay(rule);
+            }
+        }
+
+        // Cells that started or stopped counting as a neighbor affect neighbor counts
+        match (counted, rule.counts_as_neighbor(self.value)) {
+            (false, true) => Some(true),
+            (true, false) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Per cell, indexed like the cells, how often and when it changed state (see `Grid::with_activity`)
+/// Kept apart from the cells, so grids not colored by activity don't carry them
+#[derive(Clone)]
+struct Activity {
+    flips: Vec<u16>,   // Times the cell's state changed during the run, saturating (see Grid::activity)
+    changed: Vec<u32>, // Generation of the cell's last change of state, truncated (see Grid::since_change)
+}
+
+impl Activity {
+    /// `len` cells that last changed in `generation`
+    fn new(len: usize, generation: u64) -> Self {
+        Self { flips: vec![0; len], changed: vec![generation as u32; len] }
+    }
+
+    /// Count a change of state of the cell at `index` in `generation` towards the heatmap
+    #[inline]
+    fn flip(&mut self, index: usize, generation: u64) {
+        self.flips[index] = self.flips[index].saturating_add(1);
+        self.changed[index] = generation as u32;
+    }
+
+    /// Generations from the last change of state of the cell at `index` to `generation`
+    #[inline]
+    fn since_change(&self, index: usize, generation: u64) -> u64 {
+        // Rewound grids can hold changes from later generations
+        (generation as u32).saturating_sub(self.changed[index]) as u64
+    }
+}
+
+/// Cells that started counting as a neighbor (spawns) or stopped (deaths) during a step
+/// With the default rule these are the cells that entered or left max_state
+#[derive(Default, Debug)]
+pub struct StepChanges {
+    pub spawns: Vec<usize>,
+    pub deaths: Vec<usize>,
+}
+
+/// First living cell or obstacle along a ray, see `Grid::raycast`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RayHit {
+    pub pos: IVec3,
+    /// Empty cell the ray crossed just before, where a cell placed against the hit one goes
+    pub before: Option<IVec3>,
+    /// Distance along the ray to where it enters the hit cell
+    pub distance: f32,
+}
+
+/// What a single cell holds, for inspecting it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CellInfo {
+    pub state: u8,
+    pub age: u16,
+    pub neighbors: u16,
+    pub zone: u8,
+    pub species: u8,
+    pub obstacle: bool,
+}
+
+/// Seeded RNG for probabilistic rules, so a run with the same seed replays identically
+#[derive(Resource)]
+pub struct SimRng(pub StdRng);
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+/// Roll a rule probability, leaving the RNG untouched for certain outcomes so deterministic rules
+/// don't consume random numbers
+#[inline]
+fn roll(rng: &mut (impl Rng + ?Sized), probability: f32) -> bool {
+    probability >= 1.0 || (probability > 0.0 && rng.random::<f32>() < probability)
+}
+
+/// What lies past the grid edge along an axis
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Boundary {
+    /// The grid wraps around to the opposite edge (toroidal)
+    #[default]
+    Wrap,
+    /// Permanently dead cells that are never counted
+    Dead,
+}
+
+impl Boundary {
+    /// Coordinate `coord` moved into 0..size, None when it falls off a dead edge
+    #[inline]
+    pub(crate) fn resolve(self, coord: i32, size: i32) -> Option<i32> {
+        match self {
+            Boundary::Wrap => Some(coord.rem_euclid(size)),
+            Boundary::Dead => (0..size).contains(&coord).then_some(coord),
+        }
+    }
+}
+
+/// Boundary mode of each grid axis, e.g. wrap in X/Z and dead in Y to simulate a slab
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Boundaries {
+    pub x: Boundary,
+    pub y: Boundary,
+    pub z: Boundary,
+}
+
+impl Boundaries {
+    /// Wrap along every axis, the default toroidal grid
+    pub fn wrap() -> Self {
+        Self::default()
+    }
+
+    /// Dead along every axis, a closed box
+    pub fn closed() -> Self {
+        Self { x: Boundary::Dead, y: Boundary::Dead, z: Boundary::Dead }
+    }
+
+    /// Dead along `axis` and wrapping along the others: an endless slab
+    pub fn slab(axis: Axis) -> Self {
+        Self::wrap().with(axis, Boundary::Dead)
+    }
+
+    /// Wrapping along `axis` only: an endless tube (with a square cross-section)
+    pub fn tube(axis: Axis) -> Self {
+        Self::closed().with(axis, Boundary::Wrap)
+    }
+
+    /// Set the boundary of one axis
+    pub fn with(mut self, axis: Axis, boundary: Boundary) -> Self {
+        match axis {
+            Axis::X => self.x = boundary,
+            Axis::Y => self.y = boundary,
+            Axis::Z => self.z = boundary,
+        }
+        self
+    }
+}
+
+/// Memory order of the cells in the grid's flat array
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum CellLayout {
+    /// Rows along X: index = x + y * size + z * size²
+    #[default]
+    Linear,
+    /// Z-order curve with interleaved x/y/z bits, so cells close in 3D mostly share cache lines
+    /// Only used for power-of-two grid sizes up to 1024
+    Morton,
+    /// Linear rows padded with `width` layers of ghost cells on every side, so neighbors within
+    /// `width` are a fixed index offset away; counts landing in the halo are folded back once per step
+    Halo { width: i32 },
+}
+
+/// Order in which cells take their turn within a step
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum UpdateMode {
+    /// Every cell updates at once from the previous generation's states
+    #[default]
+    Synchronous,
+    /// Cubes of `block` cells per side update one after another in a fixed order, the cells of a cube
+    /// at once; later cubes already see the new states of earlier ones
+    BlockSequential { block: i32 },
+    /// Cells update one at a time in a new random order each step (asynchronous updating), each seeing
+    /// the current states of its neighbors
+    RandomSequential,
+}
+
+impl CellLayout {
+    /// Whether a grid of `size` cells per side can use this layout
+    pub fn supports(self, size: i32) -> bool {
+        match self {
+            CellLayout::Linear => true,
+            CellLayout::Morton => size > 0 && size <= 1024 && (size as u32).is_power_of_two(),
+            CellLayout::Halo { width } => width > 0,
+        }
+    }
+
+    /// Ghost cell layers on each side of the grid
+    fn halo_width(self) -> i32 {
+        match self {
+            CellLayout::Halo { width } => width,
+            _ => 0,
+        }
+    }
+}
+
+/// Spread the low 10 bits of `v` out to every third bit (bit i moves to bit 3i)
+#[inline]
+fn spread_bits(v: u32) -> u32 {
+    let mut x = v & 0x3ff;
+    x = (x | (x << 16)) & 0x0300_00ff;
+    x = (x | (x << 8)) & 0x0300_f00f;
+    x = (x | (x << 4)) & 0x030c_30c3;
+    x = (x | (x << 2)) & 0x0924_9249;
+    x
+}
+
+/// Inverse of `spread_bits`: gather every third bit back into the low 10 bits
+#[inline]
+fn compact_bits(v: u32) -> u32 {
+    let mut x = v & 0x0924_9249;
+    x = (x | (x >> 2)) & 0x030c_30c3;
+    x = (x | (x >> 4)) & 0x0300_f00f;
+    x = (x | (x >> 8)) & 0x0300_00ff;
+    x = (x | (x >> 16)) & 0x0000_03ff;
+    x
+}
+
+/// Bits of the x, y and z coordinates in a Morton index
+const MORTON_X: u32 = 0x0924_9249;
+const MORTON_Y: u32 = MORTON_X << 1;
+const MORTON_Z: u32 = MORTON_X << 2;
+
+/// Add two Morton indices per axis (dilated integer addition), wrapping each axis at the grid size
+/// `last` is the highest index of the power-of-two grid, cutting off carries past the top bit
+#[inline]
+fn morton_add(a: u32, b: u32, last: u32) -> u32 {
+    let x = (a | !MORTON_X).wrapping_add(b & MORTON_X) & MORTON_X;
+    let y = (a | !MORTON_Y).wrapping_add(b & MORTON_Y) & MORTON_Y;
+    let z = (a | !MORTON_Z).wrapping_add(b & MORTON_Z) & MORTON_Z;
+    (x | y | z) & last
+}
+
+/// Fraction of cells randomly born or killed each step regardless of the rule (0.0 = off)
+/// Re-energizes patterns that have frozen or died down
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct Temperature(pub f32);
+
+/// Generations simulated per rendered frame, so slow rules can be fast-forwarded
+/// Rule schedules and immigration are checked once per frame, between the batches of generations
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct StepsPerFrame(pub u32);
+
+impl Default for StepsPerFrame {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// Cells per chunk of the flat array, phase 1 skips chunks that are fully dead and dormant
+const CHUNK_LEN: usize = 4096;
+
+/// Neighbor updates per cell in a step above which phase 2 counts every cell from scratch (see `count_packed`)
+const PACKED_UPDATES_PER_CELL: usize = 1;
+
+/// Most cells per side a grid is started with or grown to, 512³ cells take over a gigabyte
+pub const MAX_GRID_SIZE: i32 = 512;
+
+/// First bytes of a grid snapshot file, followed by the format version (see `Grid::save`)
+const SNAPSHOT_MAGIC: [u8; 4] = *b"C3DG";
+
+/// Version 1 stored the rule's notation only, version 2 the whole rule and version 3 every cell's species,
+/// decay ticks and age along with its state (see `Grid::encode_cells`)
+const SNAPSHOT_VERSION: u8 = 3;
+
+/// Run-length encode `values`, each run as the value and its LEB128 length, runs of empty space are long
+fn encode_runs(values: impl IntoIterator<Item = u8>, bytes: &mut Vec<u8>) {
+    let mut write_run = |value: u8, mut length: usize| {
+        bytes.push(value);
+        while length >= 0x80 {
+            bytes.push(length as u8 | 0x80);
+            length >>= 7;
+        }
+        bytes.push(length as u8);
+    };
+    let mut run: Option<(u8, usize)> = None;
+    for value in values {
+        run = match run {
+            Some((current, length)) if current == value => Some((current, length + 1)),
+            Some((current, length)) => {
+                write_run(current, length);
+                Some((value, 1))
+            }
+            None => Some((value, 1)),
+        };
+    }
+    if let Some((value, length)) = run {
+        write_run(value, length);
+    }
+}
+
+/// States of `total` cells from `Grid::encode_states`
+pub(crate) fn decode_states(bytes: &[u8], total: usize) -> io::Result<Vec<u8>> {
+    decode_runs(&mut bytes.iter(), total)
+}
+
+/// `total` values from `encode_runs`, leaving `bytes` after the last run
+fn decode_runs<'a>(bytes: &mut impl Iterator<Item = &'a u8>, total: usize) -> io::Result<Vec<u8>> {
+    let mut states = Vec::with_capacity(total);
+    while states.len() < total {
+        let value = *bytes.next().ok_or_else(|| invalid_data("truncated cell states"))?;
+        let mut length = 0usize;
+        for shift in (0..35).step_by(7) {
+            let byte = *bytes.next().ok_or_else(|| invalid_data("truncated cell states"))?;
+            length |= ((byte & 0x7F) as usize) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        if states.len() + length > total {
+            return Err(invalid_data("too many cell states"));
+        }
+        states.resize(states.len() + length, value);
+    }
+    Ok(states)
+}
+
+/// Every cell's state, species, decay ticks and age in position order, decoded from `Grid::encode_cells`
+struct SavedCells {
+    states: Vec<u8>,
+    species: Vec<u8>,
+    ticks: Vec<u8>,
+    ages: Vec<u16>,
+}
+
+impl SavedCells {
+    fn decode(bytes: &[u8], total: usize) -> io::Result<Self> {
+        let mut bytes = bytes.iter();
+        let states = decode_runs(&mut bytes, total)?;
+        let species = decode_runs(&mut bytes, total)?;
+        let ticks = decode_runs(&mut bytes, total)?;
+        let low = decode_runs(&mut bytes, total)?;
+        let high = decode_runs(&mut bytes, total)?;
+        let ages = low.iter().zip(&high).map(|(&low, &high)| u16::from_le_bytes([low, high])).collect();
+        Ok(Self { states, species, ticks, ages })
+    }
+}
+
+/// Random-looking key of a cell of `species` in `state` for the Zobrist hash (splitmix64 of all three),
+/// 0 for dead cells so the hash only depends on the living ones
+#[inline]
+fn cell_key(index: usize, species: u8, state: u8) -> u64 {
+    if state == 0 {
+        return 0;
+    }
+    let mut key = ((index as u64) << 16 | (species as u64) << 8 | state as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    key = (key ^ (key >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    key = (key ^ (key >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    key ^ (key >> 31)
+}
+
+/// Running tally of where the living cells are, updated with each birth and death
+#[derive(Clone, Debug, Default)]
+struct Occupancy {
+    count: usize,
+    /// Sum of the living cells' positions
+    sum: I64Vec3,
+    /// Living cells in each x, y and z plane, whose first and last non-empty ones bound the cells
+    planes: [Vec<u32>; 3],
+}
+
+impl Occupancy {
+    fn new(size: i32) -> Self {
+        let planes = vec![0; size.max(0) as usize];
+        Self { count: 0, sum: I64Vec3::ZERO, planes: [planes.clone(), planes.clone(), planes] }
+    }
+
+    fn add(&mut self, pos: IVec3) {
+        self.count += 1;
+        self.sum += pos.as_i64vec3();
+        for (planes, coord) in self.planes.iter_mut().zip(pos.to_array()) {
+            planes[coord as usize] += 1;
+        }
+    }
+
+    fn remove(&mut self, pos: IVec3) {
+        self.count -= 1;
+        self.sum -= pos.as_i64vec3();
+        for (planes, coord) in self.planes.iter_mut().zip(pos.to_array()) {
+            planes[coord as usize] -= 1;
+        }
+    }
+
+    fn centroid(&self) -> Option<Vec3> {
+        (self.count > 0).then(|| self.sum.as_dvec3().as_vec3() / self.count as f32)
+    }
+
+    fn bounds(&self) -> Option<(IVec3, IVec3)> {
+        let [x, y, z] = self.planes.each_ref().map(|planes| {
+            let first = planes.iter().position(|&count| count > 0)?;
+            let last = planes.iter().rposition(|&count| count > 0)?;
+            Some((first as i32, last as i32))
+        });
+        let ((min_x, max_x), (min_y, max_y), (min_z, max_z)) = (x?, y?, z?);
+        Some((IVec3::new(min_x, min_y, min_z), IVec3::new(max_x, max_y, max_z)))
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
 }
 
 #[derive(Resource)]
 pub struct Grid {
     cells: Vec<Cell>,  // Flat 1D array for cache efficiency
     pub size: i32,     // Grid size in each dimension
+    species_count: usize,       // Number of species in multi-species grids, 0 for single-rule grids
+    species_neighbors: Vec<u16>, // Per cell, the cached neighbor count of each species (cell * species_count + species)
+    generation: u64,   // Number of steps simulated so far
+    boundaries: Boundaries, // What neighbors past each edge are
+    mask: Option<Vec<bool>>, // Per cell, whether it's inside the simulated domain (None = everywhere)
+    obstacles_count: bool, // Whether obstacle cells count as living neighbors
+    layout: CellLayout, // Order of the cells in `cells`
+    spread: Vec<u32>,   // Morton layout: spread_bits of each coordinate, looked up instead of recomputed
+    layout_offsets: Vec<(NeighborMethod, Option<Vec<u32>>)>, // Each neighborhood's offsets as index deltas, None when the layout can't use them
+    halo_links: Vec<(u32, Option<u32>)>, // Halo layout: each ghost cell and the cell it stands in for (None past a dead boundary)
+    halo_dirty: bool,   // Halo layout: whether ghost cells hold counts not yet folded back
+    parallel: bool,     // Whether steps run on all cores (see `with_parallel`)
+    awake: Vec<bool>,   // Per chunk of CHUNK_LEN cells, whether the next phase 1 has to visit it
+    packed_counting: bool, // Whether dense steps and recounts may count 64 cells at a time (see `with_packed_counting`)
+    update_mode: UpdateMode, // Order in which cells take their turn within a step
+    active_cells: usize, // Cells visited by the last phase 1
+    hash: u64,          // Zobrist hash of every cell's state (see `state_hash`), kept up to date as cells change
+    occupancy: Occupancy, // Where the living cells are (see `centroid`), kept up to date like `hash`
+    activity: Option<Activity>, // Per cell change counts and times, only while colors read them (see `with_activity`)
 }
 
 impl Grid {
     pub fn new(size: i32) -> Self {
         let total = (size * size * size) as usize;
         Self {
-            cells: vec![Cell { value: 0, neighbors: 0 }; total],
+            cells: vec![Cell::DEAD; total],
             size,
+            species_count: 0,
+            species_neighbors: Vec::new(),
+            generation: 0,
+            boundaries: Boundaries::wrap(),
+            mask: None,
+            obstacles_count: false,
+            layout: CellLayout::Linear,
+            spread: Vec::new(),
+            layout_offsets: Vec::new(),
+            halo_links: Vec::new(),
+            halo_dirty: false,
+            parallel: false,
+            awake: vec![true; total.div_ceil(CHUNK_LEN)],
+            packed_counting: true,
+            update_mode: UpdateMode::Synchronous,
+            active_cells: total,
+            hash: 0,
+            occupancy: Occupancy::new(size),
+            activity: None,
+        }
+    }
+
+    /// Store cells in `layout` order, moving existing cells (and their neighbor counts) into place
+    /// Grids whose size the layout doesn't support stay linear, see `CellLayout::supports`
+    pub fn with_layout(mut self, layout: CellLayout) -> Self {
+        if layout == self.layout || !layout.supports(self.size) {
+            return self;
+        }
+        // Old index and position of every cell, leaving out ghost cells
+        let positions: Vec<(usize, IVec3)> = (0..self.cells.len())
+            .map(|index| (index, self.index_to_pos(index)))
+            .filter(|&(_, pos)| self.in_bounds(pos))
+            .collect();
+        let domain = self.has_domain();
+        let old_cells = std::mem::take(&mut self.cells);
+        let old_mask = self.mask.take();
+        let old_species = std::mem::take(&mut self.species_neighbors);
+        let old_activity = self.activity.take();
+
+        self.layout = layout;
+        self.spread = match layout {
+            CellLayout::Morton => (0..self.size as u32).map(spread_bits).collect(),
+            _ => Vec::new(),
+        };
+        self.layout_offsets.clear();
+        let side = self.side() as usize;
+        let total = side * side * side;
+        let count = self.species_count;
+        self.cells = vec![Cell::DEAD; total];
+        self.species_neighbors = vec![0; total * count];
+        self.awake = vec![true; total.div_ceil(CHUNK_LEN)];
+        let mut activity = old_activity.as_ref().map(|_| Activity::new(total, self.generation));
+        // Ghost cells are closed, so everything that respects the domain skips them
+        let mut mask = (domain || layout.halo_width() > 0).then(|| vec![false; total]);
+
+        for (old, pos) in positions {
+            let new = self.pos_to_index(pos);
+            self.cells[new] = old_cells[old];
+            if let Some(mask) = &mut mask {
+                mask[new] = old_mask.as_ref().is_none_or(|old_mask| old_mask[old]);
+            }
+            if count > 0 {
+                self.species_neighbors[new * count..(new + 1) * count].copy_from_slice(&old_species[old * count..(old + 1) * count]);
+            }
+            if let (Some(activity), Some(old_activity)) = (&mut activity, &old_activity) {
+                activity.flips[new] = old_activity.flips[old];
+                activity.changed[new] = old_activity.changed[old];
+            }
+        }
+        self.mask = mask;
+        self.activity = activity;
+        self.link_halo();
+        self.retrack();
+        self
+    }
+
+    pub fn layout(&self) -> CellLayout {
+        self.layout
+    }
+
+    /// Run both phases of `update_states`/`apply_changes` on all cores, splitting the grid into z slabs
+    /// Probabilistic rules draw different (but still seeded) numbers than a sequential grid
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    pub fn is_parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// Let recounts and steps where many cells change count neighbors 64 cells at a time from bit-packed
+    /// rows (see `packed::BitGrid`) instead of updating each changed cell's neighbors. On by default,
+    /// used for unweighted, single-shell neighborhoods on Linear or Morton grids whose size is a multiple of 64
+    pub fn with_packed_counting(mut self, packed: bool) -> Self {
+        self.packed_counting = packed;
+        self
+    }
+
+    /// Update cells in blocks or one at a time instead of all at once (see `UpdateMode`)
+    /// Sequential modes apply to single-rule and zoned grids and run on one core, species and cyclic
+    /// grids stay synchronous
+    pub fn with_update_mode(mut self, mode: UpdateMode) -> Self {
+        self.update_mode = mode;
+        self
+    }
+
+    /// Order in which cells take their turn within a step
+    pub fn update_mode(&self) -> UpdateMode {
+        self.update_mode
+    }
+
+    /// Count how often and when each cell changes state, for `activity` and `since_change` (and so
+    /// ColorMethod::Activity and Velocity, see `ColorMethod::reads_activity`). Off by default, the counts
+    /// take 6 bytes per cell
+    pub fn with_activity(mut self, track: bool) -> Self {
+        self.activity = track.then(|| Activity::new(self.cells.len(), self.generation));
+        self
+    }
+
+    /// Only simulate cells where `open(pos)` is true, all others stay dead and are never counted
+    /// Set this before spawning cells so the cached neighbor counts match
+    pub fn with_mask(mut self, open: impl Fn(IVec3) -> bool) -> Self {
+        let mask = (0..self.cells.len())
+            .map(|index| {
+                let pos = self.index_to_pos(index);
+                self.in_bounds(pos) && open(pos)
+            })
+            .collect();
+        self.mask = Some(mask);
+        self.wake_all();
+        self
+    }
+
+    /// Only simulate cells inside `domain`, see `with_mask`
+    pub fn with_domain(self, domain: &Domain) -> Self {
+        let size = self.size;
+        self.with_mask(|pos| domain.contains(pos, size))
+    }
+
+    /// Change the grid to `new_size` cells per side
+    /// With `preserve` the old cells (including obstacles and the domain mask) are copied into the center
+    /// of the new volume, cropping them when shrinking; a masked grid keeps its container, so the new margin
+    /// lies outside the domain. Without it the grid starts empty. The cell layout is kept when the new size
+    /// supports it. Neighbor caches are cleared, rebuild
+    /// them with `recount_neighbors`, `recount_zoned_neighbors`/`assign_zones` or `recount_species`
+    pub fn resize(&mut self, new_size: i32, preserve: bool) {
+        let new_size = new_size.max(1);
+        let mut resized = Grid::new(new_size)
+            .with_boundaries(self.boundaries)
+            .with_counting_obstacles(self.obstacles_count)
+            .with_layout(self.layout)
+            .with_parallel(self.parallel)
+            .with_packed_counting(self.packed_counting)
+            .with_update_mode(self.update_mode);
+        resized.generation = self.generation;
+        resized = resized.with_activity(self.activity.is_some());
+
+        if preserve {
+            let offset = IVec3::splat((new_size - self.size) / 2);
+            if self.has_domain() {
+                resized = resized.with_mask(|_| false);
+            }
+            for index in 0..self.cells.len() {
+                let old_pos = self.index_to_pos(index);
+                let pos = old_pos + offset;
+                if !self.in_bounds(old_pos) || !resized.in_bounds(pos) {
+                    continue;
+                }
+                let new_index = resized.pos_to_index(pos);
+                resized.cells[new_index] = Cell { neighbors: 0, faces: 0, ..self.cells[index] };
+                if let Some(mask) = &mut resized.mask {
+                    mask[new_index] = self.is_open(index);
+                }
+                if let (Some(activity), Some(old_activity)) = (&mut resized.activity, &self.activity) {
+                    activity.flips[new_index] = old_activity.flips[index];
+                    activity.changed[new_index] = old_activity.changed[index];
+                }
+            }
+        }
+
+        resized.retrack();
+        *self = resized;
+    }
+
+    /// Write size, generation, all of `rule` (as RON, with what the notation leaves out) and every cell's
+    /// state, species and age (see `encode_cells`) to a compact binary snapshot
+    pub fn save(&self, path: impl AsRef<Path>, rule: &Rule) -> io::Result<()> {
+        let rule = ron::to_string(rule).map_err(|e| invalid_data(&e.to_string()))?;
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.push(SNAPSHOT_VERSION);
+        bytes.extend((self.size as u32).to_le_bytes());
+        bytes.extend(self.generation.to_le_bytes());
+        bytes.extend((rule.len() as u32).to_le_bytes());
+        bytes.extend(rule.as_bytes());
+        bytes.extend(self.encode_cells());
+        std::fs::write(path, bytes)
+    }
+
+    /// Replace the cells and generation with a snapshot written by `save`, returning its rule
+    /// Version 1 snapshots only hold the notation, so their rule loses everything the notation leaves out,
+    /// and versions before 3 only the states, so their cells keep the grid's species and start at age 0
+    /// A snapshot of another size resizes the grid like `resize`; zones, obstacles and the domain stay,
+    /// snapshot cells that can't live there or belong to none of the grid's `species` are dropped. Neighbor
+    /// caches have to be rebuilt as after `resize`
+    pub fn load(&mut self, path: impl AsRef<Path>, species: usize) -> io::Result<Rule> {
+        let bytes = std::fs::read(path)?;
+        let header = SNAPSHOT_MAGIC.len() + 1;
+        if bytes.get(..header - 1) != Some(&SNAPSHOT_MAGIC[..]) || bytes.len() < header + 14 {
+            return Err(invalid_data("not a grid snapshot"));
+        }
+        let version = bytes[header - 1];
+        let size = u32::from_le_bytes(bytes[header..header + 4].try_into().unwrap());
+        let generation = u64::from_le_bytes(bytes[header + 4..header + 12].try_into().unwrap());
+        let (rule_len, offset) = match version {
+            1 => (u16::from_le_bytes(bytes[header + 12..header + 14].try_into().unwrap()) as usize, header + 14),
+            2 | SNAPSHOT_VERSION => {
+                let len = bytes.get(header + 12..header + 16).ok_or_else(|| invalid_data("truncated rule"))?;
+                (u32::from_le_bytes(len.try_into().unwrap()) as usize, header + 16)
+            }
+            _ => return Err(invalid_data("unsupported snapshot version")),
+        };
+        let rule_text = bytes
+            .get(offset..offset + rule_len)
+            .and_then(|rule| std::str::from_utf8(rule).ok())
+            .ok_or_else(|| invalid_data("truncated rule"))?;
+        let rule = if version == 1 {
+            Rule::from_notation(rule_text).map_err(|e| invalid_data(&e.to_string()))?
+        } else {
+            let rule: Rule = ron::from_str(rule_text).map_err(|e| invalid_data(&e.to_string()))?;
+            rule.validate().map_err(|e| invalid_data(&e.to_string()))?;
+            rule
+        };
+        if !(1..=1024).contains(&size) {
+            return Err(invalid_data("grid size out of range"));
+        }
+
+        // Decode before touching the grid, so a broken snapshot leaves it as it was
+        let size = size as i32;
+        let (cells, total) = (&bytes[offset + rule_len..], (size * size * size) as usize);
+        let (saved, states) = if version == SNAPSHOT_VERSION {
+            (Some(SavedCells::decode(cells, total)?), Vec::new())
+        } else {
+            (None, decode_states(cells, total)?)
+        };
+        if size != self.size {
+            self.resize(size, true);
+        }
+        match saved {
+            Some(saved) => self.set_cells(&saved, species, generation),
+            None => self.set_states(&states, generation),
+        }
+        Ok(rule)
+    }
+
+    /// Every cell state run-length encoded in position order, so the encoding doesn't depend on the cell layout
+    /// Mostly empty or settled grids shrink to a small fraction of a byte per cell
+    pub fn encode_states(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        encode_runs(self.states(), &mut bytes);
+        bytes
+    }
+
+    /// Every cell's state, species, decay ticks and age, each run-length encoded in position order like
+    /// `encode_states`, so `restore_cells` brings back multi-species and aging grids exactly
+    /// Grids without species or aging rules only add a few bytes
+    pub fn encode_cells(&self) -> Vec<u8> {
+        let size = self.size;
+        let order: Vec<usize> = (0..size.pow(3))
+            .map(|i| self.pos_to_index(IVec3::new(i % size, i / size % size, i / size / size)))
+            .collect();
+        let mut bytes = Vec::new();
+        encode_runs(order.iter().map(|&index| self.cells[index].value), &mut bytes);
+        encode_runs(order.iter().map(|&index| self.cells[index].species), &mut bytes);
+        encode_runs(order.iter().map(|&index| self.cells[index].ticks), &mut bytes);
+        encode_runs(order.iter().map(|&index| self.cells[index].age.to_le_bytes()[0]), &mut bytes);
+        encode_runs(order.iter().map(|&index| self.cells[index].age.to_le_bytes()[1]), &mut bytes);
+        bytes
+    }
+
+    /// Go back to cells from `encode_cells` of a grid of the same size and its species, at `generation`
+    /// Neighbor caches have to be rebuilt as after `resize`
+    pub fn restore_cells(&mut self, bytes: &[u8], generation: u64) -> io::Result<()> {
+        let saved = SavedCells::decode(bytes, self.size.pow(3) as usize)?;
+        self.set_cells(&saved, usize::from(u8::MAX) + 1, generation);
+        Ok(())
+    }
+
+    /// Go back to states from `encode_states` of a grid of the same size, at `generation`
+    /// Neighbor caches have to be rebuilt as after `resize`
+    pub fn restore_states(&mut self, bytes: &[u8], generation: u64) -> io::Result<()> {
+        let states = decode_states(bytes, self.size.pow(3) as usize)?;
+        self.set_states(&states, generation);
+        Ok(())
+    }
+
+    /// Every cell's state in position order (x fastest), whatever the cell layout
+    pub fn states(&self) -> Vec<u8> {
+        let mut states = Vec::with_capacity(self.size.pow(3) as usize);
+        for z in 0..self.size {
+            for y in 0..self.size {
+                for x in 0..self.size {
+                    states.push(self.cells[self.pos_to_index(IVec3::new(x, y, z))].value);
+                }
+            }
+        }
+        states
+    }
+
+    /// Set every cell's state from `states` in position order (see `states`) and the generation,
+    /// dropping cells that can't live. Neighbor caches have to be rebuilt as after `resize`
+    pub fn set_states(&mut self, states: &[u8], generation: u64) {
+        let size = self.size;
+        for cell in &mut self.cells {
+            cell.value = 0;
+            cell.ticks = 0;
+            cell.age = 0;
+        }
+        for (i, &value) in states.iter().enumerate() {
+            let i = i as i32;
+            let index = self.pos_to_index(IVec3::new(i % size, i / size % size, i / size / size));
+            if value > 0 && self.can_live(index) {
+                self.cells[index].spawn(value);
+            }
+        }
+        self.generation = generation;
+        self.wake_all();
+        self.retrack();
+    }
+
+    /// Set every cell from `saved` and the generation like `set_states`, with its species, decay ticks and
+    /// age, dropping cells of none of the first `species` species
+    fn set_cells(&mut self, saved: &SavedCells, species: usize, generation: u64) {
+        let size = self.size;
+        for cell in self.cells.iter_mut().filter(|cell| !cell.obstacle) {
+            *cell = Cell { value: 0, species: 0, ticks: 0, age: 0, ..*cell };
+        }
+        for (i, &value) in saved.states.iter().enumerate() {
+            let pos = IVec3::new(i as i32 % size, i as i32 / size % size, i as i32 / size / size);
+            let index = self.pos_to_index(pos);
+            if !self.can_live(index) || usize::from(saved.species[i]) >= species {
+                continue;
+            }
+            let cell = &mut self.cells[index];
+            cell.species = saved.species[i];
+            if value > 0 {
+                cell.spawn(value);
+                cell.ticks = saved.ticks[i];
+                cell.age = saved.ages[i];
+            }
+        }
+        self.generation = generation;
+        self.wake_all();
+        self.retrack();
+    }
+
+    /// Make the next phase 1 visit every chunk, after changes it doesn't track (new rules, spawns, noise)
+    fn wake_all(&mut self) {
+        self.awake.fill(true);
+    }
+
+    /// Cells the last phase 1 visited, the rest sat in dead, dormant chunks
+    pub fn active_cells(&self) -> usize {
+        self.active_cells
+    }
+
+    /// Whether the cell at `index` is inside the simulated domain
+    #[inline]
+    fn is_open(&self, index: usize) -> bool {
+        self.mask.as_ref().is_none_or(|mask| mask[index])
+    }
+
+    /// Whether any cell of the grid is outside the simulated domain (ghost cells don't count)
+    fn has_domain(&self) -> bool {
+        self.mask.as_ref().is_some_and(|mask| {
+            mask.iter().enumerate().any(|(index, &open)| !open && self.in_bounds(self.index_to_pos(index)))
+        })
+    }
+
+    /// Whether `pos` is a cell of the grid rather than outside it (or a ghost cell)
+    #[inline]
+    fn in_bounds(&self, pos: IVec3) -> bool {
+        pos.cmpge(IVec3::ZERO).all() && pos.cmplt(IVec3::splat(self.size)).all()
+    }
+
+    /// Cells per side of the stored array, including ghost cells
+    #[inline]
+    fn side(&self) -> i32 {
+        self.size + 2 * self.layout.halo_width()
+    }
+
+    /// Whether the cell at `index` can ever be alive: inside the domain and not an obstacle
+    #[inline]
+    fn can_live(&self, index: usize) -> bool {
+        self.is_open(index) && !self.cells[index].obstacle
+    }
+
+    /// Make obstacle cells count as living neighbors (in the neighborhood of their zone's rule),
+    /// so growth can cling to them. Obstacles never count in multi-species grids
+    pub fn with_counting_obstacles(mut self, count: bool) -> Self {
+        self.obstacles_count = count;
+        self
+    }
+
+    /// Turn every cell of `obstacle` inside the domain into a static obstacle, killing what lived there
+    /// Obstacles are never born or die; neighbor counts are rebuilt for `rules` (the active rule or zone rules)
+    pub fn place_obstacle(&mut self, obstacle: &Obstacle, rules: &[Rule]) {
+        for index in 0..self.cells.len() {
+            if self.is_open(index) && obstacle.contains(self.index_to_pos(index), self.size) {
+                let cell = &mut self.cells[index];
+                *cell = Cell { value: 0, ticks: 0, age: 0, obstacle: true, ..*cell };
+            }
+        }
+        self.recount_zoned_neighbors(rules);
+    }
+
+    /// Remove all obstacles, leaving their cells dead
+    pub fn clear_obstacles(&mut self, rules: &[Rule]) {
+        for cell in self.cells.iter_mut() {
+            cell.obstacle = false;
+        }
+        self.recount_zoned_neighbors(rules);
+    }
+
+    /// Whether the cell at `index` adds to its neighbors' counts
+    #[inline]
+    fn is_counted(&self, rule: &Rule, index: usize) -> bool {
+        let cell = self.cells[index];
+        if cell.obstacle {
+            self.obstacles_count
+        } else {
+            rule.counts_as_neighbor(cell.value)
+        }
+    }
+
+    /// Use per-axis boundary modes instead of wrapping everywhere
+    /// Set this before spawning cells so the cached neighbor counts match
+    pub fn with_boundaries(mut self, boundaries: Boundaries) -> Self {
+        self.boundaries = boundaries;
+        self.layout_offsets.clear();
+        self.link_halo();
+        self
+    }
+
+    pub fn boundaries(&self) -> Boundaries {
+        self.boundaries
+    }
+
+    /// Number of generations simulated since the grid was created
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Zobrist hash of every cell's state, updated as cells change rather than recomputed, so comparing it
+    /// across generations cheaply tells whether the grid repeats. Only comparable within the same layout
+    pub fn state_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Number of living cells, kept up to date as cells change rather than counted
+    pub fn population(&self) -> usize {
+        self.occupancy.count
+    }
+
+    /// Average position of the living cells, None once they're gone
+    /// Structures crossing a wrapping edge average to somewhere between their parts
+    pub fn centroid(&self) -> Option<Vec3> {
+        self.occupancy.centroid()
+    }
+
+    /// Lowest and highest corner of the box around the living cells, None once they're gone
+    pub fn bounds(&self) -> Option<(IVec3, IVec3)> {
+        self.occupancy.bounds()
+    }
+
+    /// Times the cell at `pos` changed state during the run (saturating at u16::MAX), None past the edges
+    /// or without `with_activity`. Kept per position, so it builds up a heatmap of static scaffolding and
+    /// churning frontier
+    pub fn activity(&self, pos: IVec3) -> Option<u16> {
+        let activity = self.activity.as_ref()?;
+        self.in_bounds(pos).then(|| activity.flips[self.pos_to_index(pos)])
+    }
+
+    /// Generations since the cell at `pos` last changed state (its whole run for cells that never did),
+    /// None past the edges or without `with_activity`
+    pub fn since_change(&self, pos: IVec3) -> Option<u64> {
+        let activity = self.activity.as_ref()?;
+        self.in_bounds(pos).then(|| activity.since_change(self.pos_to_index(pos), self.generation))
+    }
+
+    /// Start the activity heatmap over, e.g. for a new run
+    pub fn clear_activity(&mut self) {
+        if let Some(activity) = &mut self.activity {
+            *activity = Activity::new(self.cells.len(), self.generation);
+        }
+    }
+
+    /// Hash and tally every cell's state from scratch, after changes that touch most cells anyway
+    fn retrack(&mut self) {
+        let mut hash = 0;
+        let mut occupancy = Occupancy::new(self.size);
+        for z in 0..self.size {
+            for y in 0..self.size {
+                for x in 0..self.size {
+                    let index = self.pos_to_index(IVec3::new(x, y, z));
+                    let cell = self.cells[index];
+                    hash ^= cell_key(index, cell.species, cell.value);
+                    if !cell.is_dead() {
+                        occupancy.add(IVec3::new(x, y, z));
+                    }
+                }
+            }
+        }
+        self.hash = hash;
+        self.occupancy = occupancy;
+    }
+
+    /// Fold a cell's change from state `before` to `after` into the hash (keeping its species) and the
+    /// tally of living cells
+    #[inline]
+    fn track_cell(&mut self, index: usize, before: u8, after: u8) {
+        let species = self.cells[index].species;
+        self.hash ^= cell_key(index, species, before) ^ cell_key(index, species, after);
+        if let Some(activity) = self.activity.as_mut().filter(|_| before != after) {
+            activity.flip(index, self.generation);
+        }
+        self.track_life(index, before > 0, after > 0);
+    }
+
+    /// Tally a cell going from living or not to living or not
+    #[inline]
+    fn track_life(&mut self, index: usize, before: bool, after: bool) {
+        match (before, after) {
+            (false, true) => self.occupancy.add(self.index_to_pos(index)),
+            (true, false) => self.occupancy.remove(self.index_to_pos(index)),
+            _ => {}
+        }
+    }
+
+    /// Convert 3D position to 1D index
+    #[inline]
+    fn pos_to_index(&self, pos: IVec3) -> usize {
+        match self.layout {
+            CellLayout::Linear => {
+                let x = pos.x as usize;
+                let y = pos.y as usize;
+                let z = pos.z as usize;
+                let size = self.size as usize;
+                x + y * size + z * size * size
+            }
+            CellLayout::Morton => {
+                let spread = &self.spread;
+                (spread[pos.x as usize] | spread[pos.y as usize] << 1 | spread[pos.z as usize] << 2) as usize
+            }
+            CellLayout::Halo { width } => {
+                let pos = (pos + width).as_uvec3();
+                let side = self.side() as usize;
+                pos.x as usize + pos.y as usize * side + pos.z as usize * side * side
+            }
+        }
+    }
+
+    /// Convert 1D index to 3D position
+    #[inline]
+    fn index_to_pos(&self, index: usize) -> IVec3 {
+        match self.layout {
+            CellLayout::Linear => {
+                let size = self.size;
+                IVec3::new(
+                    (index as i32) % size,
+                    (index as i32) / size % size,
+                    (index as i32) / size / size
+                )
+            }
+            CellLayout::Morton => {
+                let index = index as u32;
+                IVec3::new(
+                    compact_bits(index) as i32,
+                    compact_bits(index >> 1) as i32,
+                    compact_bits(index >> 2) as i32,
+                )
+            }
+            // Ghost cells map to positions just outside the grid
+            CellLayout::Halo { width } => {
+                let side = self.side();
+                IVec3::new(
+                    (index as i32) % side,
+                    (index as i32) / side % side,
+                    (index as i32) / side / side
+                ) - width
+            }
+        }
+    }
+
+    /// Index of the neighbor at `offset` from `pos`, None past a dead boundary or outside the domain
+    #[inline]
+    fn neighbor_index(&self, pos: IVec3, offset: IVec3) -> Option<usize> {
+        let pos = pos + offset;
+        let size = self.size;
+        let x = self.boundaries.x.resolve(pos.x, size)?;
+        let y = self.boundaries.y.resolve(pos.y, size)?;
+        let z = self.boundaries.z.resolve(pos.z, size)?;
+        let index = self.pos_to_index(IVec3::new(x, y, z));
+        self.is_open(index).then_some(index)
+    }
+
+    /// Wrap position to handle toroidal boundaries, used for placing cells
+    #[inline]
+    fn wrap(&self, pos: IVec3) -> IVec3 {
+        let size = self.size;
+        IVec3::new(
+            ((pos.x % size) + size) % size,
+            ((pos.y % size) + size) % size,
+            ((pos.z % size) + size) % size,
+        )
+    }
+
+    /// Update neighbor counts when a cell starts or stops counting as a neighbor
+    /// Weighted neighborhoods add each offset's weight instead of 1
+    fn update_neighbors(&mut self, rule: &Rule, index: usize, increment: bool) {
+        if self.layout != CellLayout::Linear {
+            let slot = self.layout_offsets(&rule.neighbor_method);
+            if self.layout_offsets[slot].1.is_some() {
+                match self.layout {
+                    CellLayout::Morton => self.update_neighbors_morton(rule, slot, index, increment),
+                    _ => self.update_neighbors_halo(rule, slot, index, increment),
+                }
+                return;
+            }
+        }
+        self.update_neighbors_by_pos(rule, index, increment);
+    }
+
+    /// `update_neighbors` through each neighbor's position, so no counts are left in ghost cells
+    fn update_neighbors_by_pos(&mut self, rule: &Rule, index: usize, increment: bool) {
+        let pos = self.index_to_pos(index);
+        let offsets = rule.neighbor_method.get_neighbors();
+
+        match rule.neighbor_method.weights() {
+            None => {
+                for &offset in offsets {
+                    let Some(neighbor_index) = self.neighbor_index(pos, offset) else {
+                        continue;
+                    };
+                    self.awake[neighbor_index / CHUNK_LEN] = true;
+                    if increment {
+                        self.cells[neighbor_index].neighbors += 1;
+                    } else {
+                        self.cells[neighbor_index].neighbors -= 1;
+                    }
+                }
+            }
+            Some(weights) => {
+                for (&offset, &weight) in offsets.iter().zip(weights) {
+                    let Some(neighbor_index) = self.neighbor_index(pos, offset) else {
+                        continue;
+                    };
+                    self.awake[neighbor_index / CHUNK_LEN] = true;
+                    if increment {
+                        self.cells[neighbor_index].neighbors += weight;
+                    } else {
+                        self.cells[neighbor_index].neighbors -= weight;
+                    }
+                }
+            }
+        }
+
+        // Two-shell rules also need the face-adjacent part of the count
+        if rule.shells.is_some() {
+            for &offset in offsets.iter().filter(|&&offset| is_face_offset(offset)) {
+                let Some(neighbor_index) = self.neighbor_index(pos, offset) else {
+                    continue;
+                };
+                if increment {
+                    self.cells[neighbor_index].faces += 1;
+                } else {
+                    self.cells[neighbor_index].faces -= 1;
+                }
+            }
+        }
+    }
+
+    /// Cache slot of `method`'s offsets as index deltas for the current layout, see `layout_offsets`
+    /// Morton deltas need wrapping boundaries, halo deltas a neighborhood that fits in the halo
+    fn layout_offsets(&mut self, method: &NeighborMethod) -> usize {
+        if let Some(slot) = self.layout_offsets.iter().position(|(cached, _)| cached == method) {
+            return slot;
+        }
+        let size = self.size;
+        let side = self.side();
+        let spread = &self.spread;
+        let offsets = match self.layout {
+            CellLayout::Linear => None,
+            CellLayout::Morton => (self.boundaries == Boundaries::wrap()).then(|| {
+                method
+                    .get_neighbors()
+                    .iter()
+                    .map(|offset| {
+                        let offset = offset.rem_euclid(IVec3::splat(size));
+                        spread[offset.x as usize] | spread[offset.y as usize] << 1 | spread[offset.z as usize] << 2
+                    })
+                    .collect()
+            }),
+            CellLayout::Halo { width } => (method.reach() <= width).then(|| {
+                method
+                    .get_neighbors()
+                    .iter()
+                    .map(|offset| (offset.x + offset.y * side + offset.z * side * side) as u32)
+                    .collect()
+            }),
+        };
+        self.layout_offsets.push((method.clone(), offsets));
+        self.layout_offsets.len() - 1
+    }
+
+    /// `update_neighbors` for wrapping Morton grids: each neighbor index is the cell's index plus the
+    /// offset's Morton index (see `morton_add`), skipping the position decode, wrap and encode
+    fn update_neighbors_morton(&mut self, rule: &Rule, slot: usize, index: usize, increment: bool) {
+        let method = &rule.neighbor_method;
+        let offsets = method.get_neighbors();
+        let weights = method.weights();
+        let track_faces = rule.shells.is_some();
+        let last = self.cells.len() as u32 - 1;
+        let mask = self.mask.as_deref();
+        let Some(morton_offsets) = &self.layout_offsets[slot].1 else {
+            return;
+        };
+        for (i, &morton_offset) in morton_offsets.iter().enumerate() {
+            let neighbor_index = morton_add(index as u32, morton_offset, last) as usize;
+            if mask.is_some_and(|mask| !mask[neighbor_index]) {
+                continue;
+            }
+            let weight = weights.map_or(1, |weights| weights[i]);
+            let faces = u8::from(track_faces && is_face_offset(offsets[i]));
+            self.awake[neighbor_index / CHUNK_LEN] = true;
+            let neighbor = &mut self.cells[neighbor_index];
+            if increment {
+                neighbor.neighbors += weight;
+                neighbor.faces += faces;
+            } else {
+                neighbor.neighbors -= weight;
+                neighbor.faces -= faces;
+            }
+        }
+    }
+
+    /// `update_neighbors` for halo grids: each neighbor is a fixed index delta away, no wrapping
+    /// Counts for cells past the edge pile up in ghost cells until `sync_halo`, so they may run
+    /// below zero in between; wrapping arithmetic keeps the folded sums exact
+    fn update_neighbors_halo(&mut self, rule: &Rule, slot: usize, index: usize, increment: bool) {
+        let method = &rule.neighbor_method;
+        let offsets = method.get_neighbors();
+        let weights = method.weights();
+        let track_faces = rule.shells.is_some();
+        let Some(deltas) = &self.layout_offsets[slot].1 else {
+            return;
+        };
+        for (i, &delta) in deltas.iter().enumerate() {
+            let weight = weights.map_or(1, |weights| weights[i]);
+            let faces = u8::from(track_faces && is_face_offset(offsets[i]));
+            let neighbor_index = (index as u32).wrapping_add(delta) as usize;
+            self.awake[neighbor_index / CHUNK_LEN] = true;
+            let neighbor = &mut self.cells[neighbor_index];
+            if increment {
+                neighbor.neighbors = neighbor.neighbors.wrapping_add(weight);
+                neighbor.faces = neighbor.faces.wrapping_add(faces);
+            } else {
+                neighbor.neighbors = neighbor.neighbors.wrapping_sub(weight);
+                neighbor.faces = neighbor.faces.wrapping_sub(faces);
+            }
+        }
+        self.halo_dirty = true;
+    }
+
+    /// Pair every ghost cell of a halo grid with the cell it stands in for across the boundary
+    fn link_halo(&mut self) {
+        self.halo_links.clear();
+        self.halo_dirty = false;
+        if self.layout.halo_width() == 0 {
+            return;
+        }
+        let size = self.size;
+        let boundaries = self.boundaries;
+        for index in 0..self.cells.len() {
+            let pos = self.index_to_pos(index);
+            if self.in_bounds(pos) {
+                continue;
+            }
+            let target = match (boundaries.x.resolve(pos.x, size), boundaries.y.resolve(pos.y, size), boundaries.z.resolve(pos.z, size)) {
+                (Some(x), Some(y), Some(z)) => Some(self.pos_to_index(IVec3::new(x, y, z)) as u32),
+                _ => None,
+            };
+            self.halo_links.push((index as u32, target));
+        }
+    }
+
+    /// Fold the counts piled up in ghost cells into the cells they stand in for, dropping counts
+    /// past dead boundaries. Runs once after each batch of neighbor updates
+    fn sync_halo(&mut self) {
+        if !self.halo_dirty {
+            return;
+        }
+        self.halo_dirty = false;
+        for &(ghost, target) in &self.halo_links {
+            let ghost = &mut self.cells[ghost as usize];
+            let (neighbors, faces) = (ghost.neighbors, ghost.faces);
+            if neighbors == 0 && faces == 0 {
+                continue;
+            }
+            ghost.neighbors = 0;
+            ghost.faces = 0;
+            if let Some(target) = target {
+                self.awake[target as usize / CHUNK_LEN] = true;
+                let target = &mut self.cells[target as usize];
+                target.neighbors = target.neighbors.wrapping_add(neighbors);
+                target.faces = target.faces.wrapping_add(faces);
+            }
+        }
+    }
+
+    /// Bitmask per cell of which pattern offsets count as neighbors (bit N = Nth neighbor offset)
+    /// Only cells whose zone rule is non-totalistic get a pattern, None when no rule is
+    fn neighbor_patterns(&self, rules: &[Rule]) -> Option<Vec<u32>> {
+        if rules.iter().all(|rule| rule.pattern.is_none()) {
+            return None;
+        }
+        Some((0..self.cells.len()).map(|index| self.neighbor_pattern(rules, index)).collect())
+    }
+
+    /// Bitmask of which pattern offsets of the cell at `index` count as neighbors, 0 for totalistic zone rules
+    fn neighbor_pattern(&self, rules: &[Rule], index: usize) -> u32 {
+        let cell = self.cells[index];
+        let rule = zone_rule(rules, cell.zone);
+        // No live neighbors means an empty pattern, skip the lookups
+        if rule.pattern.is_none() || cell.neighbors == 0 {
+            return 0;
+        }
+        let pos = self.index_to_pos(index);
+        let mut pattern = 0;
+        for (bit, &offset) in pattern_offsets(&rule.neighbor_method).iter().enumerate() {
+            let Some(neighbor_index) = self.neighbor_index(pos, offset) else {
+                continue;
+            };
+            if self.is_counted(zone_rule(rules, self.cells[neighbor_index].zone), neighbor_index) {
+                pattern |= 1 << bit;
+            }
+        }
+        pattern
+    }
+
+    /// Phase 1 of a step: apply birth/survival/decay to every cell using the cached neighbor counts
+    /// Returns which cells started or stopped counting as neighbors; neighbor counts are stale until `apply_changes`
+    /// `rng` decides births and survivals that the rule only allows with some probability
+    pub fn update_states(&mut self, rule: &Rule, rng: &mut impl Rng) -> StepChanges {
+        self.update_zoned_states(std::slice::from_ref(rule), rng)
+    }
+
+    /// Phase 1 of a step in a zoned grid: like `update_states`, with each cell following its zone's rule
+    /// Parallel grids (see `with_parallel`) update slabs of cells on all cores, each slab drawing from
+    /// its own RNG seeded from `rng`, so a seed still replays identically whatever the core count
+    pub fn update_zoned_states(&mut self, rules: &[Rule], rng: &mut impl Rng) -> StepChanges {
+        self.generation += 1;
+
+        // Non-totalistic rules need every neighbor pattern, read before any cell changes
+        let patterns = self.neighbor_patterns(rules);
+        let mask = self.mask.as_deref();
+        // Probabilistic births can happen later without any neighbor changing, so no chunk sleeps
+        let restless = rules.iter().any(|rule| !rule.birth_chance.is_empty());
+        let awake = &self.awake;
+        let tracking_activity = self.activity.is_some();
+        // Visits the awake chunks of a slab, returning its changes and the chunks to visit next step:
+        // those with living cells or changes, phase 2 wakes the chunks whose neighbor counts change
+        let update_slab = |start: usize, cells: &mut [Cell], rng: &mut dyn rand::RngCore| {
+            let mut changes = StepChanges::default();
+            let mut wake = Vec::new();
+            let mut active = 0;
+            let mut hash = 0;
+            let mut lives = Vec::new();
+            let mut flipped = Vec::new();
+            let end = start + cells.len();
+            let mut chunk_start = start;
+            while chunk_start < end {
+                let chunk = chunk_start / CHUNK_LEN;
+                let chunk_end = ((chunk + 1) * CHUNK_LEN).min(end);
+                if restless || awake[chunk] {
+                    active += chunk_end - chunk_start;
+                    let mut restless_chunk = false;
+                    for (index, cell) in (chunk_start..).zip(&mut cells[chunk_start - start..chunk_end - start]) {
+                        // Cells outside the domain are always dead, obstacles never change
+                        if cell.obstacle || mask.is_some_and(|mask| !mask[index]) {
+                            continue;
+                        }
+                        let rule = zone_rule(rules, cell.zone);
+                        let pattern = match (&patterns, &rule.pattern) {
+                            (Some(patterns), Some(_)) => Some(patterns[index]),
+                            _ => None,
+                        };
+                        let value = cell.value;
+                        match cell.step(rule, pattern, rng) {
+                            Some(true) => changes.spawns.push(index),
+                            Some(false) => changes.deaths.push(index),
+                            None => {}
+                        }
+                        if cell.value != value {
+                            hash ^= cell_key(index, cell.species, value) ^ cell_key(index, cell.species, cell.value);
+                            if tracking_activity {
+                                flipped.push(index);
+                            }
+                            if (value == 0) != cell.is_dead() {
+                                lives.push(index);
+                            }
+                        }
+                        // A cell that just died may be born again from the same counts
+                        restless_chunk |= !cell.is_dead() || cell.value != value;
+                    }
+                    if restless_chunk {
+                        wake.push(chunk);
+                    }
+                }
+                chunk_start = chunk_end;
+            }
+            (changes, wake, active, hash, lives, flipped)
+        };
+
+        let slabs: Vec<_> = if self.parallel {
+            let slab_len = self.slab_len();
+            let seeds: Vec<u64> = (0..self.cells.len().div_ceil(slab_len)).map(|_| rng.random()).collect();
+            self.cells
+                .par_chunks_mut(slab_len)
+                .zip(seeds)
+                .enumerate()
+                .map(|(slab, (cells, seed))| update_slab(slab * slab_len, cells, &mut StdRng::seed_from_u64(seed)))
+                .collect()
+        } else {
+            vec![update_slab(0, &mut self.cells, rng)]
+        };
+
+        // Slabs are in index order, so the changes are too (see `apply_zoned_changes`)
+        let mut changes = StepChanges::default();
+        self.awake.fill(false);
+        self.active_cells = 0;
+        for (slab, wake, active, hash, lives, flipped) in slabs {
+            self.hash ^= hash;
+            // Cells that were born or died
+            for index in lives {
+                let alive = !self.cells[index].is_dead();
+                self.track_life(index, !alive, alive);
+            }
+            if let Some(activity) = &mut self.activity {
+                for index in flipped {
+                    activity.flip(index, self.generation);
+                }
+            }
+            changes.spawns.extend(slab.spawns);
+            changes.deaths.extend(slab.deaths);
+            for chunk in wake {
+                self.awake[chunk] = true;
+            }
+            self.active_cells += active;
+        }
+        changes
+    }
+
+    /// Phase 2 of a step: update cached neighbor counts for cells that started or stopped counting
+    pub fn apply_changes(&mut self, rule: &Rule, changes: &StepChanges) {
+        self.apply_zoned_changes(std::slice::from_ref(rule), changes);
+    }
+
+    /// Phase 2 of a step in a zoned grid: each changed cell updates its zone rule's neighborhood
+    /// Parallel grids with z-plane layouts (Linear, Halo) split the grid into slabs that each only update
+    /// their own cells' counts, from the changed cells within reach, so no two cores write the same cell
+    pub fn apply_zoned_changes(&mut self, rules: &[Rule], changes: &StepChanges) {
+        // Once changes update more neighbors than there are cells, counting every cell from scratch is cheaper
+        let updates = (changes.spawns.len() + changes.deaths.len()) * rules[0].neighbor_method.get_neighbors().len();
+        if updates > self.cells.len() * PACKED_UPDATES_PER_CELL && self.can_count_packed(rules) {
+            self.count_packed(rules);
+            return;
+        }
+        let sorted = changes.spawns.is_sorted() && changes.deaths.is_sorted();
+        if self.parallel && self.layout != CellLayout::Morton && sorted {
+            self.apply_changes_parallel(rules, changes);
+            return;
+        }
+        for &index in &changes.spawns {
+            self.update_neighbors(zone_rule(rules, self.cells[index].zone), index, true);
+        }
+        for &index in &changes.deaths {
+            self.update_neighbors(zone_rule(rules, self.cells[index].zone), index, false);
+        }
+        self.sync_halo();
+    }
+
+    /// `apply_zoned_changes` on all cores, see there. `changes` must be in index order
+    fn apply_changes_parallel(&mut self, rules: &[Rule], changes: &StepChanges) {
+        // Index deltas are looked up before the slabs start, they only read them
+        let slots: Vec<Option<usize>> = rules
+            .iter()
+            .map(|rule| match self.layout {
+                CellLayout::Linear => None,
+                _ => Some(self.layout_offsets(&rule.neighbor_method)).filter(|&slot| self.layout_offsets[slot].1.is_some()),
+            })
+            .collect();
+        self.halo_dirty |= slots.iter().any(Option::is_some);
+        let reach = rules.iter().map(|rule| rule.neighbor_method.reach()).max().unwrap_or(0);
+        let spawns: Vec<(usize, u8)> = changes.spawns.iter().map(|&index| (index, self.cells[index].zone)).collect();
+        let deaths: Vec<(usize, u8)> = changes.deaths.iter().map(|&index| (index, self.cells[index].zone)).collect();
+
+        let plane = (self.side() * self.side()) as usize;
+        let slab_len = self.slab_len();
+        let width = self.layout.halo_width();
+        // Take the cells out so the slabs can borrow them mutably while reading the rest of the grid
+        let mut cells = std::mem::take(&mut self.cells);
+        let grid = &*self;
+        let woken: Vec<Vec<usize>> = cells.par_chunks_mut(slab_len).enumerate().map(|(slab, cells)| {
+            let start = slab * slab_len;
+            let mut touched = vec![false; (start + cells.len()).div_ceil(CHUNK_LEN) - start / CHUNK_LEN];
+            // Planes of the slab as z coordinates, ghost planes fall outside the grid
+            let z_min = (start / plane) as i32 - width;
+            let z_max = ((start + cells.len()) / plane) as i32 - width;
+            // Spawns before deaths like the sequential pass, so counts never run below zero
+            for (sources, increment) in [(&spawns, true), (&deaths, false)] {
+                for range in grid.sources_between(sources, z_min - reach, z_max + reach) {
+                    for &(index, zone) in &sources[range] {
+                        let rule_index = (zone as usize).min(rules.len() - 1);
+                        grid.update_slab_neighbors(&rules[rule_index], slots[rule_index], cells, start, &mut touched, index, increment);
+                    }
+                }
+            }
+            (start / CHUNK_LEN..).zip(touched).filter(|&(_, touched)| touched).map(|(chunk, _)| chunk).collect()
+        }).collect();
+        self.cells = cells;
+        for chunk in woken.into_iter().flatten() {
+            self.awake[chunk] = true;
+        }
+        self.sync_halo();
+    }
+
+    /// Cells per slab for parallel steps: a few z-planes, so every core gets several slabs
+    /// Fixed by the grid shape, never by the core count, to keep seeded runs reproducible
+    fn slab_len(&self) -> usize {
+        const SLAB_PLANES: usize = 4;
+        let side = self.side() as usize;
+        SLAB_PLANES * side * side
+    }
+
+    /// Ranges of `sources` (in index order) with a z coordinate in `z_min..z_max`, wrapping around the grid
+    /// Includes wrapped planes even past dead boundaries, the neighbor lookups skip them
+    fn sources_between(&self, sources: &[(usize, u8)], z_min: i32, z_max: i32) -> Vec<std::ops::Range<usize>> {
+        let size = self.size;
+        let planes = if z_max - z_min >= size {
+            vec![(0, size)]
+        } else {
+            let (low, high) = (z_min.rem_euclid(size), z_max.rem_euclid(size));
+            if low < high { vec![(low, high)] } else { vec![(low, size), (0, high)] }
+        };
+        let first = |z: i32| sources.partition_point(|&(index, _)| self.index_to_pos(index).z < z);
+        planes.into_iter().map(|(low, high)| first(low)..first(high)).collect()
+    }
+
+    /// `update_neighbors` restricted to the cells of one slab (`cells`, starting at index `start`)
+    /// `slot` holds the layout's index deltas for the rule's neighborhood, None to look neighbors up by position
+    /// `touched` marks the slab's chunks (from the one holding `start`) whose counts changed
+    #[allow(clippy::too_many_arguments)]
+    fn update_slab_neighbors(&self, rule: &Rule, slot: Option<usize>, cells: &mut [Cell], start: usize, touched: &mut [bool], index: usize, increment: bool) {
+        let offsets = rule.neighbor_method.get_neighbors();
+        let weights = rule.neighbor_method.weights();
+        let track_faces = rule.shells.is_some();
+        let pos = self.index_to_pos(index);
+        let deltas = slot.and_then(|slot| self.layout_offsets[slot].1.as_deref());
+
+        for (i, &offset) in offsets.iter().enumerate() {
+            let neighbor_index = match deltas {
+                Some(deltas) => (index as u32).wrapping_add(deltas[i]) as usize,
+                None => match self.neighbor_index(pos, offset) {
+                    Some(neighbor_index) => neighbor_index,
+                    None => continue,
+                },
+            };
+            let Some(neighbor) = neighbor_index.checked_sub(start).and_then(|local| cells.get_mut(local)) else {
+                continue;
+            };
+            touched[neighbor_index / CHUNK_LEN - start / CHUNK_LEN] = true;
+            let weight = weights.map_or(1, |weights| weights[i]);
+            let faces = u8::from(track_faces && is_face_offset(offset));
+            // Ghost cells may run below zero until `sync_halo`, see `update_neighbors_halo`
+            if increment {
+                neighbor.neighbors = neighbor.neighbors.wrapping_add(weight);
+                neighbor.faces = neighbor.faces.wrapping_add(faces);
+            } else {
+                neighbor.neighbors = neighbor.neighbors.wrapping_sub(weight);
+                neighbor.faces = neighbor.faces.wrapping_sub(faces);
+            }
+        }
+    }
+
+    /// Advance one generation without any rendering (usable outside of Bevy)
+    pub fn step(&mut self, rule: &Rule, rng: &mut impl Rng) -> StepChanges {
+        self.step_zoned(std::slice::from_ref(rule), rng)
+    }
+
+    /// Advance `n` generations without any rendering, e.g. to fast-forward slowly evolving rules
+    pub fn step_n(&mut self, rule: &Rule, n: u32, rng: &mut impl Rng) {
+        for _ in 0..n {
+            self.step(rule, rng);
+        }
+    }
+
+    /// Advance one generation of a zoned grid in its update mode (see `UpdateMode`)
+    /// Sequential modes update a block or a single cell at a time, and each turn's changes update
+    /// neighbor counts before the next turn; the returned changes are already counted either way
+    pub fn step_zoned(&mut self, rules: &[Rule], rng: &mut impl Rng) -> StepChanges {
+        if self.update_mode == UpdateMode::Synchronous {
+            let changes = self.update_zoned_states(rules, rng);
+            self.apply_zoned_changes(rules, &changes);
+            return changes;
+        }
+        self.generation += 1;
+        self.wake_all();
+        self.active_cells = self.cells.len();
+        let mut changes = StepChanges::default();
+        let size = self.size;
+        match self.update_mode {
+            UpdateMode::Synchronous => {}
+            UpdateMode::BlockSequential { block } => {
+                let block = block.clamp(1, size);
+                let mut turn = Vec::new();
+                for block_z in (0..size).step_by(block as usize) {
+                    for block_y in (0..size).step_by(block as usize) {
+                        for block_x in (0..size).step_by(block as usize) {
+                            let origin = IVec3::new(block_x, block_y, block_z);
+                            let end = (origin + block).min(IVec3::splat(size));
+                            turn.clear();
+                            for z in origin.z..end.z {
+                                for y in origin.y..end.y {
+                                    for x in origin.x..end.x {
+                                        let index = self.pos_to_index(IVec3::new(x, y, z));
+                                        if self.can_live(index) {
+                                            turn.push(index);
+                                        }
+                                    }
+                                }
+                            }
+                            self.take_turn(rules, &turn, &mut changes, rng);
+                        }
+                    }
+                }
+            }
+            UpdateMode::RandomSequential => {
+                // Ghost cells of halo grids are outside the mask, so they never take a turn
+                let mut order: Vec<usize> = (0..self.cells.len()).filter(|&index| self.can_live(index)).collect();
+                order.shuffle(rng);
+                for index in order {
+                    self.take_turn(rules, &[index], &mut changes, rng);
+                }
+            }
+        }
+        changes
+    }
+
+    /// Update the cells at `indices` at once from the current counts, then update their neighbors' counts
+    fn take_turn(&mut self, rules: &[Rule], indices: &[usize], changes: &mut StepChanges, rng: &mut impl Rng) {
+        // Non-totalistic rules read every pattern of the turn before any of its cells change
+        let has_patterns = rules.iter().any(|rule| rule.pattern.is_some());
+        let patterns: Vec<u32> = if has_patterns {
+            indices.iter().map(|&index| self.neighbor_pattern(rules, index)).collect()
+        } else {
+            Vec::new()
+        };
+        let mut turn_changes = Vec::new();
+        for (i, &index) in indices.iter().enumerate() {
+            let rule = zone_rule(rules, self.cells[index].zone);
+            let pattern = rule.pattern.as_ref().map(|_| patterns[i]);
+            let value = self.cells[index].value;
+            let spawned = self.cells[index].step(rule, pattern, rng);
+            self.track_cell(index, value, self.cells[index].value);
+            if let Some(spawned) = spawned {
+                turn_changes.push((index, spawned));
+            }
+        }
+        for (index, spawned) in turn_changes {
+            self.update_neighbors_by_pos(zone_rule(rules, self.cells[index].zone), index, spawned);
+            if spawned {
+                changes.spawns.push(index);
+            } else {
+                changes.deaths.push(index);
+            }
+        }
+    }
+
+    /// Randomly flip about `temperature` (fraction) of all cells: dead cells are born at max_state,
+    /// living cells die. Neighbor counts are updated as each cell flips, so the cache stays consistent
+    pub fn apply_noise(&mut self, rule: &Rule, temperature: f32, rng: &mut impl Rng) -> StepChanges {
+        self.apply_zoned_noise(std::slice::from_ref(rule), temperature, rng)
+    }
+
+    /// Noise flips in a zoned grid, cells are born at their zone rule's max_state
+    pub fn apply_zoned_noise(&mut self, rules: &[Rule], temperature: f32, rng: &mut impl Rng) -> StepChanges {
+        let mut changes = StepChanges::default();
+
+        // Round the expected flip count randomly so tiny temperatures still flip occasionally
+        let expected = self.cells.len() as f32 * temperature.clamp(0.0, 1.0);
+        let flips = expected as usize + roll(rng, expected.fract()) as usize;
+
+        for _ in 0..flips {
+            let index = rng.random_range(0..self.cells.len());
+            if !self.can_live(index) {
+                continue;
+            }
+            self.awake[index / CHUNK_LEN] = true;
+            let rule = zone_rule(rules, self.cells[index].zone);
+            let value = self.cells[index].value;
+            if value == 0 {
+                self.cells[index].spawn(rule.birth_state());
+                if rule.counts_as_neighbor(rule.birth_state()) {
+                    self.update_neighbors(rule, index, true);
+                    changes.spawns.push(index);
+                }
+            } else {
+                if rule.counts_as_neighbor(value) {
+                    self.update_neighbors(rule, index, false);
+                    changes.deaths.push(index);
+                }
+                self.cells[index].value = 0;
+            }
+            self.track_cell(index, value, self.cells[index].value);
+        }
+        self.sync_halo();
+
+        changes
+    }
+
+    /// Bring up to `amount` random dead cells in `region` to life at their zone rule's birth state,
+    /// returning how many were born. Neighbor counts are updated right away like noise flips
+    pub fn inject_cells(&mut self, rules: &[Rule], region: &Region, amount: usize, rng: &mut impl Rng) -> usize {
+        let mut born = 0;
+        for _ in 0..amount {
+            let index = self.pos_to_index(self.wrap(region.random_pos(self.size, rng)));
+            if !self.cells[index].is_dead() || !self.can_live(index) {
+                continue;
+            }
+            self.awake[index / CHUNK_LEN] = true;
+            let rule = zone_rule(rules, self.cells[index].zone);
+            self.cells[index].spawn(rule.birth_state());
+            self.track_cell(index, 0, rule.birth_state());
+            if rule.counts_as_neighbor(rule.birth_state()) {
+                self.update_neighbors(rule, index, true);
+            }
+            born += 1;
+        }
+        self.sync_halo();
+        born
+    }
+
+    /// Set cells to new states right away, e.g. for painting while the simulation runs, updating neighbor
+    /// counts like noise flips. States are clamped to the cell's zone rule, 0 kills; cells past the edges,
+    /// outside the domain and obstacles are skipped. Returns each changed cell with its state before and after
+    pub fn edit_cells(&mut self, rules: &[Rule], cells: impl IntoIterator<Item = (IVec3, u8)>) -> Vec<(IVec3, u8, u8)> {
+        let mut changed = Vec::new();
+        for (pos, state) in cells {
+            if !self.in_bounds(pos) {
+                continue;
+            }
+            let index = self.pos_to_index(pos);
+            let rule = zone_rule(rules, self.cells[index].zone);
+            let (before, after) = (self.cells[index].value, state.min(rule.states));
+            if before == after || !self.can_live(index) {
+                continue;
+            }
+
+            let counted = rule.counts_as_neighbor(before);
+            // A fresh life (or none), like a birth or death in a step
+            self.cells[index].spawn(after);
+            self.track_cell(index, before, after);
+            match (counted, rule.counts_as_neighbor(after)) {
+                (false, true) => self.update_neighbors(rule, index, true),
+                (true, false) => self.update_neighbors(rule, index, false),
+                _ => {}
+            }
+            self.awake[index / CHUNK_LEN] = true;
+            changed.push((pos, before, after));
+        }
+        self.sync_halo();
+        changed
+    }
+
+    /// Turn the cells of the box from `min` to `max` (inclusive, clipped to the grid) `turns` quarter turns
+    /// about `axis` around the box center, counterclockwise looking down the axis, then rebuild the neighbor
+    /// counts for `rules`. A box that isn't square across the axis turns into another box, cells landing
+    /// past the edges, outside the domain or on obstacles are lost. Zones and obstacles stay where they are
+    pub fn rotate_region(&mut self, rules: &[Rule], min: IVec3, max: IVec3, axis: Axis, turns: i32) {
+        let Some((min, max)) = self.clip_box(min, max) else { return };
+        let size = max - min + 1;
+        let (_, turned) = axis.turn(IVec3::ZERO, size, turns);
+        let corner = min + (size - turned) / 2;
+        self.move_cells(rules, min, max, |pos| corner + axis.turn(pos - min, size, turns).0);
+    }
+
+    /// Mirror the cells of the box from `min` to `max` (inclusive, clipped to the grid) along `axis`,
+    /// then rebuild the neighbor counts for `rules`, see `rotate_region`
+    pub fn mirror_region(&mut self, rules: &[Rule], min: IVec3, max: IVec3, axis: Axis) {
+        let Some((min, max)) = self.clip_box(min, max) else { return };
+        let i = axis.index();
+        self.move_cells(rules, min, max, |mut pos| {
+            pos[i] = min[i] + max[i] - pos[i];
+            pos
+        });
+    }
+
+    /// Turn the whole grid `turns` quarter turns about `axis`, see `rotate_region`
+    pub fn rotate(&mut self, rules: &[Rule], axis: Axis, turns: i32) {
+        self.rotate_region(rules, IVec3::ZERO, IVec3::splat(self.size - 1), axis, turns);
+    }
+
+    /// Mirror the whole grid along `axis`, see `rotate_region`
+    pub fn mirror(&mut self, rules: &[Rule], axis: Axis) {
+        self.mirror_region(rules, IVec3::ZERO, IVec3::splat(self.size - 1), axis);
+    }
+
+    /// Corners of the part of the box from `min` to `max` inside the grid, None if it's all outside
+    fn clip_box(&self, min: IVec3, max: IVec3) -> Option<(IVec3, IVec3)> {
+        let clipped = (min.min(max).max(IVec3::ZERO), min.max(max).min(IVec3::splat(self.size - 1)));
+        clipped.0.cmple(clipped.1).all().then_some(clipped)
+    }
+
+    /// Move every living cell of the box from `min` to `max` to `to(pos)` at once, keeping its state, age
+    /// and species, then rebuild the neighbor counts for `rules`
+    fn move_cells(&mut self, rules: &[Rule], min: IVec3, max: IVec3, to: impl Fn(IVec3) -> IVec3) {
+        let mut moved = Vec::new();
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let pos = IVec3::new(x, y, z);
+                    let index = self.pos_to_index(pos);
+                    let cell = &mut self.cells[index];
+                    if !cell.is_dead() {
+                        moved.push((to(pos), *cell));
+                        cell.spawn(0);
+                    }
+                }
+            }
+        }
+        for (pos, cell) in moved {
+            if !self.in_bounds(pos) {
+                continue;
+            }
+            let index = self.pos_to_index(pos);
+            if self.can_live(index) {
+                let target = &mut self.cells[index];
+                (target.value, target.species, target.ticks, target.age) = (cell.value, cell.species, cell.ticks, cell.age);
+            }
+        }
+        self.recount_zoned_neighbors(rules);
+    }
+
+    /// Set every cell to the state `state(pos)` returns for its position, e.g. to carve spheres, gyroids or
+    /// extruded text, then rebuild the neighbor counts for `rule`. 0 leaves a cell as it is, states above the
+    /// rule's max state are clamped; cells outside the domain and obstacles are skipped
+    pub fn seed_with(&mut self, rule: &Rule, state: impl FnMut(IVec3) -> u8) {
+        self.seed_zoned_with(std::slice::from_ref(rule), state);
+    }
+
+    /// `seed_with` in a zoned grid, clamping to each cell's zone rule
+    pub fn seed_zoned_with(&mut self, rules: &[Rule], mut state: impl FnMut(IVec3) -> u8) {
+        for z in 0..self.size {
+            for y in 0..self.size {
+                for x in 0..self.size {
+                    let pos = IVec3::new(x, y, z);
+                    let index = self.pos_to_index(pos);
+                    let value = state(pos).min(zone_rule(rules, self.cells[index].zone).states);
+                    if value > 0 && self.can_live(index) {
+                        self.cells[index].spawn(value);
+                    }
+                }
+            }
+        }
+        self.recount_zoned_neighbors(rules);
+    }
+
+    /// Bring exactly `fraction` of the cells in `region` that can live to life at their zone rule's birth
+    /// state, returning how many were born. Cells are drawn without repeats, so unlike a cluster's amount
+    /// the density is what was asked for on any grid size; cells that were already alive count too
+    pub fn seed_density(&mut self, rules: &[Rule], region: &Region, fraction: f32, rng: &mut impl Rng) -> usize {
+        self.wake_all();
+        let size = self.size;
+        let mut candidates = Vec::new();
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let pos = IVec3::new(x, y, z);
+                    let index = self.pos_to_index(pos);
+                    if region.contains(pos, size) && self.can_live(index) {
+                        candidates.push(index);
+                    }
+                }
+            }
+        }
+        let amount = (candidates.len() as f32 * fraction.clamp(0.0, 1.0)).round() as usize;
+        let mut born = 0;
+        for chosen in rand::seq::index::sample(rng, candidates.len(), amount) {
+            let index = candidates[chosen];
+            if !self.cells[index].is_dead() {
+                continue;
+            }
+            let rule = zone_rule(rules, self.cells[index].zone);
+            self.cells[index].spawn(rule.birth_state());
+            self.track_cell(index, 0, rule.birth_state());
+            if rule.counts_as_neighbor(rule.birth_state()) {
+                self.update_neighbors(rule, index, true);
+            }
+            born += 1;
+        }
+        self.sync_halo();
+        born
+    }
+
+    /// Rebuild all cached neighbor counts, e.g. after the active rule changed
+    /// Cells above the rule's state count are clamped to its max state
+    pub fn recount_neighbors(&mut self, rule: &Rule) {
+        self.recount_zoned_neighbors(std::slice::from_ref(rule));
+    }
+
+    /// Rebuild all cached neighbor counts of a zoned grid, clamping cells to their zone rule's max state
+    pub fn recount_zoned_neighbors(&mut self, rules: &[Rule]) {
+        self.wake_all();
+        for cell in self.cells.iter_mut() {
+            cell.neighbors = 0;
+            cell.faces = 0;
+            cell.value = cell.value.min(zone_rule(rules, cell.zone).states);
+        }
+        self.retrack();
+        if self.can_count_packed(rules) {
+            self.count_packed(rules);
+            return;
+        }
+        for index in 0..self.cells.len() {
+            let rule = zone_rule(rules, self.cells[index].zone);
+            if self.is_counted(rule, index) {
+                self.update_neighbors(rule, index, true);
+            }
+        }
+        self.sync_halo();
+    }
+
+    /// Whether `count_packed` can count for `rules`: packed counting is on, the size is a multiple of 64
+    /// and every zone shares one unweighted neighborhood without two-shell counts
+    /// Halo grids keep updating neighborhoods, their fixed index offsets are cheaper than counting from scratch
+    fn can_count_packed(&self, rules: &[Rule]) -> bool {
+        let method = &rules[0].neighbor_method;
+        self.packed_counting
+            && !matches!(self.layout, CellLayout::Halo { .. })
+            && BitGrid::fits(self.size)
+            && method.weights().is_none()
+            && rules.iter().all(|rule| rule.neighbor_method == *method && rule.shells.is_none())
+    }
+
+    /// Set every cell's neighbor count from bit-packed rows of the counted cells, 64 cells at a time
+    /// Chunks whose counts changed are woken; cells outside the domain keep their (unused) counts
+    fn count_packed(&mut self, rules: &[Rule]) {
+        let Some(mut bits) = BitGrid::new(self.size) else {
+            return;
+        };
+        // Every layout's index is a row's start plus a per-column offset (Morton's bits don't overlap)
+        let origin = self.pos_to_index(IVec3::ZERO);
+        let columns: Vec<usize> = (0..self.size).map(|x| self.pos_to_index(IVec3::new(x, 0, 0)) - origin).collect();
+        // Same test as `is_counted`, with each zone's threshold looked up once
+        let thresholds: Vec<u8> = rules.iter().map(|rule| rule.neighbor_threshold().max(1)).collect();
+        let counted = |cell: &Cell| {
+            if cell.obstacle {
+                self.obstacles_count
+            } else {
+                cell.value >= thresholds[(cell.zone as usize).min(thresholds.len() - 1)]
+            }
+        };
+        for z in 0..self.size {
+            for y in 0..self.size {
+                let start = self.pos_to_index(IVec3::new(0, y, z));
+                for (row_columns, word) in columns.chunks_exact(64).zip(bits.row_mut(y, z)) {
+                    for (bit, &column) in row_columns.iter().enumerate() {
+                        *word |= u64::from(counted(&self.cells[start + column])) << bit;
+                    }
+                }
+            }
+        }
+
+        // Take the cells out so the rows can be written while looking up indices
+        let mut cells = std::mem::take(&mut self.cells);
+        let mut awake = std::mem::take(&mut self.awake);
+        let grid = &*self;
+        bits.count(rules[0].neighbor_method.get_neighbors(), self.boundaries, |y, z, counts| {
+            let start = grid.pos_to_index(IVec3::new(0, y, z));
+            for (&column, &count) in columns.iter().zip(counts) {
+                let index = start + column;
+                if grid.is_open(index) {
+                    awake[index / CHUNK_LEN] |= cells[index].neighbors != count;
+                    cells[index].neighbors = count;
+                }
+            }
+        });
+        self.cells = cells;
+        self.awake = awake;
+    }
+
+    /// Replace the active rule mid-simulation, keeping cached neighbor counts valid
+    /// Neighbors are only recounted when the neighborhood, counting convention or state count
+    /// changed; returns whether a recount happened
+    pub fn swap_rule(&mut self, active: &mut Rule, new: Rule) -> bool {
+        let recount = !active.counts_like(&new);
+        *active = new;
+        // Dormant chunks may come alive under the new rule
+        self.wake_all();
+        if recount {
+            self.recount_neighbors(active);
+        }
+        recount
+    }
+
+    /// Store each cell's zone from `layout` and rebuild the neighbor counts for the zone rules
+    pub fn assign_zones(&mut self, layout: &ZoneLayout, rules: &[Rule]) {
+        for index in 0..self.cells.len() {
+            let pos = self.index_to_pos(index);
+            if self.in_bounds(pos) {
+                self.cells[index].zone = layout.zone_at(pos, self.size);
+            }
+        }
+        self.recount_zoned_neighbors(rules);
+    }
+
+    /// Zone of the cell at `pos` (0 in grids without zones)
+    pub fn zone_at(&self, pos: IVec3) -> u8 {
+        self.cells[self.pos_to_index(self.wrap(pos))].zone
+    }
+
+    /// State, age and cached neighbor count of the cell at `pos`, None past the edges
+    pub fn cell_info(&self, pos: IVec3) -> Option<CellInfo> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        let cell = self.cells[self.pos_to_index(pos)];
+        Some(CellInfo {
+            state: cell.value,
+            age: cell.age,
+            neighbors: cell.neighbors,
+            zone: cell.zone,
+            species: cell.species,
+            obstacle: cell.obstacle,
+        })
+    }
+
+    /// Walk a ray from `origin` along `direction` cell by cell (DDA), in grid positions where each cell
+    /// spans half a unit around its position, and return the first living cell or obstacle within
+    /// `max_distance`. Rays stop at the grid edges whatever the boundaries
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RayHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return None;
+        }
+        // Cell corners on integers from here, so the cell holding a point is its floor
+        let origin = origin + 0.5;
+        let size = self.size as f32;
+
+        // Part of the ray inside the grid box
+        let (mut enter, mut exit) = (0.0f32, max_distance);
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                if !(0.0..size).contains(&origin[axis]) {
+                    return None;
+                }
+            } else {
+                let near = -origin[axis] / direction[axis];
+                let far = (size - origin[axis]) / direction[axis];
+                enter = enter.max(near.min(far));
+                exit = exit.min(near.max(far));
+            }
+        }
+        if enter > exit {
+            return None;
+        }
+
+        let mut pos = (origin + direction * enter).floor().as_ivec3().clamp(IVec3::ZERO, IVec3::splat(self.size - 1));
+        let step = direction.signum().as_ivec3();
+        // Distance to the next cell boundary on each axis, and between boundaries
+        let mut next = Vec3::INFINITY;
+        for axis in 0..3 {
+            if direction[axis] != 0.0 {
+                let boundary = pos[axis] as f32 + if direction[axis] > 0.0 { 1.0 } else { 0.0 };
+                next[axis] = (boundary - origin[axis]) / direction[axis];
+            }
+        }
+        let delta = direction.abs().recip();
+
+        let mut before = None;
+        let mut distance = enter;
+        loop {
+            let cell = self.cells[self.pos_to_index(pos)];
+            if !cell.is_dead() || cell.obstacle {
+                return Some(RayHit { pos, before, distance });
+            }
+            let axis = next.min_position();
+            distance = next[axis];
+            before = Some(pos);
+            pos[axis] += step[axis];
+            next[axis] += delta[axis];
+            if distance > exit || !self.in_bounds(pos) {
+                return None;
+            }
+        }
+    }
+
+    /// Spawn a dense cluster of cells in the center, placed by `rng` so a seed replays the same start
+    pub fn spawn_center_cluster(&mut self, rule: &Rule, max_state: u8, radius: i32, amount: usize, rng: &mut impl Rng) {
+        self.wake_all();
+        let center = self.size / 2;
+
+        for _ in 0..amount {
+            let pos = IVec3::new(
+                center + rng.random_range(-radius..=radius),
+                center + rng.random_range(-radius..=radius),
+                center + rng.random_range(-radius..=radius),
+            );
+
+            let wrapped_pos = self.wrap(pos);
+            let index = self.pos_to_index(wrapped_pos);
+
+            if self.cells[index].is_dead() && self.can_live(index) {
+                self.cells[index].spawn(max_state);
+                self.track_cell(index, 0, max_state);
+                // Update neighbor counts for surrounding cells
+                if rule.counts_as_neighbor(max_state) {
+                    self.update_neighbors(rule, index, true);
+                }
+            }
+        }
+        self.sync_halo();
+    }
+
+    /// Spawn a dense cluster of cells in the center, each at its zone rule's max state
+    pub fn spawn_zoned_cluster(&mut self, zones: &ZonedRules, radius: i32, amount: usize, rng: &mut impl Rng) {
+        self.seed_zoned(&SeedPattern::CenterCluster { radius, amount }, &zones.rules, rng);
+    }
+
+    /// Bring the cells of `pattern` to life at `rule`'s birth state, `rng` placing the random ones
+    pub fn seed(&mut self, pattern: &SeedPattern, rule: &Rule, rng: &mut impl Rng) {
+        self.seed_zoned(pattern, std::slice::from_ref(rule), rng);
+    }
+
+    /// Seed a zoned grid with `pattern`, each cell born at its zone rule's birth state
+    pub fn seed_zoned(&mut self, pattern: &SeedPattern, rules: &[Rule], rng: &mut impl Rng) {
+        // Only the grid knows which cells can live, so a density fill leaves out the closed ones
+        if let SeedPattern::Density { region, fraction } = pattern {
+            self.seed_density(rules, region, *fraction, rng);
+            return;
+        }
+        self.wake_all();
+        pattern.place(self.size, rng, |pos| {
+            let index = self.pos_to_index(self.wrap(pos));
+            if self.cells[index].is_dead() && self.can_live(index) {
+                let rule = zone_rule(rules, self.cells[index].zone);
+                self.cells[index].spawn(rule.birth_state());
+                self.track_cell(index, 0, rule.birth_state());
+                if rule.counts_as_neighbor(rule.birth_state()) {
+                    self.update_neighbors(rule, index, true);
+                }
+            }
+        });
+        self.sync_halo();
+    }
+
+    /// Make room for per-species neighbor counts, rebuilding them if the species count changed
+    fn ensure_species(&mut self, ecosystem: &Ecosystem) {
+        if self.species_count != ecosystem.species.len() {
+            self.recount_species(ecosystem);
+        }
+    }
+
+    /// Update per-species neighbor counts when a cell starts or stops counting as a neighbor
+    /// The cell's species decides the neighborhood (and weights) it counts towards
+    fn update_species_neighbors(&mut self, ecosystem: &Ecosystem, index: usize, increment: bool) {
+        let species = self.cells[index].species as usize;
+        let method = &ecosystem.species[species].rule.neighbor_method;
+        let weights = method.weights();
+        let pos = self.index_to_pos(index);
+
+        for (i, &offset) in method.get_neighbors().iter().enumerate() {
+            let weight = weights.map_or(1, |weights| weights[i]);
+            let Some(neighbor_index) = self.neighbor_index(pos, offset) else {
+                continue;
+            };
+            let slot = neighbor_index * self.species_count + species;
+            // The plain neighbor count tracks all species, e.g. for ColorMethod::Neighbor
+            if increment {
+                self.species_neighbors[slot] += weight;
+                self.cells[neighbor_index].neighbors += weight;
+            } else {
+                self.species_neighbors[slot] -= weight;
+                self.cells[neighbor_index].neighbors -= weight;
+            }
         }
     }
 
-    /// Convert 3D position to 1D index
-    #[inline]
-    fn pos_to_index(&self, pos: IVec3) -> usize {
-        let x = pos.x as usize;
-        let y = pos.y as usize;
-        let z = pos.z as usize;
-        let size = self.size as usize;
-        x + y * size + z * size * size
-    }
-
-    /// Convert 1D index to 3D position
-    #[inline]
-    fn index_to_pos(&self, index: usize) -> IVec3 {
-        let size = self.size;
-        IVec3::new(
-            (index as i32) % size,
-            (index as i32) / size % size,
-            (index as i32) / size / size
-        )
-    }
+    /// Rebuild all per-species neighbor counts, e.g. after the ecosystem changed
+    /// Cells of removed species die, states are clamped to their species' state count
+    pub fn recount_species(&mut self, ecosystem: &Ecosystem) {
+        self.species_count = ecosystem.species.len();
+        self.species_neighbors = vec![0; self.cells.len() * self.species_count];
 
-    /// Wrap position to handle toroidal boundaries
-    #[inline]
-    fn wrap(&self, pos: IVec3) -> IVec3 {
-        let size = self.size;
-        IVec3::new(
-            ((pos.x % size) + size) % size,
-            ((pos.y % size) + size) % size,
-            ((pos.z % size) + size) % size,
-        )
+        for cell in self.cells.iter_mut() {
+            cell.neighbors = 0;
+            match ecosystem.species.get(cell.species as usize) {
+                Some(species) => cell.value = cell.value.min(species.rule.states),
+                None => *cell = Cell { value: 0, species: 0, ..*cell },
+            }
+        }
+        for index in 0..self.cells.len() {
+            let cell = self.cells[index];
+            if !cell.is_dead() && ecosystem.species[cell.species as usize].rule.counts_as_neighbor(cell.value) {
+                self.update_species_neighbors(ecosystem, index, true);
+            }
+        }
+        self.retrack();
     }
 
-    /// Update neighbor counts when a cell transitions to/from max_state
-    fn update_neighbors(&mut self, rule: &Rule, index: usize, increment: bool) {
-        let pos = self.index_to_pos(index);
+    /// Advance one generation of a multi-species grid, each species following its own rule
+    /// A dead cell is born as the species with the highest neighbor count among those whose birth
+    /// rule matches (lowest index on ties). Pattern rules are not used in multi-species grids
+    pub fn step_species(&mut self, ecosystem: &Ecosystem, rng: &mut impl Rng) -> StepChanges {
+        self.ensure_species(ecosystem);
+        let species_count = self.species_count;
+        let mut changes = StepChanges::default();
+        self.generation += 1;
+        self.active_cells = self.cells.len();
 
-        for &offset in rule.neighbor_method.get_neighbors() {
-            let neighbor_pos = self.wrap(pos + offset);
-            let neighbor_index = self.pos_to_index(neighbor_pos);
+        for index in 0..self.cells.len() {
+            if !self.can_live(index) {
+                continue;
+            }
+            let counts = &self.species_neighbors[index * species_count..(index + 1) * species_count];
+            let cell = &mut self.cells[index];
+            let rule = &ecosystem.species[cell.species as usize].rule;
+            let counted = !cell.is_dead() && rule.counts_as_neighbor(cell.value);
+            let value = cell.value;
 
-            if increment {
-                self.cells[neighbor_index].neighbors += 1;
+            if cell.is_dead() {
+                let mut born: Option<(usize, u16)> = None;
+                for (species, candidate) in ecosystem.species.iter().enumerate() {
+                    let neighbors = ecosystem.effective_count(species, counts);
+                    if candidate.rule.should_birth(neighbors)
+                        && born.is_none_or(|(_, best)| neighbors > best)
+                        && roll(rng, candidate.rule.birth_probability(neighbors))
+                    {
+                        born = Some((species, neighbors));
+                    }
+                }
+                if let Some((species, _)) = born {
+                    cell.species = species as u8;
+                    cell.spawn(ecosystem.species[species].rule.birth_state());
+                }
             } else {
-                self.cells[neighbor_index].neighbors -= 1;
+                let neighbors = ecosystem.effective_count(cell.species as usize, counts);
+                let expired = cell.grow_older(rule);
+                let survives = expired.is_none()
+                    && cell.value == rule.birth_state()
+                    && rule.should_survive(neighbors)
+                    && roll(rng, rule.survival_probability(neighbors));
+                if expired == Some(AgeAction::Die) {
+                    cell.value = 0;
+                } else if !survives {
+                    cell.decay(rule);
+                }
             }
+
+            // A newborn may belong to a different species than the dead cell it replaced
+            let rule = &ecosystem.species[cell.species as usize].rule;
+            match (counted, !cell.is_dead() && rule.counts_as_neighbor(cell.value)) {
+                (false, true) => changes.spawns.push(index),
+                (true, false) => changes.deaths.push(index),
+                _ => {}
+            }
+            // A living cell keeps its species, so the hash key of its old state is the same species' key
+            let after = cell.value;
+            if after != value {
+                self.track_cell(index, value, after);
+            }
+        }
+
+        for &index in &changes.spawns {
+            self.update_species_neighbors(ecosystem, index, true);
+        }
+        for &index in &changes.deaths {
+            self.update_species_neighbors(ecosystem, index, false);
         }
+        changes
     }
 
-    /// Spawn a dense cluster of cells in the center
-    pub fn spawn_center_cluster(&mut self, rule: &Rule, max_state: u8, radius: i32, amount: usize) {
-        let mut rng = rand::rng();
-        let center = self.size / 2;
+    /// Spawn one random cluster per species, spread evenly on a ring around the grid center
+    pub fn spawn_species_clusters(&mut self, ecosystem: &Ecosystem, radius: i32, amount: usize, rng: &mut impl Rng) {
+        self.ensure_species(ecosystem);
+        let center = Vec3::splat(self.size as f32 / 2.0);
+        let ring = if ecosystem.species.len() > 1 { radius as f32 * 1.5 } else { 0.0 };
 
-        for _ in 0..amount {
-            let pos = IVec3::new(
-                center + rng.random_range(-radius..=radius),
-                center + rng.random_range(-radius..=radius),
-                center + rng.random_range(-radius..=radius),
-            );
+        for (species, member) in ecosystem.species.iter().enumerate() {
+            let angle = species as f32 / ecosystem.species.len() as f32 * std::f32::consts::TAU;
+            let cluster_center = (center + Vec3::new(angle.cos(), angle.sin(), 0.0) * ring).as_ivec3();
 
-            let wrapped_pos = self.wrap(pos);
-            let index = self.pos_to_index(wrapped_pos);
+            for _ in 0..amount {
+                let pos = cluster_center + IVec3::new(
+                    rng.random_range(-radius..=radius),
+                    rng.random_range(-radius..=radius),
+                    rng.random_range(-radius..=radius),
+                );
+                let index = self.pos_to_index(self.wrap(pos));
 
-            if self.cells[index].is_dead() {
-                self.cells[index].value = max_state;
-                // Update neighbor counts for surrounding cells
-                self.update_neighbors(rule, index, true);
+                if self.cells[index].is_dead() && self.can_live(index) {
+                    self.cells[index].species = species as u8;
+                    self.cells[index].spawn(member.rule.birth_state());
+                    self.track_cell(index, 0, member.rule.birth_state());
+                    if member.rule.counts_as_neighbor(member.rule.birth_state()) {
+                        self.update_species_neighbors(ecosystem, index, true);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Advance one generation of a cyclic automaton: a cell in state s moves to s + 1 (wrapping to 0)
+    /// when enough neighbors are already in s + 1. Cells that advanced are reported as spawns,
+    /// cells that wrapped back to 0 (and stop rendering) as deaths
+    pub fn step_cyclic(&mut self, rule: &CyclicRule) -> StepChanges {
+        let offsets = rule.neighbor_method.get_neighbors();
+        let weights = rule.neighbor_method.weights();
+        let mut changes = StepChanges::default();
+        self.generation += 1;
+        let generation = self.generation;
+        self.active_cells = self.cells.len();
+
+        // Count successors against the old states before any cell advances
+        let successors: Vec<u16> = (0..self.cells.len())
+            .map(|index| {
+                // Cells outside the domain never advance, skip their lookups
+                if !self.is_open(index) {
+                    return 0;
+                }
+                let pos = self.index_to_pos(index);
+                let successor = rule.successor(self.cells[index].value);
+                offsets
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &offset)| {
+                        self.neighbor_index(pos, offset).is_some_and(|neighbor| {
+                            !self.cells[neighbor].obstacle && self.cells[neighbor].value == successor
+                        })
+                    })
+                    .map(|(i, _)| weights.map_or(1, |weights| weights[i]))
+                    .sum()
+            })
+            .collect();
+
+        let mask = self.mask.as_deref();
+        let mut hash = self.hash;
+        let mut lives = Vec::new();
+        for (index, (cell, count)) in self.cells.iter_mut().zip(successors).enumerate() {
+            // The cached count holds successor neighbors in cyclic mode, e.g. for ColorMethod::Neighbor
+            cell.neighbors = count;
+            // Cells outside the domain and obstacles stay in state 0
+            if count >= rule.threshold && !cell.obstacle && mask.is_none_or(|mask| mask[index]) {
+                let value = cell.value;
+                cell.value = rule.successor(value);
+                hash ^= cell_key(index, cell.species, value) ^ cell_key(index, cell.species, cell.value);
+                if let Some(activity) = &mut self.activity {
+                    activity.flip(index, generation);
+                }
+                if (value == 0) != cell.is_dead() {
+                    lives.push(index);
+                }
+                if cell.is_dead() {
+                    changes.deaths.push(index);
+                } else {
+                    changes.spawns.push(index);
+                }
             }
         }
+        self.hash = hash;
+        for index in lives {
+            let alive = !self.cells[index].is_dead();
+            self.track_life(index, !alive, alive);
+        }
+
+        changes
+    }
+
+    /// Give every cell a random state in 0..states, the usual start for cyclic automata
+    /// Cells outside the domain are left in state 0
+    pub fn fill_random_states(&mut self, states: u8, rng: &mut impl Rng) {
+        self.wake_all();
+        let mask = self.mask.as_deref();
+        for (index, cell) in self.cells.iter_mut().enumerate() {
+            let value = if mask.is_none_or(|mask| mask[index]) && !cell.obstacle { rng.random_range(0..states.max(1)) } else { 0 };
+            *cell = Cell { value, species: 0, zone: cell.zone, ticks: 0, age: 0, neighbors: 0, faces: 0, obstacle: cell.obstacle };
+        }
+        self.retrack();
     }
 
-    /// Build instance data for rendering
-    pub fn build_instances(&self, colors: &CellColors, max_state: u8) -> Vec<crate::rendering::InstanceData> {
+    /// Build instance data for rendering, with neighbor-based colors relative to `max_neighbors` (see `max_neighbors`)
+    pub fn build_instances(&self, colors: &CellColors, max_state: u8, max_neighbors: u16) -> Vec<crate::rendering::InstanceData> {
         let grid_center = Vec3::splat((self.size - 1) as f32 * 0.5);
         let max_distance = grid_center.length(); // Max distance from center to corner
         let mut instance_data = Vec::new();
+        let components = (colors.method == ColorMethod::Component).then(|| Components::label(self));
+        // Counts spread over orders of magnitude, so they map to colors on a log scale
+        let activity = self.activity.as_ref();
+        let most_flips = match (colors.method, activity) {
+            (ColorMethod::Activity, Some(activity)) => {
+                self.cells.iter().zip(&activity.flips).filter(|(cell, _)| !cell.is_dead()).map(|(_, &flips)| flips).max().unwrap_or(0)
+            }
+            _ => 0,
+        };
+        // Faces on the grid's edges are always in view, even where the grid wraps. Hidden cells show
+        // through translucent ones, between smaller shapes and wherever the grid is cut open
+        let surface_only = colors.surface_only && colors.buried_hidden && colors.scale == ScaleCurve::Uniform && !colors.translucent;
+        let hidden = |pos: IVec3| {
+            VON_NEUMANN_NEIGHBORS.iter().all(|&face| {
+                let neighbor = pos + face;
+                self.in_bounds(neighbor) && {
+                    let cell = self.cells[self.pos_to_index(neighbor)];
+                    cell.obstacle || !cell.is_dead()
+                }
+            })
+        };
 
         for (index, cell) in self.cells.iter().enumerate() {
-            if cell.value > 0 {
+            if cell.obstacle {
+                instance_data.push(crate::rendering::InstanceData {
+                    position: self.index_to_pos(index).as_vec3() - grid_center,
+                    scale: 1.0,
+                    color: colors.obstacle_color.to_srgba().to_f32_array(),
+                    glow: 0.0,
+                });
+            } else if cell.value > 0 {
                 let pos = self.index_to_pos(index);
+                if surface_only && hidden(pos) {
+                    continue;
+                }
                 let position = pos.as_vec3() - grid_center;
 
                 // Calculate color based on the selected method
@@ -139,21 +2554,50 @@ impl Grid {
                     }
                     ColorMethod::Neighbor => {
                         // Interpolate based on neighbor count (0=death_color, max=birth_color)
-                        let max_neighbors = 26.0; // Moore neighborhood
-                        cell.neighbors as f32 / max_neighbors
+                        cell.neighbors as f32 / max_neighbors as f32
                     }
                     ColorMethod::Single => {
                         // Just use birth_color for all cells
                         1.0
                     }
+                    ColorMethod::Species => {
+                        // Fade from the species color (max_state) towards death_color
+                        cell.value as f32 / max_state as f32
+                    }
+                    ColorMethod::Component => 1.0,
+                    ColorMethod::Activity => {
+                        let flips = activity.map_or(0, |activity| activity.flips[index]);
+                        (flips as f32).ln_1p() / (most_flips.max(1) as f32).ln_1p()
+                    }
+                    ColorMethod::Rainbow | ColorMethod::Palette => 1.0,
+                    ColorMethod::Velocity => {
+                        let since_change = activity.map_or(self.generation, |activity| activity.since_change(index, self.generation));
+                        (-(since_change as f32) / colors.cooling.max(f32::EPSILON)).exp()
+                    }
                 };
 
-                let color = colors.lerp_color(t);
+                let color = match (colors.method, &components) {
+                    (ColorMethod::Species, _) => colors.lerp_to(colors.species_color(cell.species), t),
+                    (ColorMethod::Rainbow, _) => colors.rainbow(position, grid_center),
+                    (ColorMethod::Palette, _) => colors.palette_color(cell.value, max_state),
+                    // Until `color_components` recolors them by their tracked ids
+                    (ColorMethod::Component, Some(components)) => component_color(components.label_at(pos).unwrap_or_default() as u32 + 1),
+                    _ => colors.lerp_color(t),
+                };
+                let mut color = color.to_srgba();
+                if colors.translucent {
+                    color.alpha *= colors.opacity * cell.value as f32 / max_state as f32;
+                }
+                if colors.occlusion > 0.0 {
+                    let occlusion = 1.0 - colors.occlusion * (cell.neighbors as f32 / max_neighbors as f32).min(1.0);
+                    color = Srgba { red: color.red * occlusion, green: color.green * occlusion, blue: color.blue * occlusion, ..color };
+                }
 
                 instance_data.push(crate::rendering::InstanceData {
                     position,
-                    scale: 1.0,
-                    color: color.to_srgba().to_f32_array(),
+                    scale: colors.scale.scale(cell.value, max_state),
+                    color: color.to_f32_array(),
+                    glow: colors.glow * (-(cell.age as f32) / colors.glow_fade.max(f32::EPSILON)).exp(),
                 });
             }
         }
@@ -165,13 +2609,158 @@ impl Grid {
     pub fn cell_count(&self) -> usize {
         self.cells.iter().filter(|c| !c.is_dead()).count()
     }
+
+    /// Position and state of every living cell, in storage order
+    pub fn iter_alive(&self) -> impl Iterator<Item = (IVec3, u8)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| !cell.is_dead())
+            .map(|(index, cell)| (self.index_to_pos(index), cell.value))
+    }
+
+    /// State of the cell at `pos`, None past the edges
+    pub fn get(&self, pos: IVec3) -> Option<u8> {
+        self.in_bounds(pos).then(|| self.cells[self.pos_to_index(pos)].value)
+    }
+
+    /// Position and state of the living cells in the box from `min` to `max` (inclusive, clipped to the grid)
+    pub fn cells_in_box(&self, min: IVec3, max: IVec3) -> impl Iterator<Item = (IVec3, u8)> + '_ {
+        self.clip_box(min, max)
+            .into_iter()
+            .flat_map(|(min, max)| {
+                (min.z..=max.z).flat_map(move |z| (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z))))
+            })
+            .filter_map(|pos| Some((pos, self.get(pos).filter(|&state| state > 0)?)))
+    }
+
+    /// Position and state of the living cells within `radius` of `center`
+    pub fn cells_in_sphere(&self, center: Vec3, radius: f32) -> impl Iterator<Item = (IVec3, u8)> + '_ {
+        let (min, max) = ((center - radius).ceil().as_ivec3(), (center + radius).floor().as_ivec3());
+        self.cells_in_box(min, max).filter(move |(pos, _)| pos.as_vec3().distance_squared(center) <= radius * radius)
+    }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CellColors {
+    #[serde(with = "hex_color")]
     pub birth_color: Color,
+    #[serde(with = "hex_color")]
     pub death_color: Color,
     pub method: ColorMethod,
+    /// Color of each species for ColorMethod::Species, cycled when there are more species
+    #[serde(with = "hex_color_list")]
+    pub species_colors: Vec<Color>,
+    /// Fade cells' alpha by their state (continuous engines by their value instead of cube size), so decaying
+    /// cells fade out. Needs `BlendAlpha`
+    pub translucent: bool,
+    /// Alpha of the highest state (or a continuous value of 1) with `translucent`, lower to see through
+    /// solid structures
+    pub opacity: f32,
+    /// Color of static obstacle cells
+    #[serde(with = "hex_color")]
+    pub obstacle_color: Color,
+    /// Axis ColorMethod::Rainbow spreads its hues along, None for the distance from the grid center
+    pub hue_axis: Option<Axis>,
+    /// Hues in degrees ColorMethod::Rainbow runs through, from the low end of the axis (or the center) to
+    /// the high end, e.g. (0, 300) for red to magenta
+    pub hue_range: (f32, f32),
+    /// Color stops as (position in 0..1, color) that colors run through instead of death_color to
+    /// birth_color, e.g. for state ramps of many states. Empty for the two-color lerp
+    #[serde(with = "hex_gradient", skip_serializing_if = "Vec::is_empty")]
+    pub gradient: Vec<(f32, Color)>,
+    /// Color of each state for ColorMethod::Palette from the newborn (highest) state down, e.g. alive,
+    /// dying, refractory for Brian's Brain. States past the end take the last color
+    #[serde(with = "hex_color_list", skip_serializing_if = "Vec::is_empty")]
+    pub palette: Vec<Color>,
+    /// Generations it takes a cell's heat to cool to about a third in ColorMethod::Velocity
+    pub cooling: f32,
+    /// How cubes shrink as cells decay through their states, e.g. Power(0.5)
+    pub scale: ScaleCurve,
+    /// Leave out cells hidden inside solid structures, whose six faces all touch living cells or obstacles.
+    /// Only while `buried_hidden` holds and with full-size opaque cubes (uniform `scale`, not `translucent`);
+    /// the greedy renderer already skips hidden faces
+    pub surface_only: bool,
+    /// Whether cells inside solid structures are out of sight: cells are drawn as plain cubes (no other
+    /// `CellMeshKind`, custom mesh or LOD points) and no slice plane, clip box or exploded view opens the
+    /// grid up. Kept up to date by `track_buried_cells`, never saved
+    #[serde(skip)]
+    pub buried_hidden: bool,
+    /// Emissive brightness of newborn cells, which flash and bloom as they're born (0 = off)
+    pub glow: f32,
+    /// Generations it takes a newborn cell's glow to fade to about a third
+    pub glow_fade: f32,
+    /// How much cells darken when surrounded by living neighbors, from the cached neighbor count, so crevices
+    /// and the insides of clusters read as occluded (0 = off, 1 = black when every neighbor the rule's
+    /// neighborhood counts lives)
+    pub occlusion: f32,
+    /// Darken the edges of every cube face so neighboring cells of the same color stay apart without the
+    /// wireframe. Needs `CellOutlines`, and meshes whose faces each span the whole UV square like cubes
+    pub outlines: bool,
+}
+
+/// Serde helper storing colors as sRGB hex strings ("#FFFF00")
+pub(crate) mod hex_color {
+    use bevy::color::{Color, Srgba};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&color.to_srgba().to_hex())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Srgba::hex(&hex)
+            .map(Color::from)
+            .map_err(|e| serde::de::Error::custom(format!("invalid color '{}': {}", hex, e)))
+    }
+}
+
+/// Serde helper storing a list of colors as sRGB hex strings
+mod hex_color_list {
+    use bevy::color::{Color, Srgba};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(colors: &[Color], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: Vec<String> = colors.iter().map(|color| color.to_srgba().to_hex()).collect();
+        hex.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Color>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| {
+                Srgba::hex(hex)
+                    .map(Color::from)
+                    .map_err(|e| serde::de::Error::custom(format!("invalid color '{}': {}", hex, e)))
+            })
+            .collect()
+    }
+}
+
+/// Serde helper storing gradient stops as (position, sRGB hex string) pairs
+mod hex_gradient {
+    use bevy::color::{Color, Srgba};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(stops: &[(f32, Color)], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: Vec<(f32, String)> = stops.iter().map(|(at, color)| (*at, color.to_srgba().to_hex())).collect();
+        hex.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(f32, Color)>, D::Error> {
+        let mut stops = Vec::<(f32, String)>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(at, hex)| {
+                Srgba::hex(&hex)
+                    .map(|color| (at, Color::from(color)))
+                    .map_err(|e| serde::de::Error::custom(format!("invalid color '{}': {}", hex, e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(stops)
+    }
 }
 
 impl Default for CellColors {
@@ -180,28 +2769,371 @@ impl Default for CellColors {
             birth_color: Color::srgb(1.0, 1.0, 0.0),
             death_color: Color::srgb(1.0, 0.0, 0.0),
             method: ColorMethod::StateLerp,
+            species_colors: vec![
+                Color::srgb(1.0, 1.0, 0.0),
+                Color::srgb(0.0, 0.6, 1.0),
+                Color::srgb(0.2, 1.0, 0.3),
+                Color::srgb(1.0, 0.3, 0.9),
+            ],
+            translucent: false,
+            opacity: 1.0,
+            obstacle_color: Color::srgb(0.4, 0.4, 0.45),
+            hue_axis: None,
+            hue_range: (0.0, 300.0),
+            gradient: Vec::new(),
+            palette: Vec::new(),
+            cooling: 8.0,
+            scale: ScaleCurve::Uniform,
+            surface_only: false,
+            buried_hidden: true,
+            glow: 0.0,
+            glow_fade: 2.0,
+            occlusion: 0.0,
+            outlines: false,
         }
     }
 }
 
 impl CellColors {
-    /// Helper to interpolate between two colors
-    fn lerp_color(&self, t: f32) -> Color {
-        let c1 = self.death_color.to_srgba();
-        let c2 = self.birth_color.to_srgba();
-        Color::srgb(
-            c1.red * (1.0 - t) + c2.red * t,
-            c1.green * (1.0 - t) + c2.green * t,
-            c1.blue * (1.0 - t) + c2.blue * t,
-        )
+    /// Color at `t` along the gradient, or from death_color (t = 0) to birth_color (t = 1) without one
+    pub fn lerp_color(&self, t: f32) -> Color {
+        let Some(&(first, first_color)) = self.gradient.first() else {
+            return self.lerp_to(self.birth_color, t);
+        };
+        if t <= first {
+            return first_color;
+        }
+        // Stops are sorted by position, the last one holds past the end
+        let next = self.gradient.iter().position(|&(at, _)| at > t).unwrap_or(self.gradient.len());
+        let (from, from_color) = self.gradient[next - 1];
+        let Some(&(to, to_color)) = self.gradient.get(next) else {
+            return from_color;
+        };
+        lerp_srgb(from_color, to_color, (t - from) / (to - from))
+    }
+
+    /// ColorMethod::Palette color of `state` when `max_state` is the newborn state, the lerp without a palette
+    pub fn palette_color(&self, state: u8, max_state: u8) -> Color {
+        let Some(&last) = self.palette.last() else {
+            return self.lerp_color(state as f32 / max_state.max(1) as f32);
+        };
+        self.palette.get(max_state.saturating_sub(state) as usize).copied().unwrap_or(last)
+    }
+
+    /// Use `stops` as the gradient, sorted by position
+    pub fn with_gradient(mut self, stops: impl IntoIterator<Item = (f32, Color)>) -> Self {
+        self.gradient = stops.into_iter().collect();
+        self.gradient.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self
+    }
+
+    /// Black through red and orange to pale yellow, for ramps of dying embers
+    pub fn fire_gradient() -> Vec<(f32, Color)> {
+        vec![
+            (0.0, Color::srgb(0.05, 0.0, 0.0)),
+            (0.35, Color::srgb(0.7, 0.05, 0.0)),
+            (0.65, Color::srgb(1.0, 0.45, 0.0)),
+            (0.85, Color::srgb(1.0, 0.8, 0.2)),
+            (1.0, Color::srgb(1.0, 1.0, 0.75)),
+        ]
+    }
+
+    /// Deep navy through blue and cyan to white
+    pub fn ice_gradient() -> Vec<(f32, Color)> {
+        vec![
+            (0.0, Color::srgb(0.0, 0.02, 0.15)),
+            (0.4, Color::srgb(0.0, 0.25, 0.7)),
+            (0.75, Color::srgb(0.3, 0.8, 1.0)),
+            (1.0, Color::srgb(0.95, 1.0, 1.0)),
+        ]
+    }
+
+    /// ColorMethod::Rainbow color of a cell at `position` from the center of a grid whose center is `grid_center`
+    /// cells from the origin corner
+    pub fn rainbow(&self, position: Vec3, grid_center: Vec3) -> Color {
+        let t = match self.hue_axis {
+            Some(axis) => {
+                let half = grid_center[axis.index()].max(f32::EPSILON);
+                (position[axis.index()] / half + 1.0) * 0.5
+            }
+            None => position.length() / grid_center.length().max(f32::EPSILON),
+        };
+        let (from, to) = self.hue_range;
+        Color::hsl((from + (to - from) * t.clamp(0.0, 1.0)).rem_euclid(360.0), 0.9, 0.55)
+    }
+
+    /// Color of a species, cycling through species_colors (birth_color if none are set)
+    pub fn species_color(&self, species: u8) -> Color {
+        if self.species_colors.is_empty() {
+            return self.birth_color;
+        }
+        self.species_colors[species as usize % self.species_colors.len()]
+    }
+
+    /// Interpolate from death_color (t = 0) to `target` (t = 1)
+    fn lerp_to(&self, target: Color, t: f32) -> Color {
+        lerp_srgb(self.death_color, target, t)
+    }
+}
+
+/// Interpolate two colors' sRGB channels
+fn lerp_srgb(from: Color, to: Color, t: f32) -> Color {
+    let c1 = from.to_srgba();
+    let c2 = to.to_srgba();
+    Color::srgb(
+        c1.red * (1.0 - t) + c2.red * t,
+        c1.green * (1.0 - t) + c2.green * t,
+        c1.blue * (1.0 - t) + c2.blue * t,
+    )
+}
+
+/// Press G to grow the grid by 16 cells per side (Shift+G to shrink), keeping the cells in the center
+/// Neighbor caches and zones are rebuilt for the active mode
+pub fn resize_grid(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    zones: Option<Res<ZonedRules>>,
+) {
+    const STEP: i32 = 16;
+    const MIN_SIZE: i32 = 16;
+
+    if !keys.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    let shrink = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    let new_size = if shrink { grid.size - STEP } else { grid.size + STEP }.clamp(MIN_SIZE, MAX_GRID_SIZE);
+    if new_size == grid.size {
+        return;
+    }
+
+    grid.resize(new_size, true);
+    rebuild_neighbors(&mut grid, &rule, ecosystem.as_deref(), cyclic.as_deref(), zones.as_deref());
+    println!("Resized grid to {}³ ({} living cells)", new_size, grid.cell_count());
+}
+
+/// Keep `CellColors::buried_hidden` in step with the cell shape, LOD and the slice plane, clip box and
+/// exploded view, rebuilding the instances when `surface_only` has cells to bring back or leave out
+#[allow(clippy::too_many_arguments)]
+pub fn track_buried_cells(
+    kind: Res<CellMeshKind>,
+    custom: Option<Res<CustomCellMeshDrawn>>,
+    lod: Res<CellLod>,
+    slice: Res<SlicePlane>,
+    clip_box: Res<ClipBox>,
+    exploded: Res<ExplodedView>,
+    mut colors: ResMut<CellColors>,
+    grid: Res<Grid>,
+    rule: Res<Rule>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    zones: Option<Res<ZonedRules>>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+) {
+    let buried_hidden = *kind == CellMeshKind::Cube
+        && custom.is_none()
+        && !lod.enabled
+        && !slice.enabled
+        && !clip_box.enabled
+        && !exploded.enabled;
+    if colors.buried_hidden == buried_hidden {
+        return;
+    }
+    colors.buried_hidden = buried_hidden;
+    if !colors.surface_only {
+        return;
+    }
+
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    if let Ok(mut instances) = instance_query.single_mut() {
+        let max_state = max_state(rules, ecosystem.as_deref(), cyclic.as_deref());
+        let max_neighbors = max_neighbors(rules, ecosystem.as_deref(), cyclic.as_deref());
+        instances.0 = grid.build_instances(&colors, max_state, max_neighbors);
+    }
+}
+
+/// Press F5 to save the grid to a snapshot file and F9 to load it back, resuming from that generation
+/// Loading replaces the single active rule with the snapshot's; zoned, species and cyclic grids keep theirs
+pub fn checkpoint_grid(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut grid: ResMut<Grid>,
+    mut rule: ResMut<Rule>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    zones: Option<Res<ZonedRules>>,
+) {
+    const PATH: &str = "snapshot.grid";
+
+    if keys.just_pressed(KeyCode::F5) {
+        match grid.save(PATH, &rule) {
+            Ok(()) => println!("Saved generation {} ({} living cells) to {}", grid.generation(), grid.cell_count(), PATH),
+            Err(e) => eprintln!("Failed to save {}: {}", PATH, e),
+        }
+    }
+    if !keys.just_pressed(KeyCode::F9) {
+        return;
+    }
+
+    let species = ecosystem.as_deref().map_or(1, |ecosystem| ecosystem.species.len());
+    let loaded = match grid.load(PATH, species) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Failed to load {}: {}", PATH, e);
+            return;
+        }
+    };
+    if cyclic.is_none() && ecosystem.is_none() && zones.is_none() {
+        *rule = loaded;
+    }
+    rebuild_neighbors(&mut grid, &rule, ecosystem.as_deref(), cyclic.as_deref(), zones.as_deref());
+    println!("Loaded generation {} ({} living cells) from {}: {}", grid.generation(), grid.cell_count(), PATH, *rule);
+}
+
+/// Press M to replace the active rule with a random nearby mutation
+pub fn mutate_rule(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut grid: ResMut<Grid>,
+    mut rule: ResMut<Rule>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    zones: Option<Res<ZonedRules>>,
+    mut rng: ResMut<SimRng>,
+) {
+    // Species, cyclic and zoned modes don't simulate the single active rule
+    if ecosystem.is_some() || cyclic.is_some() || zones.is_some() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyM) {
+        let mutated = rule.mutate(&mut rng.0, 0.05);
+        grid.swap_rule(&mut rule, mutated);
+        println!("Mutated rule: {}", *rule);
+    }
+}
+
+/// Press N / Shift+N to hot-swap the active rule for the next / previous catalog rule
+#[allow(clippy::too_many_arguments)]
+pub fn cycle_rule(
+    keys: Res<ButtonInput<KeyCode>>,
+    catalog: Res<RuleCatalog>,
+    mut grid: ResMut<Grid>,
+    mut rule: ResMut<Rule>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    zones: Option<Res<ZonedRules>>,
+    mut index: Local<Option<usize>>,
+) {
+    if ecosystem.is_some() || cyclic.is_some() || zones.is_some() || catalog.entries.is_empty() {
+        return;
+    }
+    if !keys.just_pressed(KeyCode::KeyN) {
+        return;
+    }
+
+    let count = catalog.entries.len();
+    let backwards = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    // Start from the active rule's catalog position when it came from the catalog
+    let current = index.or_else(|| catalog.entries.iter().position(|entry| entry.rule == *rule));
+    let next = match (current, backwards) {
+        (Some(i), false) => (i + 1) % count,
+        (Some(i), true) => (i + count - 1) % count,
+        (None, false) => 0,
+        (None, true) => count - 1,
+    };
+    *index = Some(next);
+
+    let entry = &catalog.entries[next];
+    let recounted = grid.swap_rule(&mut rule, entry.rule.clone());
+    println!(
+        "Switched to rule '{}': {}{}",
+        entry.name,
+        *rule,
+        if recounted { " (neighbors recounted)" } else { "" }
+    );
+}
+
+/// Press = / - to double or halve the noise temperature (halving the lowest step turns it off)
+pub fn adjust_temperature(keys: Res<ButtonInput<KeyCode>>, mut temperature: ResMut<Temperature>) {
+    const MIN_TEMPERATURE: f32 = 0.0001;
+
+    if keys.just_pressed(KeyCode::Equal) {
+        temperature.0 = (temperature.0 * 2.0).clamp(MIN_TEMPERATURE, 1.0);
+        println!("Temperature: {}", temperature.0);
+    }
+    if keys.just_pressed(KeyCode::Minus) {
+        temperature.0 = if temperature.0 <= MIN_TEMPERATURE { 0.0 } else { temperature.0 / 2.0 };
+        println!("Temperature: {}", temperature.0);
+    }
+}
+
+/// Press ] / [ to double or halve the generations simulated per frame
+pub fn adjust_steps_per_frame(keys: Res<ButtonInput<KeyCode>>, mut steps_per_frame: ResMut<StepsPerFrame>) {
+    const MAX_STEPS_PER_FRAME: u32 = 256;
+
+    if keys.just_pressed(KeyCode::BracketRight) {
+        steps_per_frame.0 = (steps_per_frame.0 * 2).clamp(1, MAX_STEPS_PER_FRAME);
+        println!("Steps per frame: {}", steps_per_frame.0);
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        steps_per_frame.0 = (steps_per_frame.0 / 2).max(1);
+        println!("Steps per frame: {}", steps_per_frame.0);
+    }
+}
+
+/// Rebuild the neighbor caches (and zones) for the active mode after the grid was resized or restored
+pub(crate) fn rebuild_neighbors(
+    grid: &mut Grid,
+    rule: &Rule,
+    ecosystem: Option<&Ecosystem>,
+    cyclic: Option<&CyclicRule>,
+    zones: Option<&ZonedRules>,
+) {
+    match (cyclic, ecosystem, zones) {
+        // Cyclic grids count neighbors from scratch every step
+        (Some(_), _, _) => {}
+        (None, Some(ecosystem), _) => grid.recount_species(ecosystem),
+        (None, None, Some(zones)) => grid.assign_zones(&zones.layout, &zones.rules),
+        (None, None, None) => grid.recount_neighbors(rule),
+    }
+}
+
+/// Highest cell state of the active mode, which colors map to the birth color
+pub(crate) fn max_state(rules: &[Rule], ecosystem: Option<&Ecosystem>, cyclic: Option<&CyclicRule>) -> u8 {
+    match (cyclic, ecosystem) {
+        (Some(cyclic), _) => cyclic.states.saturating_sub(1).max(1),
+        (None, Some(ecosystem)) => ecosystem.max_states(),
+        (None, None) => rules.iter().map(|rule| rule.states).max().unwrap_or(1),
     }
 }
 
+/// Largest neighbor count (or weighted sum) of the rules simulating the grid, at least 1
+pub(crate) fn max_neighbors(rules: &[Rule], ecosystem: Option<&Ecosystem>, cyclic: Option<&CyclicRule>) -> u16 {
+    let max = match (cyclic, ecosystem) {
+        (Some(cyclic), _) => cyclic.neighbor_method.max_neighbors(),
+        (None, Some(ecosystem)) => {
+            ecosystem.species.iter().map(|species| species.rule.neighbor_method.max_neighbors()).max().unwrap_or(1)
+        }
+        (None, None) => rules.iter().map(|rule| rule.neighbor_method.max_neighbors()).max().unwrap_or(1),
+    };
+    max.max(1)
+}
+
 /// Optimized simulation step using persistent neighbor counts
+#[allow(clippy::too_many_arguments)]
 pub fn simulate_step(
     mut grid: ResMut<Grid>,
     rule: Res<Rule>,
     colors: Res<CellColors>,
+    mut rng: ResMut<SimRng>,
+    temperature: Res<Temperature>,
+    steps_per_frame: Res<StepsPerFrame>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    zones: Option<Res<ZonedRules>>,
+    mut cycles: Option<ResMut<CycleDetector>>,
+    mut behavior_changes: MessageWriter<BehaviorChanged>,
+    mut stats: Option<ResMut<SimStats>>,
     mut instance_query: Query<&mut InstanceMaterialData>,
     time: Res<Time>,
     mut last_update: Local<f32>,
@@ -215,49 +3147,54 @@ pub fn simulate_step(
     *last_update = time.elapsed_secs();
 
     let frame_start = std::time::Instant::now();
-    let max_state = rule.states;
+    // Zoned grids look up each cell's rule by its zone, a single rule is one zone covering the grid
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let max_state = max_state(rules, ecosystem.as_deref(), cyclic.as_deref());
+    let max_neighbors = max_neighbors(rules, ecosystem.as_deref(), cyclic.as_deref());
 
-    // Track which cells spawned (transitioned to max_state) or died (left max_state)
-    let mut spawns = Vec::new();
-    let mut deaths = Vec::new();
+    // Phases 1 and 2 run once per generation, the instances are only rebuilt after the last one
+    let steps = steps_per_frame.0.max(1);
+    let (mut phase1_time, mut phase2_time) = (std::time::Duration::ZERO, std::time::Duration::ZERO);
+    let (mut changes, mut noise) = (StepChanges::default(), StepChanges::default());
+    for _ in 0..steps {
+        // === PHASE 1: Update cell values ===
+        // Cyclic and multi-species grids update their neighbor counts in the same pass
+        let phase1_start = std::time::Instant::now();
+        changes = match (&cyclic, &ecosystem) {
+            (Some(cyclic), _) => grid.step_cyclic(cyclic),
+            (None, Some(ecosystem)) => grid.step_species(ecosystem, &mut rng.0),
+            // Sequential update modes also update neighbor counts as they go
+            (None, None) if grid.update_mode() != UpdateMode::Synchronous => grid.step_zoned(rules, &mut rng.0),
+            (None, None) => grid.update_zoned_states(rules, &mut rng.0),
+        };
+        phase1_time += phase1_start.elapsed();
 
-    // === PHASE 1: Update cell values ===
-    let phase1_start = std::time::Instant::now();
-    for (index, cell) in grid.cells.iter_mut().enumerate() {
-        if cell.is_dead() {
-            // Dead cell - check birth rule using CACHED neighbor count
-            if rule.should_birth(cell.neighbors) {
-                cell.value = max_state;
-                spawns.push(index);
-            }
+        // === PHASE 2: Update neighbor counts ===
+        let phase2_start = std::time::Instant::now();
+        noise = if cyclic.is_some() || ecosystem.is_some() {
+            // Noise flips only support single-rule grids
+            StepChanges::default()
         } else {
-            // Living cell
-            // Only cells at max_state can survive if they meet the survival rule
-            if cell.value < max_state || !rule.should_survive(cell.neighbors) {
-                // Track if this cell is leaving max_state (affects neighbor counts)
-                if cell.value == max_state {
-                    deaths.push(index);
-                }
-                // Decay
-                cell.value -= 1;
+            if grid.update_mode() == UpdateMode::Synchronous {
+                grid.apply_zoned_changes(rules, &changes);
             }
-        }
-    }
-    let phase1_time = phase1_start.elapsed();
+            grid.apply_zoned_noise(rules, temperature.0, &mut rng.0)
+        };
+        phase2_time += phase2_start.elapsed();
 
-    // === PHASE 2: Update neighbor counts ===
-    let phase2_start = std::time::Instant::now();
-    for index in spawns.iter() {
-        grid.update_neighbors(&rule, *index, true);
-    }
-    for index in deaths.iter() {
-        grid.update_neighbors(&rule, *index, false);
+        // Every generation is hashed, so periods aren't hidden by steps per frame
+        if let Some(behavior) = cycles.as_deref_mut().and_then(|cycles| cycles.observe(&grid)) {
+            println!("Generation {}: the run is {}", grid.generation(), behavior);
+            behavior_changes.write(BehaviorChanged { behavior, generation: grid.generation() });
+        }
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.record(&grid, max_state);
+        }
     }
-    let phase2_time = phase2_start.elapsed();
 
     // === PHASE 3: Rebuild instance data ===
     let phase3_start = std::time::Instant::now();
-    let instance_data = grid.build_instances(&colors, max_state);
+    let instance_data = grid.build_instances(&colors, max_state, max_neighbors);
     let phase3_time = phase3_start.elapsed();
 
     // === PHASE 4: Update GPU buffer ===
@@ -275,13 +3212,205 @@ pub fn simulate_step(
     let fps = if delta_secs > 0.0 { 1.0 / delta_secs } else { 0.0 };
 
     // Print performance stats every update
-    println!("=== Performance Profile ({:.0} FPS) ===", fps);
+    println!("=== Performance Profile ({:.0} FPS, {} generation{} per frame) ===", fps, steps, if steps == 1 { "" } else { "s" });
     println!("Total:      {:6.2}ms", total_time.as_secs_f64() * 1000.0);
-    println!("Phase 1:    {:6.2}ms  (update {} cells)", phase1_time.as_secs_f64() * 1000.0, grid.cells.len());
-    println!("Phase 2:    {:6.2}ms  (update neighbors: {} spawns, {} deaths)",
-             phase2_time.as_secs_f64() * 1000.0, spawns.len(), deaths.len());
+    println!("Phase 1:    {:6.2}ms  (update {} of {} cells)", phase1_time.as_secs_f64() * 1000.0, grid.active_cells(), grid.cells.len());
+    println!("Phase 2:    {:6.2}ms  (update neighbors: {} spawns, {} deaths, noise {} spawns, {} deaths)",
+             phase2_time.as_secs_f64() * 1000.0, changes.spawns.len(), changes.deaths.len(),
+             noise.spawns.len(), noise.deaths.len());
     println!("Phase 3:    {:6.2}ms  (build {} instances)", phase3_time.as_secs_f64() * 1000.0, living_cells);
     println!("Phase 4:    {:6.2}ms  (upload to GPU)", phase4_time.as_secs_f64() * 1000.0);
     println!("Frame time: {:6.2}ms (render + overhead)", delta_secs * 1000.0);
     println!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::{RuleValue, ShellRule};
+
+    // A power of two and a multiple of 64, so Morton order and packed counting both apply
+    const SIZE: i32 = 64;
+    const STEPS: usize = 6;
+
+    // Rules and boundaries every fast path has to match the plain path in: (name, rule, boundaries, masked)
+    fn scenarios() -> Vec<(&'static str, Rule, Boundaries, bool)> {
+        let two_shell = Rule::new(&[], &[], 2, NeighborMethod::Moore).with_shells(ShellRule {
+            face_survival: RuleValue::from_range(1, 3),
+            face_birth: RuleValue::new(&[1, 2]),
+            outer_survival: RuleValue::from_range(2, 10),
+            outer_birth: RuleValue::from_range(3, 5),
+        });
+        vec![
+            ("wrapping", Rule::builder(), Boundaries::wrap(), false),
+            ("closed", Rule::builder(), Boundaries::closed(), false),
+            ("masked", Rule::builder(), Boundaries::wrap(), true),
+            ("two-shell", two_shell, Boundaries::slab(Axis::Y), false),
+        ]
+    }
+
+    // Linear grid stepping on one core and counting neighbors one changed cell at a time
+    fn plain(boundaries: Boundaries, masked: bool) -> Grid {
+        let grid = Grid::new(SIZE).with_boundaries(boundaries).with_parallel(false).with_packed_counting(false);
+        if !masked {
+            return grid;
+        }
+        // A ball poking out of the grid, so the mask cuts through chunks and across the wrap
+        let center = Vec3::splat(SIZE as f32 * 0.5);
+        grid.with_mask(|pos| pos.as_vec3().distance(center) < SIZE as f32 * 0.55)
+    }
+
+    // Whether `pos` is in the seeded cube, which sits across the corner of the grid and so across the wrap
+    fn in_seed_cube(pos: IVec3) -> bool {
+        (pos + IVec3::splat(12)).rem_euclid(IVec3::splat(SIZE)).cmplt(IVec3::splat(40)).all()
+    }
+
+    // Fill the seeded cube with random cells from a fixed seed
+    fn seed(grid: &mut Grid, rule: &Rule) {
+        let mut rng = StdRng::seed_from_u64(1);
+        grid.seed_with(rule, |pos| if in_seed_cube(pos) && rng.random_bool(0.4) { rule.states } else { 0 });
+    }
+
+    // Check the hash and occupancy each step keeps up to date against a full rescan of the cells
+    fn assert_tracked(grid: &mut Grid, step: usize) {
+        let hash = grid.state_hash();
+        let occupancy = grid.occupancy.clone();
+        grid.retrack();
+        assert_eq!(hash, grid.state_hash(), "incremental hash drifted from a full rescan after {} steps", step + 1);
+        assert!(
+            occupancy.count == grid.occupancy.count && occupancy.sum == grid.occupancy.sum && occupancy.planes == grid.occupancy.planes,
+            "incremental occupancy drifted from a full rescan after {} steps", step + 1
+        );
+    }
+
+    // States after every step of the seeded `grid`. The z layers away from the seeded cube start out dormant,
+    // unless `wake` makes every step visit every chunk
+    fn run(mut grid: Grid, rule: &Rule, wake: bool) -> Vec<Vec<u8>> {
+        seed(&mut grid, rule);
+        let mut rng = StdRng::seed_from_u64(2);
+        (0..STEPS)
+            .map(|step| {
+                if wake {
+                    grid.wake_all();
+                }
+                grid.step(rule, &mut rng);
+                assert_tracked(&mut grid, step);
+                grid.states()
+            })
+            .collect()
+    }
+
+    // Check the grid `fast` makes out of each scenario's plain grid against the one `reference` makes, step by step
+    fn assert_matches(name: &str, reference: impl Fn(Grid) -> Grid, fast: impl Fn(Grid) -> Grid, wake: bool) {
+        for (scenario, rule, boundaries, masked) in scenarios() {
+            let expected = run(reference(plain(boundaries, masked)), &rule, true);
+            assert!(expected[STEPS - 1].iter().any(|&state| state > 0), "{} scenario died out", scenario);
+            let actual = run(fast(plain(boundaries, masked)), &rule, wake);
+            for (step, (actual, expected)) in actual.iter().zip(&expected).enumerate() {
+                assert!(actual == expected, "{} grid differs from the reference in the {} scenario after {} steps", name, scenario, step + 1);
+            }
+        }
+    }
+
+    fn assert_matches_plain(name: &str, fast: impl Fn(Grid) -> Grid, wake: bool) {
+        assert_matches(name, |grid| grid, fast, wake);
+    }
+
+    #[test]
+    fn morton_layout_matches_linear() {
+        assert_matches_plain("Morton", |grid| grid.with_layout(CellLayout::Morton), true);
+    }
+
+    #[test]
+    fn halo_layout_matches_linear() {
+        // Width 2 leaves a ghost layer the radius-1 neighborhoods never reach
+        for width in [1, 2] {
+            assert_matches_plain(&format!("Halo width {}", width), |grid| grid.with_layout(CellLayout::Halo { width }), true);
+        }
+    }
+
+    #[test]
+    fn parallel_step_matches_sequential() {
+        assert_matches_plain("Parallel", |grid| grid.with_parallel(true), true);
+    }
+
+    #[test]
+    fn dormant_chunks_match_visiting_all() {
+        assert_matches_plain("Dormant-skipping", |grid| grid, false);
+    }
+
+    #[test]
+    fn packed_counting_matches_per_cell() {
+        assert_matches_plain("Packed", |grid| grid.with_packed_counting(true), true);
+        assert_matches_plain("Packed Morton", |grid| grid.with_packed_counting(true).with_layout(CellLayout::Morton), true);
+    }
+
+    #[test]
+    fn sequential_modes_match_across_fast_paths() {
+        // Block turns go by position, so every layout has to give the same generations
+        let block = |grid: Grid| grid.with_update_mode(UpdateMode::BlockSequential { block: 8 });
+        assert_matches("Block-sequential Morton", block, |grid| block(grid).with_layout(CellLayout::Morton), true);
+        assert_matches("Block-sequential Halo", block, |grid| block(grid).with_layout(CellLayout::Halo { width: 2 }), true);
+        assert_matches("Block-sequential parallel packed", block, |grid| block(grid).with_parallel(true).with_packed_counting(true), true);
+        // Random turns are shuffled in memory order, so only paths keeping the layout have to agree
+        let random = |grid: Grid| grid.with_update_mode(UpdateMode::RandomSequential);
+        assert_matches("Random-sequential parallel packed", random, |grid| random(grid).with_parallel(true).with_packed_counting(true), true);
+    }
+
+    #[test]
+    fn activity_is_counted_alike_on_every_path() {
+        let (_, rule, boundaries, masked) = scenarios().remove(0);
+        // Change counts of every position after the seeded steps, None where the grid doesn't count them
+        let activity = |mut grid: Grid| {
+            seed(&mut grid, &rule);
+            let mut rng = StdRng::seed_from_u64(2);
+            for _ in 0..STEPS {
+                grid.step(&rule, &mut rng);
+            }
+            let positions = (0..SIZE.pow(3)).map(|i| IVec3::new(i % SIZE, i / SIZE % SIZE, i / SIZE / SIZE));
+            positions.map(|pos| grid.activity(pos)).collect::<Vec<_>>()
+        };
+        let expected = activity(plain(boundaries, masked).with_activity(true));
+        assert!(expected.iter().any(|&flips| flips > Some(1)), "no cell changed more than once");
+        let fast = plain(boundaries, masked).with_layout(CellLayout::Morton).with_parallel(true).with_activity(true);
+        assert!(activity(fast) == expected, "parallel Morton grid counted different activity");
+        assert!(activity(plain(boundaries, masked)).iter().all(Option::is_none), "activity counted without with_activity");
+    }
+
+    #[test]
+    fn species_steps_keep_the_hash_tracked() {
+        let ecosystem = Ecosystem::new([("coral".to_string(), Rule::builder()), ("moss".to_string(), Rule::new(&[4, 5], &[3], 5, NeighborMethod::Moore))]);
+        let mut grid = Grid::new(32);
+        let mut rng = StdRng::seed_from_u64(3);
+        grid.spawn_species_clusters(&ecosystem, 6, 12 * 12 * 12, &mut rng);
+        for step in 0..STEPS {
+            grid.step_species(&ecosystem, &mut rng);
+            assert_tracked(&mut grid, step);
+        }
+    }
+
+    #[test]
+    fn restored_cells_keep_species_and_age() {
+        let mut grid = Grid::new(16).with_layout(CellLayout::Morton);
+        let total = grid.cells.len();
+        for index in (0..total).step_by(7) {
+            let cell = &mut grid.cells[index];
+            cell.species = (index % 3) as u8;
+            if index % 2 == 0 {
+                cell.spawn((index % 5 + 1) as u8);
+                cell.ticks = (index % 4) as u8;
+                cell.age = (index * 37 % 65_536) as u16;
+            }
+        }
+        grid.retrack();
+        let fields = |grid: &Grid| grid.cells.iter().map(|cell| (cell.value, cell.species, cell.ticks, cell.age)).collect::<Vec<_>>();
+        let expected = fields(&grid);
+
+        let saved = grid.encode_cells();
+        grid.set_states(&[], 0);
+        grid.restore_cells(&saved, 9).unwrap();
+        assert_eq!(fields(&grid), expected);
+        assert_eq!(grid.generation(), 9);
+        assert!(grid.restore_cells(&saved[..saved.len() - 1], 9).is_err(), "truncated cells have to be rejected");
+    }
+}
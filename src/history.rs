@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use crate::cyclic::CyclicRule;
+use crate::grid::{max_neighbors, max_state, rebuild_neighbors, CellColors, Grid};
+use crate::rendering::InstanceMaterialData;
+use crate::rule::Rule;
+use crate::species::Ecosystem;
+use crate::zones::ZonedRules;
+
+/// Recent generations to step back through, to see how a structure formed
+/// Frames are stored compressed with each cell's species and age (see `Grid::encode_cells`), the oldest
+/// dropped past either limit
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    /// Most frames kept, 0 turns recording off
+    pub frames: usize,
+    /// Most memory the kept frames may take, in megabytes
+    pub budget_mb: usize,
+    #[serde(skip)]
+    recorded: VecDeque<Frame>,
+    /// Compressed bytes of all recorded frames
+    #[serde(skip)]
+    bytes: usize,
+    /// Recorded frame on screen while rewound, None while the simulation runs
+    #[serde(skip)]
+    cursor: Option<usize>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Frame {
+    size: i32,
+    generation: u64,
+    cells: Vec<u8>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(1000, 128)
+    }
+}
+
+impl History {
+    pub fn new(frames: usize, budget_mb: usize) -> Self {
+        Self { frames, budget_mb, recorded: VecDeque::new(), bytes: 0, cursor: None }
+    }
+
+    /// Whether the newest frame is on screen, so the simulation may run
+    pub fn is_live(&self) -> bool {
+        self.cursor.is_none()
+    }
+
+    /// Record the grid unless it's the newest frame already, dropping the oldest frames past the limits
+    /// Frames of another grid size can't be restored, so resizing starts over
+    pub fn record(&mut self, grid: &Grid) {
+        if self.frames == 0 || !self.is_live() {
+            return;
+        }
+        match self.recorded.back() {
+            Some(newest) if newest.size != grid.size => self.clear(),
+            Some(newest) if newest.generation == grid.generation() => return,
+            _ => {}
+        }
+
+        let cells = grid.encode_cells();
+        self.bytes += cells.len();
+        self.recorded.push_back(Frame { size: grid.size, generation: grid.generation(), cells });
+        let budget = self.budget_mb * 1024 * 1024;
+        while self.recorded.len() > 1 && (self.recorded.len() > self.frames || self.bytes > budget) {
+            if let Some(oldest) = self.recorded.pop_front() {
+                self.bytes -= oldest.cells.len();
+            }
+        }
+    }
+
+    /// Drop every recorded frame and go live
+    pub fn clear(&mut self) {
+        self.recorded.clear();
+        self.bytes = 0;
+        self.cursor = None;
+    }
+
+    /// Show the recorded frame `offset` frames from the one on screen (negative is back) in `grid`,
+    /// going live again when moving onto the newest. Returns whether the grid changed
+    pub fn seek(&mut self, offset: isize, grid: &mut Grid) -> bool {
+        let Some(newest) = self.recorded.len().checked_sub(1) else { return false };
+        let current = self.cursor.unwrap_or(newest);
+        let target = current.saturating_add_signed(offset).min(newest);
+        if target == current {
+            return false;
+        }
+
+        let frame = &self.recorded[target];
+        if frame.size != grid.size || grid.restore_cells(&frame.cells, frame.generation).is_err() {
+            return false;
+        }
+        self.cursor = (target != newest).then_some(target);
+        true
+    }
+
+    /// Keep running from the frame on screen, dropping the frames after it
+    pub fn resume(&mut self) {
+        if let Some(cursor) = self.cursor.take() {
+            for dropped in self.recorded.drain(cursor + 1..) {
+                self.bytes -= dropped.cells.len();
+            }
+        }
+    }
+}
+
+/// Run condition of the simulation systems: nothing runs while an older generation is on screen
+pub fn history_is_live(history: Option<Res<History>>) -> bool {
+    history.is_none_or(|history| history.is_live())
+}
+
+/// Record the generation the simulation reached this frame
+pub fn record_history(mut history: ResMut<History>, grid: Res<Grid>) {
+    history.record(&grid);
+}
+
+/// Press , / . to step back / forward through the recorded generations (with Shift 10 at a time),
+/// the simulation resumes on reaching the newest. Enter resumes from the generation on screen instead
+#[allow(clippy::too_many_arguments)]
+pub fn rewind_history(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut history: ResMut<History>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    colors: Res<CellColors>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+) {
+    if keys.just_pressed(KeyCode::Enter) && !history.is_live() {
+        history.resume();
+        println!("Resumed from generation {}", grid.generation());
+        return;
+    }
+
+    let steps = if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight) { 10 } else { 1 };
+    let offset = match (keys.just_pressed(KeyCode::Comma), keys.just_pressed(KeyCode::Period)) {
+        (true, false) => -steps,
+        (false, true) => steps,
+        _ => return,
+    };
+    if !history.seek(offset, &mut grid) {
+        return;
+    }
+
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    rebuild_neighbors(&mut grid, &rule, ecosystem.as_deref(), cyclic.as_deref(), zones.as_deref());
+    if let Ok(mut instances) = instance_query.single_mut() {
+        let max_state = max_state(rules, ecosystem.as_deref(), cyclic.as_deref());
+        let max_neighbors = max_neighbors(rules, ecosystem.as_deref(), cyclic.as_deref());
+        instances.0 = grid.build_instances(&colors, max_state, max_neighbors);
+    }
+    if history.is_live() {
+        println!("Back at generation {}, running again", grid.generation());
+    } else {
+        println!("Rewound to generation {} ({} living cells)", grid.generation(), grid.cell_count());
+    }
+}
@@ -0,0 +1,291 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use serde::{Deserialize, Serialize};
+use crate::cyclic::CyclicRule;
+use crate::editing::Brush;
+use crate::grid::{max_state, CellColors, Grid};
+use crate::rule::Rule;
+use crate::species::Ecosystem;
+use crate::stats::SimStats;
+use crate::zones::{Axis, ZonedRules};
+
+/// Scrolling line chart of population (white) and births/deaths (green/red) over the recent
+/// generations, drawn in a corner over the cells. Births and deaths share their own scale
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PopulationGraph {
+    /// Shown at startup, H toggles it
+    pub visible: bool,
+    /// Generations across the chart
+    pub generations: usize,
+    /// Chart size in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for PopulationGraph {
+    fn default() -> Self {
+        Self { visible: true, generations: 240, width: 240, height: 80 }
+    }
+}
+
+/// Root node of the chart, hidden with H
+#[derive(Component)]
+pub struct GraphPanel;
+
+/// Image the chart is drawn into
+#[derive(Component)]
+pub struct GraphImage(Handle<Image>);
+
+/// Latest numbers under the chart
+#[derive(Component)]
+pub struct GraphLabel;
+
+const BACKGROUND: [u8; 4] = [0, 0, 0, 140];
+const POPULATION_COLOR: [u8; 4] = [255, 255, 255, 255];
+const BIRTH_COLOR: [u8; 4] = [80, 230, 80, 255];
+const DEATH_COLOR: [u8; 4] = [240, 70, 70, 255];
+
+/// Spawn the chart panel in the top left corner
+pub fn spawn_population_graph(mut commands: Commands, graph: Res<PopulationGraph>, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(Image::new_fill(
+        Extent3d { width: graph.width, height: graph.height, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &BACKGROUND,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+    commands
+        .spawn((
+            GraphPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                top: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            if graph.visible { Visibility::Inherited } else { Visibility::Hidden },
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                GraphImage(image.clone()),
+                ImageNode::new(image),
+                Node { width: Val::Px(graph.width as f32), height: Val::Px(graph.height as f32), ..default() },
+            ));
+            panel.spawn((GraphLabel, Text::new(""), TextFont { font_size: 13.0, ..default() }));
+        });
+}
+
+/// Press H to show or hide the population graph, which redraws whenever new stats come in
+pub fn draw_population_graph(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut graph: ResMut<PopulationGraph>,
+    stats: Res<SimStats>,
+    mut images: ResMut<Assets<Image>>,
+    mut panel_query: Query<&mut Visibility, With<GraphPanel>>,
+    image_query: Query<&GraphImage>,
+    mut label_query: Query<&mut Text, With<GraphLabel>>,
+) {
+    if keys.just_pressed(KeyCode::KeyH) {
+        graph.visible = !graph.visible;
+        for mut visibility in &mut panel_query {
+            *visibility = if graph.visible { Visibility::Inherited } else { Visibility::Hidden };
+        }
+    }
+    if !graph.visible || !(stats.is_changed() || graph.is_changed()) {
+        return;
+    }
+
+    let recent: Vec<_> = stats.generations().rev().take(graph.generations.max(2)).collect();
+    for GraphImage(handle) in &image_query {
+        let Some(image) = images.get_mut(handle) else { continue };
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let Some(data) = image.data.as_mut() else { continue };
+        for pixel in data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&BACKGROUND);
+        }
+
+        // Newest generation on the right edge, older ones scrolling off to the left
+        let column = |age: usize| (width - 1) as f32 - age as f32 * (width - 1) as f32 / (graph.generations.max(2) - 1) as f32;
+        let most_alive = recent.iter().map(|stats| stats.population).max().unwrap_or(0).max(1);
+        let most_changes = recent.iter().map(|stats| stats.births.max(stats.deaths)).max().unwrap_or(0).max(1);
+        let series = [
+            (recent.iter().map(|stats| stats.births as f32 / most_changes as f32).collect::<Vec<_>>(), BIRTH_COLOR),
+            (recent.iter().map(|stats| stats.deaths as f32 / most_changes as f32).collect(), DEATH_COLOR),
+            (recent.iter().map(|stats| stats.population as f32 / most_alive as f32).collect(), POPULATION_COLOR),
+        ];
+        for (values, color) in series {
+            let point = |age: usize| Vec2::new(column(age), (1.0 - values[age]) * (height - 1) as f32);
+            for age in 1..values.len() {
+                draw_line(data, width, height, point(age - 1), point(age), color);
+            }
+        }
+    }
+
+    if let (Some(latest), Ok(mut label)) = (recent.first(), label_query.single_mut()) {
+        label.0 = format!("gen {}  alive {}  +{} -{}", latest.generation, latest.population, latest.births, latest.deaths);
+    }
+}
+
+/// Draw a line of `color` between two pixel positions into RGBA pixels
+fn draw_line(data: &mut [u8], width: usize, height: usize, from: Vec2, to: Vec2, color: [u8; 4]) {
+    let steps = (to - from).abs().max_element().ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        let point = from.lerp(to, step as f32 / steps as f32).round();
+        if point.x < 0.0 || point.y < 0.0 || point.x >= width as f32 || point.y >= height as f32 {
+            continue;
+        }
+        let pixel = (point.y as usize * width + point.x as usize) * 4;
+        data[pixel..pixel + 4].copy_from_slice(&color);
+    }
+}
+
+/// Cross-section of a single layer of the grid drawn as a 2D image in the top right corner, cells colored by
+/// their state, since many structures are easier to understand sliced than in 3D
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SliceViewer {
+    /// Shown at startup, Tab toggles it
+    pub visible: bool,
+    /// Axis the layers are stacked along
+    pub axis: Axis,
+    /// Layer shown, counted from the low end of the axis, the middle layer when not set
+    pub layer: Option<i32>,
+    /// Pixels per cell on screen
+    pub cell_pixels: u32,
+}
+
+impl Default for SliceViewer {
+    fn default() -> Self {
+        Self { visible: false, axis: Axis::Z, layer: None, cell_pixels: 3 }
+    }
+}
+
+impl SliceViewer {
+    /// Grid position of the pixel at (x, y) of the layer image, rows running down from the high end
+    fn cell_at(&self, layer: i32, size: i32, x: i32, y: i32) -> IVec3 {
+        let down = size - 1 - y;
+        match self.axis {
+            Axis::X => IVec3::new(layer, down, x),
+            Axis::Y => IVec3::new(x, layer, down),
+            Axis::Z => IVec3::new(x, down, layer),
+        }
+    }
+}
+
+/// Root node of the slice viewer, hidden with Tab
+#[derive(Component)]
+pub struct SlicePanel;
+
+/// Image the layer is drawn into
+#[derive(Component)]
+pub struct SliceImage(Handle<Image>);
+
+/// Axis and layer under the image
+#[derive(Component)]
+pub struct SliceLabel;
+
+/// Spawn the slice viewer panel in the top right corner, sized to the grid
+pub fn spawn_slice_viewer(mut commands: Commands, viewer: Res<SliceViewer>, grid: Res<Grid>, mut images: ResMut<Assets<Image>>) {
+    let size = grid.size as u32;
+    let mut image = Image::new_fill(
+        Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &BACKGROUND,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    // Cells stay crisp squares when scaled up
+    image.sampler = ImageSampler::nearest();
+    let image = images.add(image);
+    let side = Val::Px((size * viewer.cell_pixels) as f32);
+    commands
+        .spawn((
+            SlicePanel,
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.0),
+                top: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            if viewer.visible { Visibility::Inherited } else { Visibility::Hidden },
+        ))
+        .with_children(|panel| {
+            panel.spawn((SliceImage(image.clone()), ImageNode::new(image), Node { width: side, height: side, ..default() }));
+            panel.spawn((SliceLabel, Text::new(""), TextFont { font_size: 13.0, ..default() }));
+        });
+}
+
+/// Press Tab to show or hide the slice viewer and Shift+Tab to switch its axis; while it's shown the mouse
+/// wheel scrolls through the layers, unless it's sizing the paint brush. Redraws whenever the grid changes
+#[allow(clippy::too_many_arguments)]
+pub fn draw_slice_viewer(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut wheel: MessageReader<MouseWheel>,
+    mut viewer: ResMut<SliceViewer>,
+    grid: Res<Grid>,
+    colors: Res<CellColors>,
+    (rule, zones, ecosystem, cyclic): (Res<Rule>, Option<Res<ZonedRules>>, Option<Res<Ecosystem>>, Option<Res<CyclicRule>>),
+    brush: Option<Res<Brush>>,
+    mut images: ResMut<Assets<Image>>,
+    mut panel_query: Query<&mut Visibility, With<SlicePanel>>,
+    mut image_query: Query<(&SliceImage, &mut Node)>,
+    mut label_query: Query<&mut Text, With<SliceLabel>>,
+) {
+    if keys.just_pressed(KeyCode::Tab) {
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            viewer.axis = match viewer.axis {
+                Axis::X => Axis::Y,
+                Axis::Y => Axis::Z,
+                Axis::Z => Axis::X,
+            };
+        } else {
+            viewer.visible = !viewer.visible;
+        }
+        for mut visibility in &mut panel_query {
+            *visibility = if viewer.visible { Visibility::Inherited } else { Visibility::Hidden };
+        }
+    }
+    let scroll: i32 = wheel.read().map(|event| event.y.signum() as i32).sum();
+    if !viewer.visible {
+        return;
+    }
+    let size = grid.size;
+    if scroll != 0 && !brush.is_some_and(|brush| brush.enabled) {
+        viewer.layer = Some((viewer.layer.unwrap_or(size / 2) + scroll).clamp(0, size - 1));
+    }
+    if !(grid.is_changed() || viewer.is_changed() || colors.is_changed()) {
+        return;
+    }
+
+    let layer = viewer.layer.unwrap_or(size / 2).clamp(0, size - 1);
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let max_state = max_state(rules, ecosystem.as_deref(), cyclic.as_deref());
+    for (SliceImage(handle), mut node) in &mut image_query {
+        let Some(image) = images.get_mut(handle) else { continue };
+        // Resizing the grid resizes the slice
+        if image.width() != size as u32 {
+            image.resize(Extent3d { width: size as u32, height: size as u32, depth_or_array_layers: 1 });
+            let side = Val::Px((size as u32 * viewer.cell_pixels) as f32);
+            (node.width, node.height) = (side, side);
+        }
+        let Some(data) = image.data.as_mut() else { continue };
+        for (index, pixel) in data.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (index as i32 % size, index as i32 / size);
+            let color = match grid.get(viewer.cell_at(layer, size, x, y)) {
+                Some(state) if state > 0 => colors.lerp_color(state as f32 / max_state.max(1) as f32).to_srgba().to_u8_array(),
+                _ => BACKGROUND,
+            };
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    if let Ok(mut label) = label_query.single_mut() {
+        label.0 = format!("{:?} layer {} / {}", viewer.axis, layer, size - 1);
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::cyclic::CyclicRule;
+use crate::grid::{Grid, SimRng};
+use crate::rule::Rule;
+use crate::species::Ecosystem;
+use crate::zones::ZonedRules;
+
+/// Part of the grid immigrants land in
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum Region {
+    /// Anywhere in the grid
+    #[default]
+    Everywhere,
+    /// Within `radius` (fraction of half the grid size) of the center
+    Ball { radius: f32 },
+}
+
+impl Region {
+    /// Random position in the region of a grid of `size` cells per side
+    pub fn random_pos(&self, size: i32, rng: &mut impl Rng) -> IVec3 {
+        match self {
+            Region::Everywhere => IVec3::new(
+                rng.random_range(0..size),
+                rng.random_range(0..size),
+                rng.random_range(0..size),
+            ),
+            Region::Ball { radius } => {
+                let radius = (radius * size as f32 * 0.5).max(0.0) as i32;
+                // Rejection sample the bounding cube, the center offset always fits
+                loop {
+                    let offset = IVec3::new(
+                        rng.random_range(-radius..=radius),
+                        rng.random_range(-radius..=radius),
+                        rng.random_range(-radius..=radius),
+                    );
+                    if offset.length_squared() <= radius * radius {
+                        return IVec3::splat(size / 2) + offset;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether the cell at `pos` of a grid of `size` cells per side is in the region
+    pub fn contains(&self, pos: IVec3, size: i32) -> bool {
+        match self {
+            Region::Everywhere => true,
+            Region::Ball { radius } => {
+                let radius = (radius * size as f32 * 0.5).max(0.0) as i32;
+                (pos - IVec3::splat(size / 2)).length_squared() <= radius * radius
+            }
+        }
+    }
+}
+
+/// Injects a few random living cells every few generations, so rules that slowly die out keep going
+/// Immigrants are born at their rule's birth state; spots that are already alive are skipped
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Immigration {
+    /// Generations between injections
+    pub every: u64,
+    /// Cells injected each time
+    pub amount: usize,
+    #[serde(default)]
+    pub region: Region,
+    /// Generation of the last injection, so each generation gets at most one
+    #[serde(skip)]
+    last: Option<u64>,
+}
+
+impl Immigration {
+    /// Inject `amount` cells anywhere in the grid every `every` generations
+    pub fn new(every: u64, amount: usize) -> Self {
+        Self { every: every.max(1), amount, region: Region::Everywhere, last: None }
+    }
+
+    /// Only inject cells within `radius` (fraction of half the grid size) of the center
+    pub fn with_ball(mut self, radius: f32) -> Self {
+        self.region = Region::Ball { radius };
+        self
+    }
+
+    /// Whether `generation` is due for an injection: a multiple of `every` was reached since the last one
+    /// Several generations can pass per frame (see `StepsPerFrame`), so the multiple itself may be skipped
+    pub fn is_due(&self, generation: u64) -> bool {
+        self.every > 0 && generation / self.every > self.last.map_or(0, |last| last / self.every)
+    }
+}
+
+/// Inject immigrants when the grid reaches or passes a multiple of `every` generations
+/// Cyclic and multi-species grids are left alone, like noise flips
+pub fn apply_immigration(
+    mut immigration: ResMut<Immigration>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    mut rng: ResMut<SimRng>,
+) {
+    let generation = grid.generation();
+    if !immigration.is_due(generation) || ecosystem.is_some() || cyclic.is_some() {
+        return;
+    }
+
+    immigration.last = Some(generation);
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let born = grid.inject_cells(rules, &immigration.region, immigration.amount, &mut rng.0);
+    println!("Generation {}: {} immigrants", generation, born);
+}
@@ -0,0 +1,167 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::mesh::PrimitiveTopology;
+use bevy::prelude::*;
+use std::sync::OnceLock;
+
+/// Corner `i` of a cube sits at (i & 1, i >> 1 & 1, i >> 2 & 1)
+fn corner_offset(corner: usize) -> IVec3 {
+    IVec3::new(corner as i32 & 1, corner as i32 >> 1 & 1, corner as i32 >> 2 & 1)
+}
+
+/// The 12 cube edges as pairs of corners
+fn edges() -> Vec<(usize, usize)> {
+    (0..8).flat_map(|a| (0..3).map(move |axis| (a, a | 1 << axis)).filter(move |&(_, b)| b != a)).collect()
+}
+
+/// Polygons (as loops of edge indices) splitting each of the 256 inside/outside patterns of a cube's
+/// corners, derived from the cube's faces instead of a hand-written table. Every face with corners on both
+/// sides gets segments between its crossed edges, diagonal patterns cutting off each inside corner, so
+/// neighboring cubes agree on their shared faces and the surface has no cracks. Segments run with the
+/// inside corners on their left seen from outside the cube, which winds every loop the same way around
+/// the inside
+fn case_polygons() -> &'static [Vec<Vec<usize>>] {
+    static TABLE: OnceLock<Vec<Vec<Vec<usize>>>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let edges = edges();
+        let edge_index = |a: usize, b: usize| edges.iter().position(|&edge| edge == (a.min(b), a.max(b))).expect("corners of an edge");
+        // Each face's corners counter-clockwise seen from outside the cube
+        let faces: Vec<[usize; 4]> = (0..3)
+            .flat_map(|axis| {
+                let (u, v) = (1 << ((axis + 1) % 3), 1 << ((axis + 2) % 3));
+                [[0, v, u | v, u], [1 << axis, 1 << axis | u, 1 << axis | u | v, 1 << axis | v]]
+            })
+            .collect();
+
+        (0..256)
+            .map(|case: usize| {
+                let inside = |corner: usize| case & 1 << corner != 0;
+                // The crossed edge each crossed edge's segment leads to
+                let mut next = vec![None; edges.len()];
+                for face in &faces {
+                    let edge = |i: usize| edge_index(face[i % 4], face[(i + 1) % 4]);
+                    // Leaving the inside corners at an edge, the segment goes back to where the run of inside
+                    // corners started
+                    for exit in (0..4).filter(|&i| inside(face[i]) && !inside(face[(i + 1) % 4])) {
+                        let entry = (1..4).map(|back| (exit + 4 - back) % 4).find(|&i| !inside(face[i])).expect("an outside corner");
+                        next[edge(exit)] = Some(edge(entry));
+                    }
+                }
+
+                // Every crossed edge lies on two faces, entered on one and left on the other, so the
+                // segments close into loops
+                let mut visited = vec![false; edges.len()];
+                let mut polygons = Vec::new();
+                for start in 0..edges.len() {
+                    if visited[start] || next[start].is_none() {
+                        continue;
+                    }
+                    let mut polygon = Vec::new();
+                    let mut current = start;
+                    while !visited[current] {
+                        visited[current] = true;
+                        polygon.push(current);
+                        current = next[current].expect("a closed loop");
+                    }
+                    polygons.push(polygon);
+                }
+                polygons
+            })
+            .collect()
+    })
+}
+
+/// Triangles of the surface where `field` (a value per cell of a `size`³ grid in position order) crosses
+/// `level`, with cells past the edges at 0 so the surface closes there. Cell positions are scaled by `scale`
+/// and moved by `offset`. Each vertex is colored by `color(index)` of the cell on the inside of its edge;
+/// normals follow the field's gradient, pointing to lower values
+pub fn marching_cubes(field: &[f32], size: i32, level: f32, scale: f32, offset: Vec3, color: impl Fn(usize) -> [f32; 4]) -> Mesh {
+    let index = |pos: IVec3| (pos.x + pos.y * size + pos.z * size * size) as usize;
+    let value = |pos: IVec3| {
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(IVec3::splat(size)).any() { 0.0 } else { field[index(pos)] }
+    };
+    let gradient = |pos: IVec3| {
+        Vec3::new(
+            value(pos + IVec3::X) - value(pos - IVec3::X),
+            value(pos + IVec3::Y) - value(pos - IVec3::Y),
+            value(pos + IVec3::Z) - value(pos - IVec3::Z),
+        )
+    };
+    let edges = edges();
+    let polygons = case_polygons();
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    for z in -1..size {
+        for y in -1..size {
+            for x in -1..size {
+                let base = IVec3::new(x, y, z);
+                let corners: [f32; 8] = std::array::from_fn(|corner| value(base + corner_offset(corner)));
+                let case = (0..8).filter(|&corner| corners[corner] > level).fold(0, |case, corner| case | 1 << corner);
+                if case == 0 || case == 255 {
+                    continue;
+                }
+                // Where each crossed edge meets the level, its normal and the color of its inside cell
+                let vertex = |edge: usize| {
+                    let (a, b) = edges[edge];
+                    let (va, vb) = (corners[a], corners[b]);
+                    let t = ((level - va) / (vb - va)).clamp(0.0, 1.0);
+                    let (pa, pb) = (base + corner_offset(a), base + corner_offset(b));
+                    let position = pa.as_vec3().lerp(pb.as_vec3(), t);
+                    let normal = -gradient(pa).lerp(gradient(pb), t);
+                    let inside = if va > level { pa } else { pb };
+                    (position, normal.normalize_or_zero(), color(index(inside)))
+                };
+                for polygon in &polygons[case] {
+                    let vertices: Vec<_> = polygon.iter().map(|&edge| vertex(edge)).collect();
+                    // The loops wind clockwise seen from outside, so fans run backwards to face out
+                    for i in 1..vertices.len() - 1 {
+                        for (position, normal, color) in [vertices[0], vertices[i + 1], vertices[i]] {
+                            positions.push((position * scale + offset).to_array());
+                            normals.push(normal.to_array());
+                            colors.push(color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+}
+
+/// Average of `field` (position order in a `size`³ grid) over blocks of `block`³ cells, as a grid of
+/// `size / block` blocks rounded up
+pub fn downsample(field: &[f32], size: i32, block: i32) -> (Vec<f32>, i32) {
+    let block = block.max(1);
+    let blocks = (size + block - 1) / block;
+    let mut sums = vec![0.0; (blocks * blocks * blocks) as usize];
+    for (index, &value) in field.iter().enumerate() {
+        let index = index as i32;
+        let pos = IVec3::new(index % size, index / size % size, index / size / size) / block;
+        sums[(pos.x + pos.y * blocks + pos.z * blocks * blocks) as usize] += value;
+    }
+    let cells = block.pow(3) as f32;
+    (sums.into_iter().map(|sum| sum / cells).collect(), blocks)
+}
+
+/// Box blur of `field` (position order in a `size`³ grid) over `radius` cells along each axis in turn,
+/// with zeros past the edges
+pub fn blur(field: &mut Vec<f32>, size: i32, radius: i32) {
+    if radius <= 0 {
+        return;
+    }
+    let width = (2 * radius + 1) as f32;
+    for stride in [1, size, size * size] {
+        let blurred = (0..field.len() as i32)
+            .map(|index| {
+                let along = index / stride % size;
+                (-radius..=radius).filter(|step| (0..size).contains(&(along + step))).map(|step| field[(index + step * stride) as usize]).sum::<f32>()
+                    / width
+            })
+            .collect();
+        *field = blurred;
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::continuous::{ContinuousGrid, Kernel, Simulation};
+
+/// 3D Lenia: continuous cells grow or shrink by a growth function of their kernel-weighted surroundings
+/// See https://chakazul.github.io/lenia.html
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LeniaRule {
+    /// Kernel radius in cells
+    pub radius: i32,
+    /// Height of each concentric kernel ring, innermost first (one ring = a single smooth shell)
+    pub peaks: Vec<f32>,
+    /// Kernel-weighted value at which cells grow fastest
+    pub mu: f32,
+    /// Width of the growth bump around mu, values further away shrink
+    pub sigma: f32,
+    /// Fraction of the growth applied per step (1 / time resolution)
+    pub dt: f32,
+}
+
+impl LeniaRule {
+    /// Smooth pulsing blobs from random noise (single ring kernel of radius 5)
+    pub fn blobs() -> Self {
+        Self {
+            radius: 5,
+            peaks: vec![1.0],
+            mu: 0.15,
+            sigma: 0.017,
+            dt: 0.1,
+        }
+    }
+
+    /// Kernel of concentric smooth rings, one per peak
+    pub fn kernel(&self) -> Kernel {
+        let rings = self.peaks.len().max(1) as f32;
+        Kernel::from_fn(self.radius, |distance| {
+            let ring = distance * rings;
+            let peak = self.peaks.get(ring as usize).copied().unwrap_or(0.0);
+            peak * kernel_core(ring.fract())
+        })
+    }
+
+    /// Growth rate (-1..1) of a cell whose kernel-weighted surroundings sum to `potential`
+    #[inline]
+    pub fn growth(&self, potential: f32) -> f32 {
+        let x = (potential - self.mu) / self.sigma;
+        2.0 * (-x * x / 2.0).exp() - 1.0
+    }
+}
+
+impl Default for LeniaRule {
+    fn default() -> Self {
+        Self::blobs()
+    }
+}
+
+/// Smooth bump on 0..1, zero at both ends and one in the middle
+fn kernel_core(r: f32) -> f32 {
+    if r <= 0.0 || r >= 1.0 {
+        return 0.0;
+    }
+    (4.0 - 1.0 / (r * (1.0 - r))).exp()
+}
+
+/// The active Lenia rule with its kernel built once
+#[derive(Resource, Clone, Debug)]
+pub struct Lenia {
+    pub rule: LeniaRule,
+    kernel: Kernel,
+}
+
+impl Lenia {
+    pub fn new(rule: LeniaRule) -> Self {
+        let kernel = rule.kernel();
+        Self { rule, kernel }
+    }
+
+    pub fn kernel(&self) -> &Kernel {
+        &self.kernel
+    }
+}
+
+impl Simulation for Lenia {
+    /// Every cell moves by dt * growth(potential)
+    fn step(&self, grid: &mut ContinuousGrid) {
+        let potential = grid.convolve(&self.kernel);
+        grid.update(|index, value| value + self.rule.dt * self.rule.growth(potential[index]));
+    }
+
+    fn kernel_size(&self) -> usize {
+        self.kernel.offsets().len()
+    }
+}
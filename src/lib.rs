@@ -0,0 +1,40 @@
+pub mod animation;
+pub mod camera;
+pub mod catalog;
+pub mod clipping;
+pub mod components;
+pub mod config;
+pub mod continuous;
+pub mod culling;
+pub mod cycles;
+pub mod cyclic;
+pub mod domain;
+pub mod editing;
+pub mod fog;
+pub mod grid;
+pub mod history;
+pub mod hud;
+pub mod immigration;
+pub mod isosurface;
+pub mod lenia;
+pub mod lod;
+pub mod meshing;
+pub mod packed;
+pub mod pattern;
+pub mod recording;
+pub mod rendering;
+pub mod reset;
+pub mod rule;
+pub mod schedule;
+pub mod search;
+pub mod seeding;
+pub mod shadows;
+pub mod shapes;
+pub mod smoothlife;
+pub mod species;
+pub mod stats;
+pub mod structure;
+pub mod tracking;
+pub mod vox;
+pub mod voxelize;
+pub mod zones;
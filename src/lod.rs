@@ -0,0 +1,130 @@
+use bevy::camera::visibility::NoFrustumCulling;
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponent;
+use serde::{Deserialize, Serialize};
+use crate::camera::FlyCamera;
+use crate::culling::CulledInstances;
+use crate::meshing::CellRenderer;
+use crate::rendering::{BlendAlpha, CellOutlines, InstanceData, InstanceMaterialData};
+use crate::shapes::CellMeshKind;
+
+/// Level of detail drawing the cells far from the camera as single points and only the near ones as full
+/// cell meshes, keeping frame rates up when flying far from a huge grid. Instanced renderer only
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CellLod {
+    /// On at startup, F3 toggles it
+    pub enabled: bool,
+    /// Distance from the camera past which cells turn into points
+    pub distance: f32,
+}
+
+impl Default for CellLod {
+    fn default() -> Self {
+        Self { enabled: false, distance: 150.0 }
+    }
+}
+
+/// Instances of one level of detail, split off the cells' `InstanceMaterialData` every frame while the LOD
+/// is on. Drawn like the cells themselves, but kept apart so the systems rebuilding the cells don't see them.
+/// Only extracted when split again or shown or hidden
+#[derive(Component, Default)]
+pub struct LodInstances {
+    instances: Vec<InstanceData>,
+    /// Whether these are the far cells drawn as points
+    far: bool,
+}
+
+impl ExtractComponent for LodInstances {
+    type QueryData = (&'static LodInstances, &'static Visibility);
+    type QueryFilter = Or<(Changed<LodInstances>, Changed<Visibility>)>;
+    type Out = InstanceMaterialData;
+
+    fn extract_component(
+        (lod, visibility): bevy::ecs::query::QueryItem<'_, '_, Self::QueryData>,
+    ) -> Option<InstanceMaterialData> {
+        (*visibility != Visibility::Hidden).then(|| InstanceMaterialData(lod.instances.clone()))
+    }
+}
+
+/// Spawn the near and far entities next to the instanced cells, with the same blending (and the near ones
+/// with the same outlines)
+pub fn spawn_lod_cells(
+    mut commands: Commands,
+    renderer: Res<CellRenderer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    cells_query: Query<(&Mesh3d, Has<BlendAlpha>, Has<CellOutlines>), With<InstanceMaterialData>>,
+) {
+    // The merged surfaces replace the instances entirely
+    if *renderer != CellRenderer::Instanced {
+        return;
+    }
+    let points = meshes.add(CellMeshKind::Point.mesh());
+    for (mesh, blend_alpha, outlines) in &cells_query {
+        for (mesh, far) in [(mesh.clone(), false), (Mesh3d(points.clone()), true)] {
+            let lod = commands
+                .spawn((mesh, Transform::IDENTITY, Visibility::Hidden, LodInstances { far, ..default() }, NoFrustumCulling))
+                .id();
+            if blend_alpha {
+                commands.entity(lod).insert(BlendAlpha);
+            }
+            if outlines && !far {
+                commands.entity(lod).insert(CellOutlines);
+            }
+        }
+    }
+}
+
+/// Press F3 to toggle the LOD. While it's on, the cells hide and their instances (only the visible ones with
+/// frustum culling) are split between the near and far entities by their distance from the camera, after
+/// anything that rebuilt, animated, sorted or culled them, whenever they or the camera changed
+pub fn split_lod_cells(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut lod: ResMut<CellLod>,
+    camera_query: Query<Ref<GlobalTransform>, With<FlyCamera>>,
+    mut cells_query: Query<
+        (Ref<InstanceMaterialData>, Option<Ref<CulledInstances>>, Ref<Mesh3d>, &mut Visibility),
+        Without<LodInstances>,
+    >,
+    mut lod_query: Query<(&mut LodInstances, &mut Mesh3d, &mut Visibility)>,
+) {
+    if keys.just_pressed(KeyCode::F3) {
+        lod.enabled = !lod.enabled;
+        println!("Level of detail {}", if lod.enabled { "on" } else { "off" });
+    }
+    // Nothing to split when a merged surface draws the cells
+    if lod_query.is_empty() {
+        return;
+    }
+    let Ok((cells, culled, cells_mesh, mut cells_visibility)) = cells_query.single_mut() else { return };
+    let Some(camera) = camera_query.iter().next() else { return };
+    if lod.is_changed() {
+        *cells_visibility = if lod.enabled { Visibility::Hidden } else { Visibility::Inherited };
+        for (_, _, mut visibility) in &mut lod_query {
+            *visibility = if lod.enabled { Visibility::Inherited } else { Visibility::Hidden };
+        }
+    }
+    let changed = lod.is_changed()
+        || camera.is_changed()
+        || cells.is_changed()
+        || cells_mesh.is_changed()
+        || culled.as_ref().is_some_and(|culled| culled.is_changed());
+    if !lod.enabled || !changed {
+        return;
+    }
+
+    let eye = camera.translation();
+    let cells = culled.as_deref().map_or(&cells.0, |culled| &culled.0);
+    let distance_squared = lod.distance * lod.distance;
+    for (mut lod_instances, mut mesh, _) in &mut lod_query {
+        let far = lod_instances.far;
+        lod_instances.instances.clear();
+        lod_instances
+            .instances
+            .extend(cells.iter().filter(|instance| (instance.position.distance_squared(eye) > distance_squared) == far).copied());
+        // The near cells follow the cell shape as it's switched
+        if !far && mesh.0 != cells_mesh.0 {
+            mesh.0 = cells_mesh.0.clone();
+        }
+    }
+}
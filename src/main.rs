@@ -1,46 +1,220 @@
+use bevy::camera::visibility::{NoFrustumCulling, VisibilitySystems};
+use bevy::light::SimulationLightSystems;
+use bevy::post_process::bloom::Bloom;
 use bevy::prelude::*;
 
 #[cfg(not(target_arch = "wasm32"))]
 use bevy::pbr::wireframe::WireframePlugin;
 
-mod camera;
-mod grid;
-mod rendering;
-mod rule;
-
-use camera::{camera_look, camera_movement, handle_exit, FlyCamera};
-use grid::{simulate_step, CellColors, ColorMethod, Grid};
-use rendering::{CellMaterialPlugin, InstanceMaterialData};
-use rule::Rule;
+use conway_3d::animation::{animate_cells, CellAnimation};
+use conway_3d::camera;
+use conway_3d::camera::{camera_look, camera_movement, handle_exit, layout_quad_view, spawn_quad_view, FlyCamera};
+use conway_3d::catalog::{RuleCatalog, RuleRegistry};
+use conway_3d::clipping::{adjust_clip_box, adjust_exploded_view, adjust_slice_plane};
+use conway_3d::components::{color_components, ComponentTracker};
+use conway_3d::config::{CliArgs, SimConfig};
+use conway_3d::continuous::{simulate_continuous, ContinuousGrid, Simulation};
+use conway_3d::culling::cull_instances;
+use conway_3d::cycles::BehaviorChanged;
+use conway_3d::editing::{copy_paste, paint_cells, pick_cells, transform_cells, undo_edits, Clipboard, EditHistory};
+use conway_3d::grid::{adjust_steps_per_frame, adjust_temperature, checkpoint_grid, cycle_rule, mutate_rule, resize_grid, simulate_step, track_buried_cells, Boundaries, CellColors, CellLayout, ColorMethod, Grid, SimRng, StepsPerFrame, Temperature, UpdateMode};
+use conway_3d::history::{history_is_live, record_history, rewind_history, History};
+use conway_3d::hud::{draw_population_graph, draw_slice_viewer, spawn_population_graph, spawn_slice_viewer, PopulationGraph, SliceViewer};
+use conway_3d::immigration::{apply_immigration, Immigration};
+use conway_3d::lenia::Lenia;
+use conway_3d::lod::{spawn_lod_cells, split_lod_cells};
+use conway_3d::meshing::{draw_cell_surface, spawn_cell_surface, CellSurface};
+use conway_3d::recording::{play_replay, record_run, Recorder, Replay};
+use conway_3d::rendering::{sort_translucent_instances, BlendAlpha, CellMaterialPlugin, CellOutlines, InstanceData, InstanceMaterialData};
+use conway_3d::reset::{auto_reset, AutoReset};
+use conway_3d::rule::Rule;
+use conway_3d::schedule::{apply_rule_schedule, RuleSchedule};
+use conway_3d::search::{run_search, SearchConfig};
+use conway_3d::seeding::{ImageSeed, SeedPattern};
+use conway_3d::shapes::{cycle_cell_mesh, use_cell_mesh, PendingCellMesh};
+use conway_3d::smoothlife::SmoothLife;
+use conway_3d::stats::{export_stats, SimStats, StatsExport};
+use conway_3d::tracking::{track_live_region, LiveRegion};
+use conway_3d::vox::{export_vox, VoxModel};
+use conway_3d::voxelize::{MeshSeedPlugin, PendingMeshSeed};
+use rand::Rng;
+use std::path::Path;
 
 fn main() {
-    App::new()
+    let mut app = App::new();
+
+    // Command line overrides for the preset in setup:
+    // cargo run -- assets/configs/coral.json
+    // cargo run -- --rule amoeba --catalog my_rules.toml
+    // cargo run --release -- --search found.toml [--rule coral]
+    // cargo run -- --rule coral --seed 42
+    // cargo run -- --rule coral --record coral.rec, then cargo run -- --replay coral.rec
+    // cargo run -- --rule coral --stats coral.csv
+    // cargo run -- assets/configs/two_species.ron
+    // cargo run -- assets/configs/cyclic_spirals.ron
+    // cargo run -- assets/configs/grow_then_erode.ron
+    // cargo run -- assets/configs/core_and_shell.ron
+    // cargo run --release -- assets/configs/lenia_blobs.ron
+    // cargo run --release -- assets/configs/smoothlife.ron
+    let args = CliArgs::parse(std::env::args().skip(1)).unwrap_or_else(|e| exit_with_error(&e));
+
+    let mut catalog = RuleCatalog::builtin().clone();
+    for path in &args.catalogs {
+        match RuleCatalog::load(path) {
+            Ok(loaded) => catalog.merge(loaded),
+            Err(e) => exit_with_error(&format!("Failed to load {}: {}", path.display(), e)),
+        }
+    }
+
+    // Rules by name from the built-in presets and every loaded catalog
+    let registry = RuleRegistry::from_catalog(&catalog);
+    if let Some(name) = &args.rule {
+        if let Err(e) = registry.resolve(name) {
+            exit_with_error(&e);
+        }
+    }
+
+    // Headless genetic search seeded from the chosen rule or the whole catalog
+    if let Some(output) = &args.search {
+        let seeds: Vec<Rule> = match &args.rule {
+            Some(name) => registry.get(name).cloned().into_iter().collect(),
+            None => catalog.entries.iter().map(|entry| entry.rule.clone()).collect(),
+        };
+        let config = SearchConfig { seed: args.seed.unwrap_or_default(), ..SearchConfig::default() };
+        if let Err(e) = run_search(&seeds, &config, output) {
+            exit_with_error(&format!("Failed to write {}: {}", output.display(), e));
+        }
+        return;
+    }
+
+    // Recorded runs play back without simulating, in the recorded grid size and rule
+    if let Some(path) = &args.replay {
+        match Replay::load(path) {
+            Ok(replay) => {
+                println!("Replaying {} frames of {} (seed {})", replay.frames(), path.display(), replay.seed);
+                app.insert_resource(replay);
+            }
+            Err(e) => exit_with_error(&format!("Failed to load {}: {}", path.display(), e)),
+        }
+    }
+
+    if let Some(path) = &args.config {
+        match SimConfig::load(path) {
+            Ok(config) => {
+                app.insert_resource(config);
+            }
+            Err(e) => exit_with_error(&format!("Failed to load {}: {}", path.display(), e)),
+        }
+    }
+
+    app.insert_resource(catalog)
+        .insert_resource(registry)
+        .insert_resource(args)
         .add_plugins((
             DefaultPlugins,
             CellMaterialPlugin,
+            MeshSeedPlugin,
             #[cfg(not(target_arch = "wasm32"))]
             WireframePlugin::default(),
         ))
-        .add_systems(Startup, setup)
+        .add_message::<BehaviorChanged>()
+        .add_systems(
+            Startup,
+            (
+                setup,
+                spawn_population_graph.after(setup).run_if(resource_exists::<PopulationGraph>),
+                spawn_slice_viewer.after(setup).run_if(resource_exists::<SliceViewer>),
+                spawn_cell_surface.after(setup),
+                spawn_quad_view.after(setup),
+                spawn_lod_cells.after(setup),
+            ),
+        )
         .add_systems(
             Update,
             (
-                simulate_step,
+                // Discrete systems only run when setup didn't pick a continuous engine
+                (
+                    // Rewinding shows an older generation, nothing simulates until it's live again
+                    apply_rule_schedule.run_if(resource_exists::<RuleSchedule>).run_if(history_is_live).before(simulate_step),
+                    apply_immigration.run_if(resource_exists::<Immigration>).run_if(history_is_live).before(simulate_step),
+                    simulate_step.run_if(history_is_live).run_if(not(resource_exists::<Replay>)),
+                    play_replay.run_if(resource_exists::<Replay>).run_if(history_is_live),
+                    (
+                        record_run.run_if(resource_exists::<Recorder>),
+                        export_stats.run_if(resource_exists::<StatsExport>),
+                        track_live_region,
+                        draw_population_graph.run_if(resource_exists::<PopulationGraph>),
+                        draw_slice_viewer.run_if(resource_exists::<SliceViewer>),
+                        track_buried_cells,
+                    )
+                        .after(simulate_step),
+                    record_history.run_if(resource_exists::<History>).after(simulate_step).after(play_replay),
+                    rewind_history.run_if(resource_exists::<History>),
+                    undo_edits,
+                    paint_cells.run_if(history_is_live),
+                    pick_cells.run_if(history_is_live),
+                    copy_paste.run_if(history_is_live),
+                    transform_cells.run_if(history_is_live),
+                    mutate_rule,
+                    cycle_rule,
+                    resize_grid,
+                    checkpoint_grid,
+                    adjust_temperature,
+                    adjust_steps_per_frame,
+                    export_vox,
+                    auto_reset.run_if(resource_exists::<AutoReset>).run_if(history_is_live).after(simulate_step),
+                )
+                    .run_if(resource_exists::<Grid>),
+                simulate_continuous::<Lenia>.run_if(resource_exists::<Lenia>),
+                simulate_continuous::<SmoothLife>.run_if(resource_exists::<SmoothLife>),
                 camera_movement,
                 camera_look,
                 handle_exit,
                 #[cfg(not(target_arch = "wasm32"))]
                 camera::toggle_wireframe,
+                cycle_cell_mesh,
+                adjust_slice_plane,
+                adjust_clip_box,
+                adjust_exploded_view,
+                layout_quad_view,
+                use_cell_mesh.run_if(resource_exists::<PendingCellMesh>),
+            ),
+        )
+        // After anything that rebuilt the instances this frame
+        .add_systems(
+            PostUpdate,
+            (
+                color_components.run_if(resource_exists::<ComponentTracker>),
+                animate_cells.run_if(resource_exists::<CellAnimation>).after(color_components),
+                // Before animating, the merged mesh draws the cells as built
+                draw_cell_surface.run_if(any_with_component::<CellSurface>).after(color_components).before(animate_cells),
+                sort_translucent_instances.run_if(any_with_component::<BlendAlpha>).after(animate_cells),
+                // With the camera and shadow cascade frusta of this frame
+                cull_instances
+                    .after(sort_translucent_instances)
+                    .after(VisibilitySystems::UpdateFrusta)
+                    .after(SimulationLightSystems::UpdateLightFrusta),
+                split_lod_cells.after(cull_instances),
             ),
         )
         .run();
 }
 
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
+    asset_server: Res<AssetServer>,
+    config: Option<Res<SimConfig>>,
+    registry: Res<RuleRegistry>,
+    args: Res<CliArgs>,
+    mut replay: Option<ResMut<Replay>>,
 ) {
-    // Preset rules from various sources:
+    // Preset rules from various sources (defined in assets/rules.toml, also selectable with --rule NAME):
     // let rule = Rule::rule_445();           // Classic 4/4/5 rule
     // let rule = Rule::builder();            // Complex expanding structures
     // let rule = Rule::pretty_crystals();    // Crystalline formations
@@ -70,41 +244,311 @@ fn setup(
     // Rule notation: survival/birth/states/method
     // 4-7/6-8/10/M means: survive with 4-7 neighbors, birth with 6-8, 10 states, Moore
     // let rule = Rule::from_ranges(4, 6, 5, 6, 11, rule::NeighborMethod::Moore);
+    // let rule: Rule = "4-7,12/6-8/10/M".parse().expect("invalid rule notation");
+    // let rule = Rule::pyroclastic().with_birth_chance(&[6], 0.4); // Birth on 6 neighbors only 40% of the time
+    // let rule = Rule::builder().with_decay(&[30]);  // Cells linger 30 generations in state 1, leaving long trails
+    // let rule = Rule::coral().with_instant_death(); // Cells die as soon as they stop surviving
+    // let rule = Rule::builder().with_max_age(40, rule::AgeAction::Decay); // Structures fade 40 generations after birth
+    // let rule = Rule::brain().with_birth_state(6).with_neighbor_threshold(4); // Born mid-way, states 4+ count as neighbors
+    // let rule = Rule::builder().with_shells(rule::ShellRule { // Survive on 2-3 face AND 5-9 edge/corner neighbors
+    //     face_survival: rule::RuleValue::from_range(2, 3), face_birth: rule::RuleValue::new(&[2]),
+    //     outer_survival: rule::RuleValue::from_range(5, 9), outer_birth: rule::RuleValue::from_range(4, 6),
+    // });
+    // let rule = Rule::amoeba().complement();        // Alive/dead swapped dual of a preset
+    // let rule = Rule::clouds_1().inverted_counts(); // Counts N become 26 - N (Moore)
+
+    // Command line overrides: --rule picks a registered rule, a config file replaces the preset
+    let rule = match (&args.rule, &config) {
+        (Some(name), _) => registry.get(name).cloned().expect("rule name is checked in main"),
+        (None, Some(config)) => config.rule.clone(),
+        (None, None) => rule,
+    };
+    let rule = replay.as_ref().map_or(rule, |replay| replay.rule.clone());
 
-    println!("Using rule with {} states", rule.states);
+    println!("Using rule {} ({} states)", rule, rule.states);
     let max_state = rule.states;
 
-    // Initialize grid
-    let size = 64;
-    let mut grid = Grid::new(size);
+    // The starting cells and probabilistic rules draw from one seeded RNG, so a run can be replayed with --seed
+    let seed = args.seed.or(config.as_ref().and_then(|config| config.seed)).unwrap_or_else(rand::random);
+    println!("Using seed {} (replay with --seed {})", seed, seed);
+    let mut rng = SimRng::new(seed);
+
+    // Random flips per step to keep patterns from stagnating, adjust with = and -
+    let temperature = config.as_ref().map_or(0.0, |config| config.temperature);
+    commands.insert_resource(Temperature(temperature));
+
+    // Generations per rendered frame, fast-forwards slow rules like expanding_blob(), adjust with ] and [
+    let steps_per_frame = config.as_ref().and_then(|config| config.steps_per_frame).unwrap_or(1);
+    commands.insert_resource(StepsPerFrame(steps_per_frame));
+
+    // A rule schedule takes over from the first generation
+    // commands.insert_resource(RuleSchedule::timeline([(0, Rule::builder()), (150, Rule::expand_then_die())]));
+    if let Some(schedule) = config.as_ref().and_then(|config| config.schedule.clone()) {
+        commands.insert_resource(schedule);
+    }
+
+    // Random cells injected every few generations, e.g. to sustain a slowly dying rule
+    // commands.insert_resource(Immigration::new(20, 50).with_ball(0.3));
+    if let Some(immigration) = config.as_ref().and_then(|config| config.immigration.clone()) {
+        commands.insert_resource(immigration);
+    }
+
+    // Initialize grid, e.g. 256 per side for point clouds
+    let size = config.as_ref().and_then(|config| config.size).unwrap_or(64);
+    // Per-axis boundaries, e.g. Boundaries::slab(Axis::Y) keeps growth between a dead floor and ceiling
+    let boundaries = config.as_ref().map_or(Boundaries::wrap(), |config| config.boundaries);
+    // let boundaries = Boundaries::tube(Axis::Y);
+    let layout = config.as_ref().map_or(CellLayout::Linear, |config| config.layout);
+    // Large grids step much faster on all cores, e.g. Grid::new(256).with_parallel(true)
+    let parallel = config.as_ref().is_some_and(|config| config.parallel);
+    // Asynchronous updating grows different structures from the same rule, e.g. UpdateMode::RandomSequential
+    let update_mode = config.as_ref().map_or(UpdateMode::Synchronous, |config| config.update_mode);
+    let mut grid = Grid::new(size)
+        .with_boundaries(boundaries)
+        .with_layout(layout)
+        .with_parallel(parallel)
+        .with_update_mode(update_mode);
+    if grid.layout() != layout {
+        println!("Grid size {} doesn't support the {:?} cell layout, using {:?}", size, layout, grid.layout());
+    }
+
+    // Grow inside a container shape, cells outside stay dead
+    // let domain = Some(Domain::Cylinder { axis: Axis::Y, radius: 0.6 });
+    if let Some(domain) = config.as_ref().and_then(|config| config.domain.as_ref()) {
+        grid = grid.with_domain(domain);
+    }
 
-    // Spawn dense cluster in center like the reference repo
-    grid.spawn_center_cluster(&rule, max_state, 6, 12 * 12 * 12);
+    // Static obstacles the cells grow around, optionally counting as neighbors to cling to
+    // grid.place_obstacle(&Obstacle::Plane { axis: Axis::Y, position: 0.4 }, std::slice::from_ref(&rule));
+    // grid.place_obstacle(&Obstacle::Voxels(domain::load_voxels("shape.txt").unwrap()), std::slice::from_ref(&rule));
+    if let Some(config) = &config {
+        grid = grid.with_counting_obstacles(config.obstacles_count);
+        for obstacle in &config.obstacles {
+            grid.place_obstacle(obstacle, std::slice::from_ref(&rule));
+        }
+    }
+
+    // Different rules per region, e.g. a builder core inside a crystal shell
+    // A gradient shifts the rule's counts across the grid, e.g. stricter survival towards the edges
+    let gradient = config.as_ref().and_then(|config| config.gradient.as_ref()).map(|gradient| gradient.zones(&rule));
+    let zones = config.as_ref().and_then(|config| config.zones.clone()).or(gradient);
+    // let zones = Some(ZonedRules::gradient(GradientDirection::Radial, 8, |t| Rule::builder().shifted((t * 3.0) as i32, 0)));
+    // let zones = Some(ZonedRules::core_and_shell(Rule::builder(), Rule::pretty_crystals(), 0.5));
+    // let zones = Some(ZonedRules::split_x(Rule::coral(), Rule::amoeba()));
+
+    // Spawn dense cluster in center like the reference repo, unless the config picks another seed pattern
+    // Multi-species configs get one cluster per species instead, cyclic automata start from noise
+    let seed_pattern = config.as_ref().and_then(|config| config.seed_pattern.clone()).unwrap_or_default();
+    // let seed_pattern = SeedPattern::HollowSphere { radius: 0.4, thickness: 2.0 };
+    let seed_image = config.as_ref().and_then(|config| config.seed_image.clone());
+    // let seed_image = Some(ImageSeed { path: "logo.png".into(), depth: 4, density: false });
+    let seed_vox = config.as_ref().and_then(|config| config.seed_vox.clone());
+    let ecosystem = config.as_ref().and_then(|config| config.ecosystem.clone());
+    let cyclic = config.as_ref().and_then(|config| config.cyclic.clone());
+    // Meshes load in the background and are rasterized into the grid once in, see `seed_from_mesh`
+    let seed_mesh = config.as_ref().and_then(|config| config.seed_mesh.clone()).filter(|_| cyclic.is_none() && ecosystem.is_none());
+    // let seed_mesh = Some(MeshSeed { path: "models/bunny.glb".into(), resolution: 48, solid: true });
+    let fallback = seed_mesh.is_none().then_some(&seed_pattern);
+    // Runs seeded from an image or model start over from their starting cells rather than the pattern
+    let reset_pattern = (seed_image.is_none() && seed_vox.is_none() && seed_mesh.is_none()).then(|| seed_pattern.clone());
+    match (cyclic, ecosystem, zones) {
+        (Some(cyclic), _, _) => {
+            grid.fill_random_states(cyclic.states, &mut rng.0);
+            commands.insert_resource(cyclic);
+        }
+        (None, Some(ecosystem), _) => {
+            grid.spawn_species_clusters(&ecosystem, 6, 12 * 12 * 12, &mut rng.0);
+            commands.insert_resource(ecosystem);
+        }
+        (None, None, Some(zones)) => {
+            grid.assign_zones(&zones.layout, &zones.rules);
+            seed_grid(&mut grid, fallback, seed_image.as_ref(), seed_vox.as_deref(), &zones.rules, &mut rng.0);
+            commands.insert_resource(zones);
+        }
+        (None, None, None) => seed_grid(&mut grid, fallback, seed_image.as_ref(), seed_vox.as_deref(), std::slice::from_ref(&rule), &mut rng.0),
+    }
+    if let Some(seed_mesh) = seed_mesh {
+        commands.insert_resource(PendingMeshSeed::load(seed_mesh, seed_pattern, &asset_server));
+    }
 
     // Create color interpolation info
-    // Try different color methods: StateLerp, DistToCenter, Neighbor, Single
+    // Try different color methods: StateLerp, DistToCenter, Neighbor, Single, Species, Component, Activity, Rainbow, Palette, Velocity
     let colors = CellColors {
         birth_color: Color::srgb(1.0, 1.0, 0.0),
         death_color: Color::srgb(1.0, 0.0, 0.0),
         method: ColorMethod::DistToCenter,        // Shows depth/3D structure nicely!
+        ..default()
     };
+    // let colors = CellColors { method: ColorMethod::StateLerp, ..default() }.with_gradient(CellColors::fire_gradient());
+    // let colors = CellColors { method: ColorMethod::StateLerp, scale: ScaleCurve::Power(0.5), ..default() };
+    // let colors = CellColors { method: ColorMethod::Single, occlusion: 0.6, ..default() }; // Crevices darken
+    let colors = config.as_ref().map_or(colors, |config| config.colors.clone());
+    // Only the activity colors need each cell's change counts
+    grid = grid.with_activity(colors.method.reads_activity());
 
-    let cube_mesh = meshes.add(Cuboid::new(1.0, 1.0, 1.0));
+    // Shape of every cell, K cycles through them
+    let cell_mesh = config.as_ref().and_then(|config| config.cell_mesh).unwrap_or_default();
+    // let cell_mesh = CellMeshKind::RoundedCube;
+    // let cell_mesh = CellMeshKind::Point;
+    let cells_mesh = meshes.add(cell_mesh.mesh());
+    commands.insert_resource(cell_mesh);
+    // A merged mesh of the exposed faces (or a smooth or gooey surface) instead of a cube per cell
+    let renderer = config.as_ref().and_then(|config| config.renderer).unwrap_or_default();
+    // let renderer = CellRenderer::Greedy;
+    // let renderer = CellRenderer::Isosurface(0.5);
+    // let renderer = CellRenderer::Metaballs { block: 2, blur: 1, level: 0.15 };
+    commands.insert_resource(renderer);
+    // Mesh from the assets folder drawn for every cell instead, once it's loaded
+    let custom_mesh = config.as_ref().and_then(|config| config.custom_mesh.clone());
+    // let custom_mesh = Some(CustomCellMesh { path: "models/crystal.obj".into(), size: 0.9 });
+    if let Some(custom_mesh) = custom_mesh {
+        commands.insert_resource(PendingCellMesh::load(&custom_mesh, &asset_server));
+    }
 
     // Build initial instance data from spawned cells
-    let instance_data = grid.build_instances(&colors, max_state);
+    // Continuous engines replace the discrete grid, sharing its instanced rendering
+    let lenia = config.as_ref().and_then(|config| config.lenia.clone());
+    let smoothlife = config.as_ref().and_then(|config| config.smoothlife.clone());
+    let instance_data = match (lenia, smoothlife) {
+        (Some(lenia), _) => {
+            println!("Using Lenia rule {:?}", lenia);
+            let radius = lenia.radius * 2;
+            start_continuous(&mut commands, Lenia::new(lenia), size, radius, &colors, &mut rng.0)
+        }
+        (None, Some(smoothlife)) => {
+            println!("Using SmoothLife rule {:?}", smoothlife);
+            let radius = smoothlife.outer_radius * 2;
+            start_continuous(&mut commands, SmoothLife::new(smoothlife), size, radius, &colors, &mut rng.0)
+        }
+        (None, None) => {
+            if let Some(replay) = replay.as_deref_mut() {
+                replay.restart(&mut grid);
+            } else if let Some(path) = &args.record {
+                match Recorder::create(path, &grid, &rule, seed) {
+                    Ok(recorder) => {
+                        println!("Recording to {}", path.display());
+                        commands.insert_resource(recorder);
+                    }
+                    Err(e) => eprintln!("Failed to record to {}: {}", path.display(), e),
+                }
+            }
+            let instance_data = grid.build_instances(&colors, max_state, rule.neighbor_method.max_neighbors());
+            // Recent generations to step back through with , and .
+            let mut history = config.as_ref().and_then(|config| config.history.clone()).unwrap_or_default();
+            // let mut history = History::new(200, 32);
+            history.record(&grid);
+            commands.insert_resource(history);
+            // Reports when the run starts repeating itself
+            let cycles = config.as_ref().and_then(|config| config.cycles.clone()).unwrap_or_default();
+            // let cycles = CycleDetector::new(5000);
+            commands.insert_resource(cycles);
+            // Starts the run over once it dies out, for an unattended ambient display
+            let auto_reset = config.as_ref().and_then(|config| config.auto_reset.clone());
+            // let auto_reset = Some(AutoReset { on_oscillator: true, ..default() });
+            if let Some(mut auto_reset) = auto_reset {
+                auto_reset.remember(&grid, seed, reset_pattern);
+                commands.insert_resource(auto_reset);
+            }
+            // Population, births, deaths and bounds of each generation
+            let mut stats = SimStats::default();
+            // let mut stats = SimStats::new(100_000);
+            stats.record(&grid, max_state);
+            commands.insert_resource(stats);
+            if let Some(path) = args.stats.as_ref().filter(|_| replay.is_none()) {
+                match StatsExport::create(path) {
+                    Ok(export) => {
+                        println!("Writing stats to {}", path.display());
+                        commands.insert_resource(export);
+                    }
+                    Err(e) => eprintln!("Failed to write stats to {}: {}", path.display(), e),
+                }
+            }
+            // Population chart in the corner, H hides it
+            let population_graph = config.as_ref().and_then(|config| config.population_graph).unwrap_or_default();
+            // let population_graph = PopulationGraph { generations: 1000, width: 400, ..default() };
+            commands.insert_resource(population_graph);
+            // A single layer of the grid as a 2D image, Tab shows it
+            let slice_viewer = config.as_ref().and_then(|config| config.slice_viewer).unwrap_or_default();
+            // let slice_viewer = SliceViewer { visible: true, axis: Axis::Y, cell_pixels: 4, ..default() };
+            commands.insert_resource(slice_viewer);
+            // Centroid and bounds of the living cells, e.g. to aim the camera at
+            commands.insert_resource(LiveRegion::of(&grid));
+            // Births and deaths grow and shrink between generations instead of popping
+            let animation = config.as_ref().and_then(|config| config.animation.clone());
+            // let animation = Some(CellAnimation { fraction: 0.5, ..default() });
+            if let Some(animation) = animation {
+                commands.insert_resource(animation);
+            }
+            // Blobs keep their colors from generation to generation
+            if colors.method == ColorMethod::Component {
+                commands.insert_resource(ComponentTracker::default());
+            }
+            // Undo stack of painting and pasting, Ctrl+Z / Ctrl+Y
+            commands.insert_resource(EditHistory::default());
+            // Paint mode, B toggles it
+            let brush = config.as_ref().and_then(|config| config.brush).unwrap_or_default();
+            // let brush = Brush::default().with_radius(6.0).with_reach(60.0);
+            commands.insert_resource(brush);
+            // Cells copied with C to paste with P
+            commands.insert_resource(Clipboard::default());
+            commands.insert_resource(grid);
+            instance_data
+        }
+    };
 
     // Spawn single entity with all instances
-    commands.spawn((
-        Mesh3d(cube_mesh),
+    // Its mesh bounds only cover the cell at the center, the instances are culled by `cull_instances` instead
+    let cells = commands.spawn((
+        Mesh3d(cells_mesh),
         Transform::IDENTITY,
         Visibility::default(),
         InstanceMaterialData(instance_data),
-    ));
+        NoFrustumCulling,
+    )).id();
+    if colors.translucent {
+        commands.entity(cells).insert(BlendAlpha);
+    }
+    if colors.outlines {
+        commands.entity(cells).insert(CellOutlines);
+    }
 
-    commands.insert_resource(grid);
+    // Glowing newborns need an HDR camera with bloom to light up their surroundings
+    let bloom = colors.glow > 0.0;
+    // Far cells fading into the background
+    let fog = config.as_ref().and_then(|config| config.fog.clone());
+    // Cells shadowing each other from a directional light
+    let shadows = config.as_ref().and_then(|config| config.shadows.clone());
+    // let shadows = Some(CellShadows::default());
+    if let Some(shadows) = shadows {
+        commands.spawn(shadows.light());
+    }
+    // Plane cutting away the cells on one side to look inside solid structures like pretty_crystals()
+    let slice = config.as_ref().and_then(|config| config.slice.clone()).unwrap_or_default();
+    // let slice = SlicePlane { enabled: true, normal: Vec3::X, offset: 0.0 };
+    commands.insert_resource(slice);
+    // Box isolating the cells inside it, or carving them out
+    let clip_box = config.as_ref().and_then(|config| config.clip_box.clone()).unwrap_or_default();
+    // let clip_box = ClipBox { enabled: true, size: Vec3::splat(24.0), invert: true, ..default() };
+    commands.insert_resource(clip_box);
+    // Octants or chunks pulled apart to expose the inner layers
+    let exploded = config.as_ref().and_then(|config| config.exploded.clone()).unwrap_or_default();
+    // let exploded = ExplodedView { enabled: true, gap: 8.0, chunk: 16.0 };
+    commands.insert_resource(exploded);
+    // Orthographic top, front and side views sharing the window with the fly camera
+    let quad_view = config.as_ref().and_then(|config| config.quad_view).unwrap_or_default();
+    // let quad_view = QuadView { enabled: true, extent: 100.0 };
+    commands.insert_resource(quad_view);
+    // Far cells drawn as single points, only the near ones as full cell meshes
+    let lod = config.as_ref().and_then(|config| config.lod).unwrap_or_default();
+    // let lod = CellLod { enabled: true, distance: 100.0 };
+    commands.insert_resource(lod);
+    // Chunks outside the camera views skipped when uploading the instances
+    let culling = config.as_ref().and_then(|config| config.culling).unwrap_or_default();
+    // let culling = FrustumCulling { enabled: true, chunk: 8, gpu: false };
+    commands.insert_resource(culling);
     commands.insert_resource(rule);
     commands.insert_resource(colors);
+    commands.insert_resource(rng);
 
     // Camera looks at origin (grid is centered around origin now)
     let camera_pos = Vec3::new(50.0, 50.0, 120.0);
@@ -115,9 +559,66 @@ fn setup(
     let yaw = -direction.x.atan2(-direction.z);
     let pitch = direction.y.asin();
 
-    commands.spawn((
+    let camera = commands.spawn((
         Camera3d::default(),
         Transform::from_xyz(camera_pos.x, camera_pos.y, camera_pos.z).looking_at(target, Vec3::Y),
         FlyCamera::new(50.0, 0.0005, pitch, yaw),
-    ));
+        // The HUD would otherwise go to the quad view's highest ordered camera, even while it's off
+        IsDefaultUiCamera,
+    )).id();
+    if bloom {
+        commands.entity(camera).insert(Bloom::NATURAL);
+    }
+    if let Some(fog) = fog {
+        commands.entity(camera).insert(fog.distance_fog());
+    }
+}
+
+/// Insert a continuous grid seeded with a noise ball of `radius` and its engine, returning the initial instances
+fn start_continuous(
+    commands: &mut Commands,
+    simulation: impl Simulation,
+    size: i32,
+    radius: i32,
+    colors: &CellColors,
+    rng: &mut impl Rng,
+) -> Vec<InstanceData> {
+    let mut field = ContinuousGrid::new(size);
+    field.spawn_center_blob(radius, rng);
+    let instance_data = field.build_instances(colors);
+    commands.insert_resource(field);
+    commands.insert_resource(simulation);
+    instance_data
+}
+
+/// Seed the grid from the image and model that are set and load, from the pattern when none do
+/// Without a pattern (a mesh seed is loading) the grid may stay empty
+fn seed_grid(
+    grid: &mut Grid,
+    pattern: Option<&SeedPattern>,
+    image: Option<&ImageSeed>,
+    vox: Option<&Path>,
+    rules: &[Rule],
+    rng: &mut impl Rng,
+) {
+    let mut seeded = false;
+    if let Some(image) = image {
+        match image.seed(grid, rules, rng) {
+            Ok(()) => seeded = true,
+            Err(e) => eprintln!("Failed to load seed image {}: {}", image.path.display(), e),
+        }
+    }
+    if let Some(path) = vox {
+        match VoxModel::load(path) {
+            Ok(model) => {
+                println!("Seeding {} voxels from {}", model.voxels.len(), path.display());
+                model.seed(grid, rules);
+                seeded = true;
+            }
+            Err(e) => eprintln!("Failed to load seed model {}: {}", path.display(), e),
+        }
+    }
+    if let Some(pattern) = pattern.filter(|_| !seeded) {
+        grid.seed_zoned(pattern, rules, rng);
+    }
 }
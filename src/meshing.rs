@@ -0,0 +1,236 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::camera::visibility::NoFrustumCulling;
+use bevy::mesh::{Indices, PrimitiveTopology};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::continuous::ContinuousGrid;
+use crate::grid::Grid;
+use crate::isosurface::{blur, downsample, marching_cubes};
+use crate::rendering::{InstanceData, InstanceMaterialData};
+
+/// How the cells' instances turn into triangles on screen
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum CellRenderer {
+    /// One instanced mesh per cell (see `CellMeshKind`), scaled and colored per cell
+    #[default]
+    Instanced,
+    /// One merged mesh of only the cube faces between living and dead cells, neighboring faces of the same
+    /// color joined into larger quads. Far fewer triangles for dense blobs, but every cell is a full cube
+    Greedy,
+    /// Smooth surface where the cells' states, as a field from 0 (dead) to 1 (the highest state alive or
+    /// a continuous value of 1), cross this level. Organic blobs instead of blocky cubes
+    Isosurface(f32),
+    /// Gooey surface through the cells' states averaged over blocks of `block`³ cells and blurred over `blur`
+    /// blocks, where the smoothed density crosses `level`. Lumps merge like metaballs
+    Metaballs { block: i32, blur: i32, level: f32 },
+}
+
+/// Entity drawing the merged mesh of the greedy and isosurface renderers
+#[derive(Component)]
+pub struct CellSurface;
+
+/// Merged mesh of the exposed faces of full cubes at the instances, colored per vertex. Instances scaled to
+/// nothing (e.g. shrinking away) are left out
+pub fn greedy_mesh(instances: &[InstanceData]) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    let mesh = |positions, normals, colors, indices| {
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+            .with_inserted_indices(Indices::U32(indices))
+    };
+
+    let visible: Vec<&InstanceData> = instances.iter().filter(|instance| instance.scale > 0.0).collect();
+    let Some(origin) = visible.iter().map(|instance| instance.position).reduce(Vec3::min) else {
+        return mesh(positions, normals, colors, indices);
+    };
+    // Cells on a dense box around the instances, each holding its color's index in `palette` plus one
+    let cell = |position: Vec3| (position - origin).round().as_ivec3();
+    let extent = visible.iter().map(|instance| cell(instance.position)).fold(IVec3::ZERO, IVec3::max) + 1;
+    let mut palette = Vec::new();
+    let mut palette_index = HashMap::new();
+    let mut cells = vec![0u32; (extent.x * extent.y * extent.z) as usize];
+    let index = |pos: IVec3| (pos.x + pos.y * extent.x + pos.z * extent.x * extent.y) as usize;
+    for instance in &visible {
+        let color = *palette_index.entry(instance.color.map(f32::to_bits)).or_insert_with(|| {
+            palette.push(instance.color);
+            palette.len() as u32
+        });
+        cells[index(cell(instance.position))] = color;
+    }
+    let color_at = |pos: IVec3| {
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(extent).any() { 0 } else { cells[index(pos)] }
+    };
+
+    // Sweep each axis both ways, slice by slice, merging runs of same-colored exposed faces into quads
+    let mut mask = Vec::new();
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let (du, dv) = (IVec3::AXES[u], IVec3::AXES[v]);
+        let (width, height) = (extent[u] as usize, extent[v] as usize);
+        for direction in [1, -1] {
+            let normal = IVec3::AXES[axis] * direction;
+            for layer in 0..extent[axis] {
+                mask.clear();
+                mask.extend((0..height).flat_map(|j| (0..width).map(move |i| (i, j))).map(|(i, j)| {
+                    let pos = IVec3::AXES[axis] * layer + du * i as i32 + dv * j as i32;
+                    let color = color_at(pos);
+                    if color != 0 && color_at(pos + normal) == 0 { color } else { 0 }
+                }));
+                for j in 0..height {
+                    let mut i = 0;
+                    while i < width {
+                        let color = mask[j * width + i];
+                        if color == 0 {
+                            i += 1;
+                            continue;
+                        }
+                        let quad_width = (i..width).take_while(|&x| mask[j * width + x] == color).count();
+                        let quad_height = (j..height)
+                            .take_while(|&y| (i..i + quad_width).all(|x| mask[y * width + x] == color))
+                            .count();
+                        for y in j..j + quad_height {
+                            mask[y * width + i..y * width + i + quad_width].fill(0);
+                        }
+
+                        // Faces sit on the near or far side of their cells, half a cell from the centers
+                        let plane = layer as f32 + if direction > 0 { 0.5 } else { -0.5 };
+                        let corner = origin + IVec3::AXES[axis].as_vec3() * plane + (du * i as i32 + dv * j as i32).as_vec3()
+                            - (du + dv).as_vec3() * 0.5;
+                        let (side_u, side_v) = (du.as_vec3() * quad_width as f32, dv.as_vec3() * quad_height as f32);
+                        let start = positions.len() as u32;
+                        positions.extend([corner, corner + side_u, corner + side_u + side_v, corner + side_v].map(|point| point.to_array()));
+                        normals.extend([normal.as_vec3().to_array(); 4]);
+                        colors.extend([palette[color as usize - 1]; 4]);
+                        // u × v points along the axis, so the corners run counter-clockwise seen from outside
+                        // on the positive side and clockwise on the negative
+                        let order = if direction > 0 { [0, 1, 2, 0, 2, 3] } else { [0, 2, 1, 0, 3, 2] };
+                        indices.extend(order.map(|corner| start + corner));
+                        i += quad_width;
+                    }
+                }
+            }
+        }
+    }
+    mesh(positions, normals, colors, indices)
+}
+
+/// Smooth surface through the cells' states (see `CellRenderer::Isosurface`), colored like the instances
+pub fn isosurface_mesh(instances: &[InstanceData], field: &[f32], size: i32, level: f32) -> Mesh {
+    let grid_center = Vec3::splat((size - 1) as f32 * 0.5);
+    let mut colors = vec![[1.0; 4]; field.len()];
+    for instance in instances {
+        let pos = (instance.position + grid_center).round().as_ivec3();
+        if pos.cmpge(IVec3::ZERO).all() && pos.cmplt(IVec3::splat(size)).all() {
+            colors[(pos.x + pos.y * size + pos.z * size * size) as usize] = instance.color;
+        }
+    }
+    marching_cubes(field, size, level, 1.0, -grid_center, |index| colors[index])
+}
+
+/// Smoothed surface through the cells' states (see `CellRenderer::Metaballs`), colored by the blurred colors
+/// of the instances weighted by their density
+pub fn metaball_mesh(instances: &[InstanceData], field: &[f32], size: i32, block: i32, radius: i32, level: f32) -> Mesh {
+    let block = block.max(1);
+    let grid_center = Vec3::splat((size - 1) as f32 * 0.5);
+    let (mut density, blocks) = downsample(field, size, block);
+    // Colors summed per block as red, green, blue and alpha times density, divided by the density after blurring
+    let mut channels = vec![vec![0.0; density.len()]; 4];
+    for instance in instances {
+        let pos = (instance.position + grid_center).round().as_ivec3();
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(IVec3::splat(size)).any() {
+            continue;
+        }
+        let weight = field[(pos.x + pos.y * size + pos.z * size * size) as usize];
+        let pos = pos / block;
+        let index = (pos.x + pos.y * blocks + pos.z * blocks * blocks) as usize;
+        for (channel, value) in channels.iter_mut().zip(instance.color) {
+            channel[index] += value * weight;
+        }
+    }
+    let mut weights: Vec<f32> = density.iter().map(|density| density * block.pow(3) as f32).collect();
+    blur(&mut density, blocks, radius);
+    blur(&mut weights, blocks, radius);
+    for channel in &mut channels {
+        blur(channel, blocks, radius);
+    }
+    let color = |index: usize| std::array::from_fn(|channel| if weights[index] > 0.0 { channels[channel][index] / weights[index] } else { 1.0 });
+    // Block centers sit in the middle of their cells
+    let offset = Vec3::splat((block - 1) as f32 * 0.5) - grid_center;
+    marching_cubes(&density, blocks, level, block as f32, offset, color)
+}
+
+/// Merged mesh of the renderer, None for the instanced renderer or without a grid to draw
+fn surface_mesh(renderer: CellRenderer, instances: &[InstanceData], grid: Option<&Grid>, continuous: Option<&ContinuousGrid>) -> Option<Mesh> {
+    match renderer {
+        CellRenderer::Instanced => None,
+        CellRenderer::Greedy => Some(greedy_mesh(instances)),
+        CellRenderer::Isosurface(_) | CellRenderer::Metaballs { .. } => {
+            let (field, size) = match (grid, continuous) {
+                (Some(grid), _) => {
+                    let states = grid.states();
+                    let highest = states.iter().copied().max().unwrap_or(0).max(1) as f32;
+                    (states.iter().map(|&state| state as f32 / highest).collect(), grid.size)
+                }
+                (None, Some(continuous)) => (continuous.values().to_vec(), continuous.size),
+                (None, None) => return None,
+            };
+            Some(match renderer {
+                CellRenderer::Metaballs { block, blur, level } => metaball_mesh(instances, &field, size, block, blur, level),
+                CellRenderer::Isosurface(level) => isosurface_mesh(instances, &field, size, level),
+                _ => unreachable!(),
+            })
+        }
+    }
+}
+
+/// Rebuild the merged mesh whenever new instances come in, after anything that rebuilds or recolors them
+pub fn draw_cell_surface(
+    renderer: Res<CellRenderer>,
+    grid: Option<Res<Grid>>,
+    continuous: Option<Res<ContinuousGrid>>,
+    instance_query: Query<Ref<InstanceMaterialData>>,
+    surface_query: Query<&Mesh3d, With<CellSurface>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let Ok(instances) = instance_query.single() else { return };
+    if !instances.is_changed() {
+        return;
+    }
+    for handle in &surface_query {
+        if let (Some(mesh), Some(surface)) =
+            (meshes.get_mut(handle), surface_mesh(*renderer, &instances.0, grid.as_deref(), continuous.as_deref()))
+        {
+            *mesh = surface;
+        }
+    }
+}
+
+/// Hide the instanced cells and draw them as a merged mesh instead with the greedy or isosurface renderer
+pub fn spawn_cell_surface(
+    mut commands: Commands,
+    renderer: Res<CellRenderer>,
+    grid: Option<Res<Grid>>,
+    continuous: Option<Res<ContinuousGrid>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut instance_query: Query<(&InstanceMaterialData, &mut Visibility)>,
+) {
+    for (instances, mut visibility) in &mut instance_query {
+        let Some(surface) = surface_mesh(*renderer, &instances.0, grid.as_deref(), continuous.as_deref()) else { continue };
+        *visibility = Visibility::Hidden;
+        // Like the instances, colors come from the vertices without any lighting
+        commands.spawn((
+            CellSurface,
+            Mesh3d(meshes.add(surface)),
+            MeshMaterial3d(materials.add(StandardMaterial { unlit: true, ..default() })),
+            // The mesh's bounds change every generation
+            NoFrustumCulling,
+        ));
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::math::IVec3;
+use crate::grid::{Boundaries, Boundary};
+
+/// One bit per cell, packed 64 cells per word along X, so neighbor counts are summed 64 cells at a time
+/// Rows are ordered y + z * size; only grids whose size is a multiple of 64 can be packed
+pub struct BitGrid {
+    size: i32,
+    words: usize, // Words per row
+    bits: Vec<u64>,
+}
+
+impl BitGrid {
+    /// Whether a grid of `size` cells per side can be packed: a positive multiple of 64
+    pub fn fits(size: i32) -> bool {
+        size > 0 && size % 64 == 0
+    }
+
+    /// Empty grid of `size` cells per side, None unless it `fits`
+    pub fn new(size: i32) -> Option<Self> {
+        if !Self::fits(size) {
+            return None;
+        }
+        let words = size as usize / 64;
+        Some(Self { size, words, bits: vec![0; words * (size * size) as usize] })
+    }
+
+    /// Set the bit of the cell at `pos`
+    #[inline]
+    pub fn set(&mut self, pos: IVec3) {
+        let x = pos.x as usize;
+        let word = self.row_start(pos.y, pos.z) + x / 64;
+        self.bits[word] |= 1 << (x % 64);
+    }
+
+    /// Words of the row at (`y`, `z`), bit x % 64 of word x / 64 being cell x
+    pub fn row_mut(&mut self, y: i32, z: i32) -> &mut [u64] {
+        let start = self.row_start(y, z);
+        &mut self.bits[start..start + self.words]
+    }
+
+    #[inline]
+    fn row_start(&self, y: i32, z: i32) -> usize {
+        (y + z * self.size) as usize * self.words
+    }
+
+    /// Number of set cells at each `offset` from every cell, past the edges as `boundaries` say,
+    /// handed to `row_counts(y, z, counts)` one row at a time in X order
+    /// Each row is summed bit-sliced (plane p holds bit p of 64 cells' counts), then unpacked
+    pub fn count(&self, offsets: &[IVec3], boundaries: Boundaries, mut row_counts: impl FnMut(i32, i32, &[u16])) {
+        let planes = (u16::BITS - (offsets.len() as u16).leading_zeros()).max(1) as usize;
+        let (size, words) = (self.size, self.words);
+
+        // Where each offset's X shift starts reading, and which bits it keeps past a dead X edge
+        let shifts: Vec<(usize, u32, Vec<u64>)> = offsets
+            .iter()
+            .map(|offset| {
+                let shift = offset.x.rem_euclid(size) as usize;
+                let keep = (0..words)
+                    .map(|word| match boundaries.x {
+                        Boundary::Wrap => u64::MAX,
+                        // Cells whose neighbor lies past the edge read nothing
+                        Boundary::Dead => {
+                            let first = 64 * word as i32;
+                            let low = (-offset.x).clamp(0, size) - first;
+                            let high = (size - offset.x).clamp(0, size) - first;
+                            range_bits(low.clamp(0, 64) as u32, high.clamp(0, 64) as u32)
+                        }
+                    })
+                    .collect();
+                (shift / 64, (shift % 64) as u32, keep)
+            })
+            .collect();
+
+        let mut sums = vec![0u64; planes * words];
+        let mut lanes = vec![0u64; size as usize / 4];
+        let mut counts = vec![0u16; size as usize];
+        for z in 0..size {
+            for y in 0..size {
+                sums.fill(0);
+                for (offset, (word_shift, bit_shift, keep)) in offsets.iter().zip(&shifts) {
+                    let (Some(source_y), Some(source_z)) = (boundaries.y.resolve(y + offset.y, size), boundaries.z.resolve(z + offset.z, size)) else {
+                        continue;
+                    };
+                    let source = &self.bits[self.row_start(source_y, source_z)..][..words];
+                    for word in 0..words {
+                        // Bit x of the shifted word is source bit (x + offset.x) mod size
+                        let low = source[(word + word_shift) % words];
+                        let mut carry = if *bit_shift == 0 {
+                            low
+                        } else {
+                            low >> bit_shift | source[(word + word_shift + 1) % words] << (64 - bit_shift)
+                        } & keep[word];
+                        // Ripple-carry add of one bit per cell into the bit-sliced sums
+                        for plane in 0..planes {
+                            if carry == 0 {
+                                break;
+                            }
+                            let sum = &mut sums[plane * words + word];
+                            let next = *sum & carry;
+                            *sum ^= carry;
+                            carry = next;
+                        }
+                    }
+                }
+
+                // Unpack four cells' counts at a time, as 16-bit lanes of a word
+                lanes.fill(0);
+                for plane in 0..planes {
+                    for word in 0..words {
+                        let bits = sums[plane * words + word];
+                        if bits == 0 {
+                            continue;
+                        }
+                        for (nibble, lane) in lanes[word * 16..(word + 1) * 16].iter_mut().enumerate() {
+                            *lane |= SPREAD[(bits >> (4 * nibble)) as usize & 15] << plane;
+                        }
+                    }
+                }
+                for (four, lane) in counts.chunks_exact_mut(4).zip(&lanes) {
+                    for (i, count) in four.iter_mut().enumerate() {
+                        *count = (lane >> (16 * i)) as u16;
+                    }
+                }
+                row_counts(y, z, &counts);
+            }
+        }
+    }
+}
+
+/// Each nibble spread into four 16-bit lanes, bit i becoming the lowest bit of lane i
+const SPREAD: [u64; 16] = {
+    let mut table = [0; 16];
+    let mut nibble = 0;
+    while nibble < 16 {
+        let mut bit = 0;
+        while bit < 4 {
+            if nibble >> bit & 1 == 1 {
+                table[nibble] |= 1 << (16 * bit);
+            }
+            bit += 1;
+        }
+        nibble += 1;
+    }
+    table
+};
+
+/// Bits `low..high` of a word
+#[inline]
+fn range_bits(low: u32, high: u32) -> u64 {
+    if low >= high {
+        return 0;
+    }
+    let upto = |bit: u32| if bit >= 64 { u64::MAX } else { (1 << bit) - 1 };
+    upto(high) & !upto(low)
+}
@@ -0,0 +1,143 @@
+use bevy::math::{IVec3, ivec3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use crate::rule::NeighborMethod;
+
+/// Largest number of neighbor offsets a pattern can describe (one bit each in a u32)
+pub const MAX_PATTERN_NEIGHBORS: usize = 32;
+
+/// Non-totalistic birth/survival: which specific neighbors are at max_state, not just how many
+/// A pattern is a bitmask where bit N is set when the Nth offset of the neighborhood is alive
+/// Neighborhoods larger than MAX_PATTERN_NEIGHBORS only use their first 32 offsets
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct PatternRule {
+    /// Neighbor patterns that keep a cell alive at max_state
+    #[serde(default)]
+    pub survival: BTreeSet<u32>,
+    /// Neighbor patterns that spawn a new cell
+    #[serde(default)]
+    pub birth: BTreeSet<u32>,
+}
+
+impl PatternRule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Survive on the pattern with `alive` offsets set, plus all its rotations and reflections
+    pub fn with_survival(mut self, method: &NeighborMethod, alive: &[IVec3]) -> Self {
+        let offsets = pattern_offsets(method);
+        self.survival.extend(isotropic_class(offsets, pattern_mask(offsets, alive)));
+        self
+    }
+
+    /// Spawn on the pattern with `alive` offsets set, plus all its rotations and reflections
+    pub fn with_birth(mut self, method: &NeighborMethod, alive: &[IVec3]) -> Self {
+        let offsets = pattern_offsets(method);
+        self.birth.extend(isotropic_class(offsets, pattern_mask(offsets, alive)));
+        self
+    }
+
+    /// Check if a cell at max_state with this neighbor pattern survives
+    #[inline]
+    pub fn survives(&self, pattern: u32) -> bool {
+        self.survival.contains(&pattern)
+    }
+
+    /// Check if a dead cell with this neighbor pattern is born
+    #[inline]
+    pub fn births(&self, pattern: u32) -> bool {
+        self.birth.contains(&pattern)
+    }
+}
+
+/// The offsets a pattern's bits refer to, in neighborhood order
+pub fn pattern_offsets(method: &NeighborMethod) -> &[IVec3] {
+    let offsets = method.get_neighbors();
+    &offsets[..offsets.len().min(MAX_PATTERN_NEIGHBORS)]
+}
+
+/// Pattern with a bit set for each of `alive` that is part of the neighborhood
+pub fn pattern_mask(offsets: &[IVec3], alive: &[IVec3]) -> u32 {
+    offsets
+        .iter()
+        .enumerate()
+        .filter(|(_, offset)| alive.contains(offset))
+        .fold(0, |mask, (bit, _)| mask | 1 << bit)
+}
+
+/// Every pattern reachable from `pattern` by a rotation or reflection of the cube
+/// Symmetries that don't map the neighborhood onto itself (asymmetric custom shapes) are skipped
+pub fn isotropic_class(offsets: &[IVec3], pattern: u32) -> BTreeSet<u32> {
+    symmetry_maps(offsets)
+        .iter()
+        .map(|map| permute(map, pattern))
+        .collect()
+}
+
+/// Smallest pattern of an isotropic class, usable as the class identifier
+pub fn canonical_pattern(offsets: &[IVec3], pattern: u32) -> u32 {
+    symmetry_maps(offsets)
+        .iter()
+        .map(|map| permute(map, pattern))
+        .min()
+        .unwrap_or(pattern)
+}
+
+/// Canonical patterns of every isotropic class with exactly `alive` neighbors set
+/// Example: Von Neumann with 2 alive has 2 classes (opposite or adjacent faces), Moore has 17
+pub fn isotropic_classes(method: &NeighborMethod, alive: u32) -> Vec<u32> {
+    let offsets = pattern_offsets(method);
+    let maps = symmetry_maps(offsets);
+    let mut classes = BTreeSet::new();
+    for pattern in patterns_with_bits(offsets.len() as u32, alive) {
+        let canonical = maps.iter().map(|map| permute(map, pattern)).min().unwrap_or(pattern);
+        classes.insert(canonical);
+    }
+    classes.into_iter().collect()
+}
+
+/// All `width`-bit patterns with exactly `bits` set, in increasing order (Gosper's hack)
+fn patterns_with_bits(width: u32, bits: u32) -> impl Iterator<Item = u32> {
+    let limit = 1u64 << width;
+    let first = if bits > width { limit } else { (1u64 << bits) - 1 };
+    std::iter::successors(Some(first), move |&pattern| {
+        if pattern == 0 {
+            return None;
+        }
+        let lowest = pattern & pattern.wrapping_neg();
+        let ripple = pattern + lowest;
+        Some((((ripple ^ pattern) >> 2) / lowest) | ripple)
+    })
+    .take_while(move |&pattern| pattern < limit)
+    .map(|pattern| pattern as u32)
+}
+
+/// For each of the 48 cube symmetries that maps the neighborhood onto itself,
+/// the index each offset moves to
+fn symmetry_maps(offsets: &[IVec3]) -> Vec<Vec<usize>> {
+    const AXES: [[usize; 3]; 6] = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+    let sign = |flips: i32, axis: i32| if flips & (1 << axis) == 0 { 1 } else { -1 };
+
+    AXES.iter()
+        .flat_map(|axes| (0..8).map(move |flips| (axes, ivec3(sign(flips, 0), sign(flips, 1), sign(flips, 2)))))
+        .filter_map(|(axes, signs)| {
+            offsets
+                .iter()
+                .map(|offset| {
+                    let coords = offset.to_array();
+                    let moved = ivec3(coords[axes[0]], coords[axes[1]], coords[axes[2]]) * signs;
+                    offsets.iter().position(|&other| other == moved)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Move each set bit of `pattern` to the index given by `map`
+fn permute(map: &[usize], pattern: u32) -> u32 {
+    map.iter()
+        .enumerate()
+        .filter(|&(bit, _)| pattern & (1 << bit) != 0)
+        .fold(0, |moved, (_, &target)| moved | 1 << target)
+}
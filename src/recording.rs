@@ -0,0 +1,269 @@
+use bevy::prelude::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::grid::{decode_states, CellColors, Grid};
+use crate::rendering::InstanceMaterialData;
+use crate::rule::Rule;
+
+/// First bytes of a recording file, followed by the format version
+const RECORDING_MAGIC: [u8; 4] = *b"C3DR";
+
+/// Version 1 stored the rule's notation only, version 2 the whole rule (as RON, like grid snapshots)
+const RECORDING_VERSION: u8 = 2;
+
+/// Writes a run as it goes: its seed, rule and starting cells, then only the cells that changed each frame
+/// Far smaller than full frames, and played back exactly by `Replay` whatever the rule's randomness
+#[derive(Resource)]
+pub struct Recorder {
+    file: BufWriter<File>,
+    /// States of the last written frame in position order, to diff the next one against
+    previous: Vec<u8>,
+    generation: u64,
+}
+
+impl Recorder {
+    /// Start a recording of `grid` at its current generation
+    pub fn create(path: impl AsRef<Path>, grid: &Grid, rule: &Rule, seed: u64) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let rule = ron::to_string(rule).map_err(|e| invalid_data(&e.to_string()))?;
+        let start = grid.encode_states();
+        file.write_all(&RECORDING_MAGIC)?;
+        file.write_all(&[RECORDING_VERSION])?;
+        file.write_all(&(grid.size as u32).to_le_bytes())?;
+        file.write_all(&seed.to_le_bytes())?;
+        file.write_all(&(rule.len() as u32).to_le_bytes())?;
+        file.write_all(rule.as_bytes())?;
+        file.write_all(&grid.generation().to_le_bytes())?;
+        write_varint(&mut file, start.len() as u64)?;
+        file.write_all(&start)?;
+        file.flush()?;
+        Ok(Self { file, previous: grid.states(), generation: grid.generation() })
+    }
+
+    /// Append the cells that changed since the last frame, unless the grid is still on that generation
+    /// Each frame is the generation, the number of changes, then each as the gap to the previous changed
+    /// cell's position and its new state
+    pub fn write_frame(&mut self, grid: &Grid) -> io::Result<()> {
+        if grid.generation() == self.generation || grid.size.pow(3) as usize != self.previous.len() {
+            return Ok(());
+        }
+        let states = grid.states();
+        let changes: Vec<(usize, u8)> = states
+            .iter()
+            .zip(&self.previous)
+            .enumerate()
+            .filter(|(_, (now, before))| now != before)
+            .map(|(index, (&now, _))| (index, now))
+            .collect();
+
+        write_varint(&mut self.file, grid.generation())?;
+        write_varint(&mut self.file, changes.len() as u64)?;
+        let mut last = 0;
+        for (index, state) in changes {
+            write_varint(&mut self.file, (index - last) as u64)?;
+            self.file.write_all(&[state])?;
+            last = index;
+        }
+        self.file.flush()?;
+        self.previous = states;
+        self.generation = grid.generation();
+        Ok(())
+    }
+}
+
+/// A run written by `Recorder`, played back one recorded frame per update instead of simulating
+#[derive(Resource)]
+pub struct Replay {
+    /// Seed the run was recorded with
+    pub seed: u64,
+    /// Rule the run was recorded with (only as far as its notation goes in version 1 recordings)
+    pub rule: Rule,
+    size: i32,
+    generation: u64,
+    /// Starting cells in position order
+    start: Vec<u8>,
+    frames: Vec<ReplayFrame>,
+    /// Next frame to play
+    next: usize,
+    /// States of the frame on screen in position order
+    states: Vec<u8>,
+}
+
+struct ReplayFrame {
+    generation: u64,
+    changes: Vec<(usize, u8)>,
+}
+
+impl Replay {
+    /// Read a recording written by `Recorder`, rejecting it unless its header and starting cells are intact
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let mut reader = ByteReader { bytes: &bytes, offset: 0 };
+        if reader.take(RECORDING_MAGIC.len())? != RECORDING_MAGIC {
+            return Err(invalid_data("not a recording"));
+        }
+        let version = reader.take(1)?[0];
+        let size = u32::from_le_bytes(reader.array()?) as i32;
+        let seed = u64::from_le_bytes(reader.array()?);
+        let rule = match version {
+            1 => {
+                let len = u16::from_le_bytes(reader.array()?) as usize;
+                Rule::from_notation(reader.text(len)?).map_err(|e| invalid_data(&e.to_string()))?
+            }
+            RECORDING_VERSION => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                let rule: Rule = ron::from_str(reader.text(len)?).map_err(|e| invalid_data(&e.to_string()))?;
+                rule.validate().map_err(|e| invalid_data(&e.to_string()))?;
+                rule
+            }
+            _ => return Err(invalid_data("unsupported recording version")),
+        };
+        let generation = u64::from_le_bytes(reader.array()?);
+        if !(1..=1024).contains(&size) {
+            return Err(invalid_data("grid size out of range"));
+        }
+        let total = size.pow(3) as usize;
+        let start_len = reader.varint()? as usize;
+        let start = decode_states(reader.take(start_len)?, total)?;
+
+        // A run stopped mid-write ends in a partial frame, everything before it still plays
+        let mut frames = Vec::new();
+        while reader.offset < bytes.len() {
+            let Ok(frame) = reader.frame(total) else { break };
+            frames.push(frame);
+        }
+        Ok(Self { seed, rule, size, generation, start, frames, next: 0, states: Vec::new() })
+    }
+
+    /// Recorded frames after the starting cells
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Put the grid back at the recording's starting cells, resizing it to the recorded size
+    pub fn restart(&mut self, grid: &mut Grid) {
+        if grid.size != self.size {
+            grid.resize(self.size, false);
+        }
+        grid.set_states(&self.start, self.generation);
+        self.states = grid.states();
+        self.next = 0;
+    }
+
+    /// Show the next recorded frame in the grid, false once the recording is over
+    pub fn advance(&mut self, grid: &mut Grid) -> bool {
+        let Some(frame) = self.frames.get(self.next) else { return false };
+        // Nothing to apply the changes to before `restart`
+        if grid.size != self.size || self.states.is_empty() {
+            return false;
+        }
+        for &(index, state) in &frame.changes {
+            self.states[index] = state;
+        }
+        grid.set_states(&self.states, frame.generation);
+        self.next += 1;
+        true
+    }
+}
+
+/// Write the generation the simulation reached this frame to the recording
+pub fn record_run(mut recorder: ResMut<Recorder>, grid: Res<Grid>, mut commands: Commands) {
+    if let Err(e) = recorder.write_frame(&grid) {
+        eprintln!("Failed to write recording, stopping it: {}", e);
+        commands.remove_resource::<Recorder>();
+    }
+}
+
+/// Play the next recorded frame at the simulation's pace, neighbor counts aren't kept up to date
+#[allow(clippy::too_many_arguments)]
+pub fn play_replay(
+    mut replay: ResMut<Replay>,
+    mut grid: ResMut<Grid>,
+    colors: Res<CellColors>,
+    rule: Res<Rule>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+    time: Res<Time>,
+    mut last_update: Local<f32>,
+    mut finished: Local<bool>,
+) {
+    const UPDATE_INTERVAL: f32 = 0.05;
+
+    if time.elapsed_secs() - *last_update < UPDATE_INTERVAL {
+        return;
+    }
+    *last_update = time.elapsed_secs();
+
+    if !replay.advance(&mut grid) {
+        if !*finished {
+            println!("Replay finished at generation {}", grid.generation());
+            *finished = true;
+        }
+        return;
+    }
+    if let Ok(mut instances) = instance_query.single_mut() {
+        instances.0 = grid.build_instances(&colors, rule.states, rule.neighbor_method.max_neighbors());
+    }
+}
+
+fn write_varint(out: &mut impl Write, mut value: u64) -> io::Result<()> {
+    while value >= 0x80 {
+        out.write_all(&[value as u8 | 0x80])?;
+        value >>= 7;
+    }
+    out.write_all(&[value as u8])
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let taken = self.bytes.get(self.offset..self.offset + len).ok_or_else(|| invalid_data("truncated recording"))?;
+        self.offset += len;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn text(&mut self, len: usize) -> io::Result<&'a str> {
+        std::str::from_utf8(self.take(len)?).map_err(|e| invalid_data(&e.to_string()))
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_data("varint too long"))
+    }
+
+    /// Frame of a grid of `total` cells
+    fn frame(&mut self, total: usize) -> io::Result<ReplayFrame> {
+        let generation = self.varint()?;
+        let count = self.varint()? as usize;
+        let mut changes = Vec::with_capacity(count.min(total));
+        let mut index = 0;
+        for _ in 0..count {
+            index += self.varint()? as usize;
+            let state = self.take(1)?[0];
+            if index >= total {
+                return Err(invalid_data("cell index out of range"));
+            }
+            changes.push((index, state));
+        }
+        Ok(ReplayFrame { generation, changes })
+    }
+}
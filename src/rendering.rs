@@ -1,47 +1,103 @@
 use bevy::{
+    camera::primitives::Frustum,
     core_pipeline::core_3d::Transparent3d,
-    ecs::system::{lifetimeless::*, SystemParamItem},
-    pbr::{MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup, SetMeshViewBindingArrayBindGroup},
+    ecs::{
+        query::QueryState,
+        system::{lifetimeless::*, SystemChangeTick, SystemParamItem},
+    },
+    pbr::{
+        LightEntity, MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+        SetMeshViewBindingArrayBindGroup, Shadow, ShadowBatchSetKey, ShadowBinKey, ViewLightEntities,
+    },
     prelude::*,
     render::{
+        camera::ExtractedCamera,
         extract_component::{ExtractComponent, ExtractComponentPlugin},
+        extract_resource::ExtractResourcePlugin,
+        graph::CameraDriverLabel,
         mesh::{allocator::MeshAllocator, RenderMesh, RenderMeshBufferInfo},
         render_asset::RenderAssets,
+        render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
         render_phase::{
-            AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand, RenderCommandResult, SetItemPipeline,
-            TrackedRenderPass, ViewSortedRenderPhases,
+            AddRenderCommand, BinnedRenderPhaseType, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand,
+            RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewBinnedRenderPhases, ViewSortedRenderPhases,
+        },
+        render_resource::{
+            binding_types::{storage_buffer_read_only_sized, storage_buffer_sized, uniform_buffer},
+            *,
         },
-        render_resource::*,
-        renderer::RenderDevice,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
         sync_world::MainEntity,
-        view::ExtractedView,
+        view::{ExtractedView, ViewUniform, ViewUniformOffset, ViewUniforms},
         Render, RenderApp, RenderStartup, RenderSystems,
     },
 };
 use bevy_mesh::VertexBufferLayout;
 use bytemuck::{Pod, Zeroable};
 use std::mem::size_of;
+use crate::camera::FlyCamera;
+use crate::clipping::{ClipBox, ExplodedView, SlicePlane};
+use crate::culling::{CulledInstances, FrustumCulling};
+use crate::lod::LodInstances;
 
 // Instance data that will be sent to the GPU
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
 #[repr(C)]
 pub struct InstanceData {
     pub position: Vec3,
     pub scale: f32,
     pub color: [f32; 4],
+    // Emissive brightness on top of the color, which blooms on HDR cameras
+    pub glow: f32,
 }
 
 // Component that holds all instance data
 #[derive(Component, Deref)]
 pub struct InstanceMaterialData(pub Vec<InstanceData>);
 
+// Extracted only when the instances (or the visible chunks among them) change or the entity is shown or
+// hidden, except for translucent cells whose sorting doesn't count as a change
 impl ExtractComponent for InstanceMaterialData {
-    type QueryData = &'static InstanceMaterialData;
-    type QueryFilter = ();
+    type QueryData = (&'static InstanceMaterialData, Option<&'static CulledInstances>, &'static Visibility);
+    type QueryFilter =
+        Or<(Changed<InstanceMaterialData>, Changed<CulledInstances>, Changed<Visibility>, With<BlendAlpha>)>;
     type Out = Self;
 
-    fn extract_component(item: bevy::ecs::query::QueryItem<'_, '_, Self::QueryData>) -> Option<Self> {
-        Some(InstanceMaterialData(item.0.clone()))
+    fn extract_component(
+        (instances, culled, visibility): bevy::ecs::query::QueryItem<'_, '_, Self::QueryData>,
+    ) -> Option<Self> {
+        // Hidden cells (drawn by the LOD or a merged surface instead) aren't uploaded at all
+        if *visibility == Visibility::Hidden {
+            return None;
+        }
+        // Only the visible chunks are uploaded while frustum culling is on
+        Some(InstanceMaterialData(culled.map_or(&instances.0, |culled| &culled.0).clone()))
+    }
+}
+
+// Marker for instance entities whose colors blend by alpha
+// Translucent cells don't write depth, so `sort_translucent_instances` keeps them drawn back to front
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct BlendAlpha;
+
+// Marker for instance entities whose cube faces darken toward their edges, see `CellColors::outlines`
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct CellOutlines;
+
+// System that orders translucent instances from the farthest to the nearest to the camera every frame,
+// after anything that rebuilds or animates them, since the camera moves between generations
+pub fn sort_translucent_instances(
+    camera_query: Query<&GlobalTransform, With<FlyCamera>>,
+    mut instance_query: Query<&mut InstanceMaterialData, With<BlendAlpha>>,
+) {
+    let Some(camera) = camera_query.iter().next() else { return };
+    let eye = camera.translation();
+    for mut instances in &mut instance_query {
+        // Reordering doesn't count as new instances
+        instances
+            .bypass_change_detection()
+            .0
+            .sort_by(|a, b| b.position.distance_squared(eye).total_cmp(&a.position.distance_squared(eye)));
     }
 }
 
@@ -52,27 +108,308 @@ struct InstanceBuffer {
     length: usize,
 }
 
-// System that prepares instance buffers for rendering
+// System that keeps the instance buffers up to date for rendering. Instances are only extracted when they
+// change, and rewritten in place while they fit their buffer
 fn prepare_instance_buffers(
     mut commands: Commands,
-    query: Query<(Entity, &InstanceMaterialData)>,
+    mut query: Query<(Entity, Ref<InstanceMaterialData>, Option<&mut InstanceBuffer>)>,
     render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
 ) {
-    for (entity, instance_data) in &query {
-        // Skip creating empty buffers (when all cells are dead)
-        if instance_data.0.is_empty() {
+    for (entity, instance_data, instance_buffer) in &mut query {
+        if !instance_data.is_changed() {
             continue;
         }
+        let contents: &[u8] = bytemuck::cast_slice(instance_data.0.as_slice());
+        match instance_buffer {
+            Some(mut instance_buffer) if instance_buffer.buffer.size() >= contents.len() as u64 => {
+                if !contents.is_empty() {
+                    render_queue.write_buffer(&instance_buffer.buffer, 0, contents);
+                }
+                instance_buffer.length = instance_data.0.len();
+            }
+            // Skip creating empty buffers (when all cells are dead)
+            _ if contents.is_empty() => {}
+            _ => {
+                // Room to grow, so a growing population doesn't reallocate every generation
+                let buffer = render_device.create_buffer(&BufferDescriptor {
+                    label: Some("instance data buffer"),
+                    size: (contents.len() as u64).next_power_of_two(),
+                    usage: BufferUsages::VERTEX | BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                render_queue.write_buffer(&buffer, 0, contents);
+                commands.entity(entity).insert(InstanceBuffer {
+                    buffer,
+                    length: instance_data.0.len(),
+                });
+            }
+        }
+    }
+}
 
-        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            label: Some("instance data buffer"),
-            contents: bytemuck::cast_slice(instance_data.0.as_slice()),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
-        commands.entity(entity).insert(InstanceBuffer {
-            buffer,
-            length: instance_data.0.len(),
+// Most camera views the GPU culling pass tests, the fly camera and the quad view's three
+const MAX_CULLING_VIEWS: usize = 4;
+
+// Frustum planes of the active camera views for the GPU culling pass, see `FrustumCulling::gpu`
+#[derive(ShaderType, Clone, Default)]
+struct CellCullingUniform {
+    // 6 per view, xyz = inward normal, w = distance
+    planes: [Vec4; MAX_CULLING_VIEWS * 6],
+    // 0 keeps every instance
+    view_count: u32,
+}
+
+// Compute pipeline compacting the instances inside the camera views into another buffer
+#[derive(Resource)]
+struct CellCullingPipeline {
+    layout: BindGroupLayout,
+    pipeline: CachedComputePipelineId,
+}
+
+// Frustum planes rewritten every frame into the one buffer all culling bind groups read
+#[derive(Resource, Default)]
+struct CellCullingViews(UniformBuffer<CellCullingUniform>);
+
+fn init_cell_culling_pipeline(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    render_device: Res<RenderDevice>,
+    pipeline_cache: Res<PipelineCache>,
+) {
+    let layout = render_device.create_bind_group_layout(
+        "cell culling layout",
+        &BindGroupLayoutEntries::sequential(
+            ShaderStages::COMPUTE,
+            (
+                uniform_buffer::<CellCullingUniform>(false),
+                storage_buffer_read_only_sized(false, None),
+                storage_buffer_sized(false, None),
+                storage_buffer_sized(false, None),
+            ),
+        ),
+    );
+    let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+        label: Some("cell culling pipeline".into()),
+        layout: vec![layout.clone()],
+        shader: asset_server.load("shaders/instance_culling.wgsl"),
+        entry_point: Some("cull".into()),
+        ..default()
+    });
+    commands.insert_resource(CellCullingPipeline { layout, pipeline });
+    commands.init_resource::<CellCullingViews>();
+}
+
+// Instances kept by the GPU culling pass, drawn indirectly in place of the entity's whole `InstanceBuffer`.
+// The buffers live as long as the instance buffer they're sized for
+#[derive(Component)]
+struct CulledInstanceBuffer {
+    visible: Buffer,
+    // Indirect draw arguments, whose instance count the pass fills in
+    draw_args: Buffer,
+    // Arguments last written, only the instance count is reset while they stay the same
+    args: [u32; 5],
+    bind_group: BindGroup,
+    // Instances and views buffers the bind group reads, and how many instances it covers
+    instances: BufferId,
+    views: BufferId,
+    length: usize,
+}
+
+// Indirect draw arguments of an entity's mesh, with no instances yet
+fn cell_draw_args(
+    main_entity: MainEntity,
+    meshes: &RenderAssets<RenderMesh>,
+    render_mesh_instances: &RenderMeshInstances,
+    mesh_allocator: &MeshAllocator,
+) -> Option<[u32; 5]> {
+    let mesh_instance = render_mesh_instances.render_mesh_queue_data(main_entity)?;
+    let gpu_mesh = meshes.get(mesh_instance.mesh_asset_id)?;
+    let vertex_buffer_slice = mesh_allocator.mesh_vertex_slice(&mesh_instance.mesh_asset_id)?;
+    let first_vertex = vertex_buffer_slice.range.start;
+    Some(match &gpu_mesh.buffer_info {
+        RenderMeshBufferInfo::Indexed { count, .. } => {
+            let index_buffer_slice = mesh_allocator.mesh_index_slice(&mesh_instance.mesh_asset_id)?;
+            // index_count, instance_count, first_index, base_vertex, first_instance
+            [*count, 0, index_buffer_slice.range.start, first_vertex, 0]
+        }
+        // vertex_count, instance_count, first_vertex, first_instance, padded to the indexed size
+        RenderMeshBufferInfo::NonIndexed => [vertex_buffer_slice.range.end - first_vertex, 0, first_vertex, 0, 0],
+    })
+}
+
+// System that sets up the GPU culling pass of the opaque instances while it's on: the views' frustum planes
+// are written every frame and the instance counts reset, the rest only when the instances or mesh change
+#[allow(clippy::too_many_arguments)]
+fn prepare_cell_culling(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    culling_pipeline: Res<CellCullingPipeline>,
+    mut culling_views: ResMut<CellCullingViews>,
+    pipeline_cache: Res<PipelineCache>,
+    culling: Option<Res<FrustumCulling>>,
+    exploded: Option<Res<ExplodedView>>,
+    meshes: Res<RenderAssets<RenderMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    mesh_allocator: Res<MeshAllocator>,
+    mut instance_query: Query<(
+        Entity,
+        &MainEntity,
+        &InstanceBuffer,
+        Option<&mut CulledInstanceBuffer>,
+        Has<BlendAlpha>,
+        Has<InstanceMaterialData>,
+    )>,
+    views: Query<&ExtractedView, With<ExtractedCamera>>,
+) {
+    let enabled = culling.is_some_and(|culling| culling.enabled && culling.gpu);
+    // Until the pipeline compiles, the instances are drawn whole
+    let ready = pipeline_cache.get_compute_pipeline(culling_pipeline.pipeline).is_some();
+    if !(enabled && ready) {
+        for (entity, _, _, culled, _, _) in &instance_query {
+            if culled.is_some() {
+                commands.entity(entity).remove::<CulledInstanceBuffer>();
+            }
+        }
+        return;
+    }
+
+    // The exploded view moves cells away from where they're tested, and with more views than fit some
+    // would go untested, so both keep every instance
+    let mut uniform = CellCullingUniform::default();
+    if !exploded.is_some_and(|exploded| exploded.enabled) && views.iter().count() <= MAX_CULLING_VIEWS {
+        for (view, planes) in views.iter().zip(uniform.planes.chunks_mut(6)) {
+            let clip_from_world = view
+                .clip_from_world
+                .unwrap_or_else(|| view.clip_from_view * Mat4::from(view.world_from_view.affine().inverse()));
+            let frustum = Frustum::from_clip_from_world(&clip_from_world);
+            for (plane, half_space) in planes.iter_mut().zip(frustum.half_spaces) {
+                *plane = half_space.normal_d();
+            }
+            uniform.view_count += 1;
+        }
+    }
+    culling_views.0.set(uniform);
+    culling_views.0.write_buffer(&render_device, &render_queue);
+    let Some(views_buffer) = culling_views.0.buffer() else {
+        return;
+    };
+
+    let bind_group = |instance_buffer: &InstanceBuffer, visible: &Buffer, draw_args: &Buffer| {
+        render_device.create_bind_group(
+            "cell culling bind group",
+            &culling_pipeline.layout,
+            &BindGroupEntries::sequential((
+                views_buffer.as_entire_binding(),
+                // Only the live instances, the buffer has room to grow past them
+                BufferBinding {
+                    buffer: &instance_buffer.buffer,
+                    offset: 0,
+                    size: BufferSize::new((instance_buffer.length * size_of::<InstanceData>()) as u64),
+                },
+                visible.as_entire_binding(),
+                draw_args.as_entire_binding(),
+            )),
+        )
+    };
+    for (entity, main_entity, instance_buffer, culled, blend_alpha, shown) in &mut instance_query {
+        // Translucent cells stay with the CPU culling, see `FrustumCulling::gpu`, and hidden ones are left out
+        let args = (!blend_alpha && shown && instance_buffer.length > 0)
+            .then(|| cell_draw_args(*main_entity, &meshes, &render_mesh_instances, &mesh_allocator))
+            .flatten();
+        let Some(args) = args else {
+            if culled.is_some() {
+                commands.entity(entity).remove::<CulledInstanceBuffer>();
+            }
+            continue;
+        };
+
+        match culled {
+            Some(mut culled) if culled.visible.size() == instance_buffer.buffer.size() => {
+                if culled.args == args {
+                    render_queue.write_buffer(&culled.draw_args, 4, bytemuck::bytes_of(&0u32));
+                } else {
+                    render_queue.write_buffer(&culled.draw_args, 0, bytemuck::cast_slice(&args));
+                    culled.args = args;
+                }
+                let (instances, views) = (instance_buffer.buffer.id(), views_buffer.id());
+                if culled.instances != instances || culled.views != views || culled.length != instance_buffer.length {
+                    culled.bind_group = bind_group(instance_buffer, &culled.visible, &culled.draw_args);
+                    culled.instances = instances;
+                    culled.views = views;
+                    culled.length = instance_buffer.length;
+                }
+            }
+            // New, or the instance buffer grew
+            _ => {
+                let visible = render_device.create_buffer(&BufferDescriptor {
+                    label: Some("visible instance buffer"),
+                    size: instance_buffer.buffer.size(),
+                    usage: BufferUsages::STORAGE | BufferUsages::VERTEX,
+                    mapped_at_creation: false,
+                });
+                let draw_args = render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("cell draw args buffer"),
+                    contents: bytemuck::cast_slice(&args),
+                    usage: BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST,
+                });
+                commands.entity(entity).insert(CulledInstanceBuffer {
+                    bind_group: bind_group(instance_buffer, &visible, &draw_args),
+                    visible,
+                    draw_args,
+                    args,
+                    instances: instance_buffer.buffer.id(),
+                    views: views_buffer.id(),
+                    length: instance_buffer.length,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct CellCullingLabel;
+
+// Render graph node dispatching the culling pass of every entity prepared for it, before any camera draws
+struct CellCullingNode {
+    query: QueryState<&'static CulledInstanceBuffer>,
+}
+
+impl FromWorld for CellCullingNode {
+    fn from_world(world: &mut World) -> Self {
+        Self { query: QueryState::new(world) }
+    }
+}
+
+impl render_graph::Node for CellCullingNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let culling_pipeline = world.resource::<CellCullingPipeline>();
+        let Some(pipeline) = world.resource::<PipelineCache>().get_compute_pipeline(culling_pipeline.pipeline) else {
+            return Ok(());
+        };
+
+        let mut pass = render_context.command_encoder().begin_compute_pass(&ComputePassDescriptor {
+            label: Some("cell culling pass"),
+            timestamp_writes: None,
         });
+        pass.set_pipeline(pipeline);
+        for culled in self.query.iter_manual(world) {
+            pass.set_bind_group(0, &culled.bind_group, &[]);
+            // 64 instances per workgroup, spilling into rows past the per-dimension workgroup limit
+            let groups = (culled.length as u32).div_ceil(64);
+            pass.dispatch_workgroups(groups.min(65535), groups.div_ceil(65535), 1);
+        }
+        Ok(())
     }
 }
 
@@ -80,29 +417,48 @@ fn prepare_instance_buffers(
 #[derive(Resource)]
 struct CellPipeline {
     shader: Handle<Shader>,
+    // The clipping module both cell shaders import, kept loaded for them
+    #[allow(dead_code)]
+    clip_module: Handle<Shader>,
     mesh_pipeline: MeshPipeline,
+    clip_layout: BindGroupLayout,
 }
 
 fn init_cell_pipeline(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mesh_pipeline: Res<MeshPipeline>,
+    render_device: Res<RenderDevice>,
 ) {
+    let clip_layout = render_device.create_bind_group_layout(
+        "cell clip layout",
+        &BindGroupLayoutEntries::single(ShaderStages::VERTEX, uniform_buffer::<CellClipUniform>(false)),
+    );
     commands.insert_resource(CellPipeline {
         shader: asset_server.load("shaders/instancing.wgsl"),
+        clip_module: asset_server.load("shaders/cell_clip.wgsl"),
         mesh_pipeline: mesh_pipeline.clone(),
+        clip_layout,
     });
 }
 
+// Mesh pipeline key plus the cell shader's own options
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct CellPipelineKey {
+    mesh_key: MeshPipelineKey,
+    outlines: bool,
+}
+
 impl SpecializedMeshPipeline for CellPipeline {
-    type Key = MeshPipelineKey;
+    type Key = CellPipelineKey;
 
     fn specialize(
         &self,
         key: Self::Key,
         layout: &bevy_mesh::MeshVertexBufferLayoutRef,
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
-        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        let mut descriptor = self.mesh_pipeline.specialize(key.mesh_key, layout)?;
+        descriptor.layout.push(self.clip_layout.clone());
 
         descriptor.vertex.shader = self.shader.clone();
 
@@ -120,6 +476,12 @@ impl SpecializedMeshPipeline for CellPipeline {
                 offset: VertexFormat::Float32x4.size(),
                 shader_location: 4,
             },
+            // Glow
+            VertexAttribute {
+                format: VertexFormat::Float32,
+                offset: VertexFormat::Float32x4.size() * 2,
+                shader_location: 5,
+            },
         ];
 
         descriptor.vertex.buffers.push(VertexBufferLayout {
@@ -128,29 +490,214 @@ impl SpecializedMeshPipeline for CellPipeline {
             attributes: instance_attrs.to_vec(),
         });
 
-        descriptor.fragment.as_mut().unwrap().shader = self.shader.clone();
+        let fragment = descriptor.fragment.as_mut().unwrap();
+        fragment.shader = self.shader.clone();
+        if key.outlines {
+            fragment.shader_defs.push("CELL_OUTLINES".into());
+        }
 
         Ok(descriptor)
     }
 }
 
-// Custom draw command for instanced rendering
-struct DrawMeshInstanced;
+// Depth-only pipeline drawing the instances into the shadow maps of directional lights
+#[derive(Resource)]
+struct CellShadowPipeline {
+    shader: Handle<Shader>,
+    view_layout: BindGroupLayout,
+    clip_layout: BindGroupLayout,
+}
+
+fn init_cell_shadow_pipeline(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    render_device: Res<RenderDevice>,
+    cell_pipeline: Res<CellPipeline>,
+) {
+    let view_layout = render_device.create_bind_group_layout(
+        "cell shadow view layout",
+        &BindGroupLayoutEntries::single(ShaderStages::VERTEX, uniform_buffer::<ViewUniform>(true)),
+    );
+    commands.insert_resource(CellShadowPipeline {
+        shader: asset_server.load("shaders/instancing_shadow.wgsl"),
+        view_layout,
+        clip_layout: cell_pipeline.clip_layout.clone(),
+    });
+}
+
+impl SpecializedMeshPipeline for CellShadowPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &bevy_mesh::MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[Mesh::ATTRIBUTE_POSITION.at_shader_location(0)])?;
+
+        // Only the position + scale of the instance data matter for depth
+        let instance_layout = VertexBufferLayout {
+            array_stride: size_of::<InstanceData>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: vec![VertexAttribute {
+                format: VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 3,
+            }],
+        };
+
+        Ok(RenderPipelineDescriptor {
+            label: Some("cell shadow pipeline".into()),
+            layout: vec![self.view_layout.clone(), self.clip_layout.clone()],
+            vertex: VertexState {
+                shader: self.shader.clone(),
+                buffers: vec![vertex_layout, instance_layout],
+                ..default()
+            },
+            primitive: PrimitiveState {
+                topology: key.primitive_topology(),
+                ..default()
+            },
+            // Shadow maps use reversed depth like the main pass
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            ..default()
+        })
+    }
+}
+
+// View uniforms of every view, bound at the offset of the light view a shadow map is drawn from
+#[derive(Resource)]
+struct CellShadowViewBindGroup(BindGroup);
+
+fn prepare_cell_shadow_view_bind_group(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    shadow_pipeline: Res<CellShadowPipeline>,
+    view_uniforms: Res<ViewUniforms>,
+) {
+    let Some(view_binding) = view_uniforms.uniforms.binding() else {
+        return;
+    };
+    let bind_group = render_device.create_bind_group(
+        "cell shadow view bind group",
+        &shadow_pipeline.view_layout,
+        &BindGroupEntries::single(view_binding),
+    );
+    commands.insert_resource(CellShadowViewBindGroup(bind_group));
+}
+
+struct SetCellShadowViewBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetCellShadowViewBindGroup<I> {
+    type Param = Option<SRes<CellShadowViewBindGroup>>;
+    type ViewQuery = Read<ViewUniformOffset>;
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        view_uniform: &'w ViewUniformOffset,
+        _entity: Option<()>,
+        bind_group: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(bind_group) = bind_group else {
+            return RenderCommandResult::Skip;
+        };
+        pass.set_bind_group(I, &bind_group.into_inner().0, &[view_uniform.offset]);
+        RenderCommandResult::Success
+    }
+}
+
+// Clipping and exploding shared by the cell shaders, cutting cells away or moving them in the vertex stage
+#[derive(ShaderType, Clone, Default)]
+struct CellClipUniform {
+    // xyz = normal, w = offset of the slice plane, all zeros when off
+    plane: Vec4,
+    // Corners of the clip box, the w of `box_min` picking what it keeps, see `ClipBox::corners`
+    box_min: Vec4,
+    box_max: Vec4,
+    // x = gap, y = chunk size of the exploded view, all zeros when off
+    explode: Vec4,
+}
+
+#[derive(Resource)]
+struct CellClipBindGroup(BindGroup);
 
-impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
+fn prepare_cell_clip_bind_group(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    cell_pipeline: Res<CellPipeline>,
+    slice: Option<Res<SlicePlane>>,
+    clip_box: Option<Res<ClipBox>>,
+    exploded: Option<Res<ExplodedView>>,
+) {
+    let (box_min, box_max) = clip_box.map_or((Vec4::ZERO, Vec4::ZERO), |clip_box| clip_box.corners());
+    let mut buffer = UniformBuffer::from(CellClipUniform {
+        plane: slice.map_or(Vec4::ZERO, |slice| slice.plane()),
+        box_min,
+        box_max,
+        explode: exploded.map_or(Vec4::ZERO, |exploded| exploded.params()),
+    });
+    buffer.write_buffer(&render_device, &render_queue);
+    let Some(binding) = buffer.binding() else {
+        return;
+    };
+    let bind_group = render_device.create_bind_group(
+        "cell clip bind group",
+        &cell_pipeline.clip_layout,
+        &BindGroupEntries::single(binding),
+    );
+    commands.insert_resource(CellClipBindGroup(bind_group));
+}
+
+struct SetCellClipBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetCellClipBindGroup<I> {
+    type Param = Option<SRes<CellClipBindGroup>>;
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        _entity: Option<()>,
+        bind_group: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(bind_group) = bind_group else {
+            return RenderCommandResult::Skip;
+        };
+        pass.set_bind_group(I, &bind_group.into_inner().0, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+// Custom draw command for instanced rendering, drawing what the GPU culling pass kept with `CULLED`
+struct DrawMeshInstanced<const CULLED: bool>;
+
+impl<P: PhaseItem, const CULLED: bool> RenderCommand<P> for DrawMeshInstanced<CULLED> {
     type Param = (
         SRes<RenderAssets<RenderMesh>>,
         SRes<RenderMeshInstances>,
         SRes<MeshAllocator>,
     );
     type ViewQuery = ();
-    type ItemQuery = Read<InstanceBuffer>;
+    type ItemQuery = (Read<InstanceBuffer>, Option<Read<CulledInstanceBuffer>>);
 
     #[inline]
     fn render<'w>(
         item: &P,
         _view: (),
-        instance_buffer: Option<&'w InstanceBuffer>,
+        instance_buffers: Option<(&'w InstanceBuffer, Option<&'w CulledInstanceBuffer>)>,
         (meshes, render_mesh_instances, mesh_allocator): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
@@ -164,9 +711,10 @@ impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
         let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
             return RenderCommandResult::Skip;
         };
-        let Some(instance_buffer) = instance_buffer else {
+        let Some((instance_buffer, culled)) = instance_buffers else {
             return RenderCommandResult::Skip;
         };
+        let culled = culled.filter(|_| CULLED);
         let Some(vertex_buffer_slice) =
             mesh_allocator.mesh_vertex_slice(&mesh_instance.mesh_asset_id)
         else {
@@ -174,7 +722,11 @@ impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
         };
 
         pass.set_vertex_buffer(0, vertex_buffer_slice.buffer.slice(..));
-        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        // With GPU culling, the instances the culling pass kept and their count in the draw arguments
+        match culled {
+            Some(culled) => pass.set_vertex_buffer(1, culled.visible.slice(..)),
+            None => pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..)),
+        }
 
         match &gpu_mesh.buffer_info {
             RenderMeshBufferInfo::Indexed {
@@ -188,15 +740,19 @@ impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
                 };
 
                 pass.set_index_buffer(index_buffer_slice.buffer.slice(..), 0, *index_format);
-                pass.draw_indexed(
-                    index_buffer_slice.range.start..(index_buffer_slice.range.start + count),
-                    vertex_buffer_slice.range.start as i32,
-                    0..instance_buffer.length as u32,
-                );
-            }
-            RenderMeshBufferInfo::NonIndexed => {
-                pass.draw(vertex_buffer_slice.range, 0..instance_buffer.length as u32);
+                match culled {
+                    Some(culled) => pass.draw_indexed_indirect(&culled.draw_args, 0),
+                    None => pass.draw_indexed(
+                        index_buffer_slice.range.start..(index_buffer_slice.range.start + count),
+                        vertex_buffer_slice.range.start as i32,
+                        0..instance_buffer.length as u32,
+                    ),
+                }
             }
+            RenderMeshBufferInfo::NonIndexed => match culled {
+                Some(culled) => pass.draw_indirect(&culled.draw_args, 0),
+                None => pass.draw(vertex_buffer_slice.range, 0..instance_buffer.length as u32),
+            },
         }
         RenderCommandResult::Success
     }
@@ -207,10 +763,20 @@ type DrawCustom = (
     SetMeshViewBindGroup<0>,
     SetMeshViewBindingArrayBindGroup<1>,
     SetMeshBindGroup<2>,
-    DrawMeshInstanced,
+    SetCellClipBindGroup<3>,
+    DrawMeshInstanced<true>,
+);
+
+type DrawCellShadow = (
+    SetItemPipeline,
+    SetCellShadowViewBindGroup<0>,
+    SetCellClipBindGroup<1>,
+    // Cells outside the camera views still cast shadows into them
+    DrawMeshInstanced<false>,
 );
 
 // Queue system to add our entities to the render phase
+#[allow(clippy::too_many_arguments)]
 fn queue_custom(
     transparent_3d_draw_functions: Res<DrawFunctions<Transparent3d>>,
     custom_pipeline: Res<CellPipeline>,
@@ -218,7 +784,7 @@ fn queue_custom(
     pipeline_cache: Res<PipelineCache>,
     meshes: Res<RenderAssets<RenderMesh>>,
     render_mesh_instances: Res<RenderMeshInstances>,
-    material_meshes: Query<(Entity, &MainEntity), With<InstanceMaterialData>>,
+    material_meshes: Query<(Entity, &MainEntity, Has<BlendAlpha>, Has<CellOutlines>), With<InstanceMaterialData>>,
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
     views: Query<(&ExtractedView, &Msaa)>,
 ) {
@@ -234,7 +800,7 @@ fn queue_custom(
         let view_key = msaa_key | MeshPipelineKey::from_hdr(view.hdr);
         let rangefinder = view.rangefinder3d();
 
-        for (entity, main_entity) in &material_meshes {
+        for (entity, main_entity, blend_alpha, outlines) in &material_meshes {
             let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(*main_entity)
             else {
                 continue;
@@ -243,8 +809,12 @@ fn queue_custom(
                 continue;
             };
 
-            let key =
+            let mut key =
                 view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology());
+            if blend_alpha {
+                key |= MeshPipelineKey::BLEND_ALPHA;
+            }
+            let key = CellPipelineKey { mesh_key: key, outlines };
             let pipeline = pipelines
                 .specialize(&pipeline_cache, &custom_pipeline, key, &mesh.layout)
                 .unwrap();
@@ -261,22 +831,105 @@ fn queue_custom(
     }
 }
 
+// Queue system casting shadows from our entities in every light view, when a light has shadows enabled
+// Translucent cells cast like solid ones
+#[allow(clippy::too_many_arguments)]
+fn queue_cell_shadows(
+    shadow_draw_functions: Res<DrawFunctions<Shadow>>,
+    shadow_pipeline: Res<CellShadowPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<CellShadowPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<RenderMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    mesh_allocator: Res<MeshAllocator>,
+    material_meshes: Query<(Entity, &MainEntity), With<InstanceMaterialData>>,
+    mut shadow_render_phases: ResMut<ViewBinnedRenderPhases<Shadow>>,
+    views: Query<&ViewLightEntities, With<ExtractedView>>,
+    light_views: Query<&ExtractedView, With<LightEntity>>,
+    ticks: SystemChangeTick,
+) {
+    let draw_shadow = shadow_draw_functions.read().id::<DrawCellShadow>();
+
+    for view_lights in &views {
+        for light_view in light_views.iter_many(&view_lights.lights) {
+            let Some(shadow_phase) = shadow_render_phases.get_mut(&light_view.retained_view_entity) else {
+                continue;
+            };
+
+            for (entity, main_entity) in &material_meshes {
+                let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(*main_entity)
+                else {
+                    continue;
+                };
+                let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                    continue;
+                };
+
+                let key = MeshPipelineKey::from_primitive_topology(mesh.primitive_topology());
+                let pipeline = pipelines
+                    .specialize(&pipeline_cache, &shadow_pipeline, key, &mesh.layout)
+                    .unwrap();
+                let (vertex_slab, index_slab) = mesh_allocator.mesh_slabs(&mesh_instance.mesh_asset_id);
+                shadow_phase.add(
+                    ShadowBatchSetKey {
+                        pipeline,
+                        draw_function: draw_shadow,
+                        material_bind_group_index: None,
+                        vertex_slab: vertex_slab.unwrap_or_default(),
+                        index_slab,
+                    },
+                    ShadowBinKey { asset_id: mesh_instance.mesh_asset_id.into() },
+                    (entity, *main_entity),
+                    mesh_instance.current_uniform_index,
+                    // Each entity draws its own instance buffer
+                    BinnedRenderPhaseType::UnbatchableMesh,
+                    ticks.this_run(),
+                );
+            }
+        }
+    }
+}
+
 // Plugin that sets up our custom rendering pipeline
 pub struct CellMaterialPlugin;
 
 impl Plugin for CellMaterialPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(ExtractComponentPlugin::<InstanceMaterialData>::default());
+        app.add_plugins((
+            ExtractComponentPlugin::<InstanceMaterialData>::default(),
+            ExtractComponentPlugin::<LodInstances>::default(),
+            ExtractComponentPlugin::<BlendAlpha>::default(),
+            ExtractComponentPlugin::<CellOutlines>::default(),
+            ExtractResourcePlugin::<SlicePlane>::default(),
+            ExtractResourcePlugin::<ClipBox>::default(),
+            ExtractResourcePlugin::<ExplodedView>::default(),
+            ExtractResourcePlugin::<FrustumCulling>::default(),
+        ));
         app.sub_app_mut(RenderApp)
             .add_render_command::<Transparent3d, DrawCustom>()
+            .add_render_command::<Shadow, DrawCellShadow>()
             .init_resource::<SpecializedMeshPipelines<CellPipeline>>()
-            .add_systems(RenderStartup, init_cell_pipeline)
+            .init_resource::<SpecializedMeshPipelines<CellShadowPipeline>>()
+            .add_systems(
+                RenderStartup,
+                ((init_cell_pipeline, init_cell_shadow_pipeline).chain(), init_cell_culling_pipeline),
+            )
             .add_systems(
                 Render,
                 (
                     queue_custom.in_set(RenderSystems::QueueMeshes),
+                    queue_cell_shadows.in_set(RenderSystems::QueueMeshes),
                     prepare_instance_buffers.in_set(RenderSystems::PrepareResources),
+                    prepare_cell_shadow_view_bind_group.in_set(RenderSystems::PrepareBindGroups),
+                    prepare_cell_clip_bind_group.in_set(RenderSystems::PrepareBindGroups),
+                    prepare_cell_culling.in_set(RenderSystems::PrepareBindGroups),
                 ),
             );
+
+        let render_app = app.sub_app_mut(RenderApp);
+        let culling_node = CellCullingNode::from_world(render_app.world_mut());
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        render_graph.add_node(CellCullingLabel, culling_node);
+        render_graph.add_node_edge(CellCullingLabel, CameraDriverLabel);
     }
 }
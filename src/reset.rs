@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::cycles::{Behavior, BehaviorChanged, CycleDetector};
+use crate::cyclic::CyclicRule;
+use crate::grid::{max_neighbors, max_state, rebuild_neighbors, CellColors, Grid, SimRng};
+use crate::history::History;
+use crate::rendering::InstanceMaterialData;
+use crate::rule::Rule;
+use crate::seeding::SeedPattern;
+use crate::species::Ecosystem;
+use crate::zones::ZonedRules;
+
+/// Start the run over when it dies out (or settles down), so it can play unattended as an ambient display
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoReset {
+    /// Also start over when the cells stop changing
+    pub on_still_life: bool,
+    /// Also start over when the cells settle into an oscillator
+    pub on_oscillator: bool,
+    /// Start from a new random seed each time instead of replaying the first run
+    pub new_seed: bool,
+    /// Seconds the end state stays on screen before starting over
+    pub delay: f32,
+    /// Seed of the first run
+    #[serde(skip)]
+    seed: u64,
+    /// Pattern the grid was seeded with, None when seeded from an image, model or mesh
+    #[serde(skip)]
+    pattern: Option<SeedPattern>,
+    /// Starting cells (see `Grid::encode_states`), restored when there's no pattern to seed again
+    #[serde(skip)]
+    start: Vec<u8>,
+    /// Elapsed time at which to start over
+    #[serde(skip)]
+    due: Option<f32>,
+}
+
+impl Default for AutoReset {
+    fn default() -> Self {
+        Self {
+            on_still_life: true,
+            on_oscillator: false,
+            new_seed: true,
+            delay: 2.0,
+            seed: 0,
+            pattern: None,
+            start: Vec::new(),
+            due: None,
+        }
+    }
+}
+
+impl AutoReset {
+    /// Remember how the run started: the seed, the pattern seeding it (if any) and the grid's cells
+    pub fn remember(&mut self, grid: &Grid, seed: u64, pattern: Option<SeedPattern>) {
+        self.seed = seed;
+        self.pattern = pattern;
+        self.remember_cells(grid);
+    }
+
+    /// Remember the grid's cells as the start, e.g. once a mesh seed is in
+    pub fn remember_cells(&mut self, grid: &Grid) {
+        self.start = grid.encode_states();
+    }
+
+    /// Whether the run behaving as `behavior` should start over
+    pub fn triggers(&self, behavior: Behavior) -> bool {
+        match behavior {
+            Behavior::Extinct => true,
+            Behavior::StillLife => self.on_still_life,
+            Behavior::Oscillator { .. } => self.on_oscillator,
+            Behavior::Chaotic => false,
+        }
+    }
+
+    /// Empty the grid and seed it again at generation 0 the way the run started, with a new seed or the
+    /// first one. Species and cyclic grids start from clusters or noise as at startup. Neighbor caches have
+    /// to be rebuilt; returns the seed used
+    pub fn reseed(&self, grid: &mut Grid, rules: &[Rule], ecosystem: Option<&Ecosystem>, cyclic: Option<&CyclicRule>, rng: &mut SimRng) -> u64 {
+        let seed = if self.new_seed { rand::random() } else { self.seed };
+        *rng = SimRng::new(seed);
+        grid.set_states(&[], 0);
+        grid.clear_activity();
+        match (cyclic, ecosystem, &self.pattern) {
+            (Some(cyclic), _, _) => grid.fill_random_states(cyclic.states, &mut rng.0),
+            (None, Some(ecosystem), _) => grid.spawn_species_clusters(ecosystem, 6, 12 * 12 * 12, &mut rng.0),
+            (None, None, Some(pattern)) => grid.seed_zoned(pattern, rules, &mut rng.0),
+            (None, None, None) => {
+                if let Err(e) = grid.restore_states(&self.start, 0) {
+                    eprintln!("Failed to restore the starting cells: {}", e);
+                }
+            }
+        }
+        seed
+    }
+}
+
+/// Start the run over a little while after it dies out or settles the way `AutoReset` asks for
+#[allow(clippy::too_many_arguments)]
+pub fn auto_reset(
+    mut behavior_changes: MessageReader<BehaviorChanged>,
+    mut reset: ResMut<AutoReset>,
+    mut grid: ResMut<Grid>,
+    mut rng: ResMut<SimRng>,
+    rule: Res<Rule>,
+    colors: Res<CellColors>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+    mut cycles: Option<ResMut<CycleDetector>>,
+    mut history: Option<ResMut<History>>,
+    mut instance_query: Query<&mut InstanceMaterialData>,
+    time: Res<Time>,
+) {
+    for change in behavior_changes.read() {
+        // A run that gets going again (e.g. by painting) is left alone
+        reset.due = reset.triggers(change.behavior).then_some(time.elapsed_secs() + reset.delay);
+        if reset.due.is_some() {
+            println!("Generation {}: starting over in {:.0} s", change.generation, reset.delay);
+        }
+    }
+    if reset.due.is_none_or(|due| time.elapsed_secs() < due) {
+        return;
+    }
+    reset.due = None;
+
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let seed = reset.reseed(&mut grid, rules, ecosystem.as_deref(), cyclic.as_deref(), &mut rng);
+    rebuild_neighbors(&mut grid, &rule, ecosystem.as_deref(), cyclic.as_deref(), zones.as_deref());
+    if let Some(cycles) = cycles.as_deref_mut() {
+        cycles.clear();
+    }
+    // Older generations belong to the previous run
+    if let Some(history) = history.as_deref_mut() {
+        history.clear();
+        history.record(&grid);
+    }
+    if let Ok(mut instances) = instance_query.single_mut() {
+        let max_state = max_state(rules, ecosystem.as_deref(), cyclic.as_deref());
+        let max_neighbors = max_neighbors(rules, ecosystem.as_deref(), cyclic.as_deref());
+        instances.0 = grid.build_instances(&colors, max_state, max_neighbors);
+    }
+    println!("Started over with seed {} ({} living cells)", seed, grid.cell_count());
+}
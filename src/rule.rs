@@ -1,25 +1,278 @@
 use bevy::prelude::Resource;
 use bevy::math::{IVec3, ivec3};
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use crate::catalog::RuleRegistry;
+use crate::pattern::PatternRule;
 
 /// Neighbor counting method
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum NeighborMethod {
-    Moore,      // 26 neighbors (3x3x3 cube minus center)
-    VonNeumann, // 6 neighbors (face-adjacent only)
+    Moore,        // 26 neighbors (3x3x3 cube minus center)
+    VonNeumann,   // 6 neighbors (face-adjacent only)
+    MooreR2,      // 124 neighbors (5x5x5 cube minus center)
+    MooreR3,      // 342 neighbors (7x7x7 cube minus center)
+    VonNeumannR2, // 12 neighbors (radius-2 cross along the axes)
+    Corners,      // 8 neighbors (diagonal corners of the 3x3x3 cube only)
+    Knight,       // 24 neighbors (3D knight moves: 2 steps on one axis, 1 on another)
+    /// User-defined offsets (up to MAX_NEIGHBORS), e.g. crosses, hollow shells or asymmetric shapes
+    Custom(#[serde(with = "offset_list")] Vec<IVec3>),
+    /// Offsets that each add their weight to the neighbor sum (Larger-than-Life style kernels)
+    Weighted(WeightedKernel),
 }
 
 impl NeighborMethod {
-    pub fn get_neighbors(&self) -> &'static [IVec3] {
+    pub fn get_neighbors(&self) -> &[IVec3] {
         match self {
             NeighborMethod::Moore => &MOORE_NEIGHBORS,
             NeighborMethod::VonNeumann => &VON_NEUMANN_NEIGHBORS,
+            NeighborMethod::MooreR2 => &MOORE_R2_NEIGHBORS,
+            NeighborMethod::MooreR3 => &MOORE_R3_NEIGHBORS,
+            NeighborMethod::VonNeumannR2 => &VON_NEUMANN_R2_NEIGHBORS,
+            NeighborMethod::Corners => &CORNER_NEIGHBORS,
+            NeighborMethod::Knight => &KNIGHT_NEIGHBORS,
+            NeighborMethod::Custom(offsets) => offsets,
+            NeighborMethod::Weighted(kernel) => kernel.offsets(),
+        }
+    }
+
+    /// Per-offset weights, None when every neighbor counts as 1
+    #[inline]
+    pub fn weights(&self) -> Option<&[u16]> {
+        match self {
+            NeighborMethod::Weighted(kernel) => Some(kernel.weights()),
+            _ => None,
         }
     }
 
-    pub fn max_neighbors(&self) -> u8 {
+    /// Largest neighbor count (or weighted sum) this neighborhood can produce
+    pub fn max_neighbors(&self) -> u16 {
         match self {
-            NeighborMethod::Moore => 26,
-            NeighborMethod::VonNeumann => 6,
+            NeighborMethod::Weighted(kernel) => kernel.total_weight().min(u16::MAX as u32) as u16,
+            _ => self.get_neighbors().len() as u16,
+        }
+    }
+
+    /// Furthest any offset reaches along a single axis (1 for Moore, 2 for MooreR2)
+    pub fn reach(&self) -> i32 {
+        self.get_neighbors().iter().map(|offset| offset.abs().max_element()).max().unwrap_or(0)
+    }
+
+    /// Custom neighborhood of every offset within `radius` (cube) accepted by `include`
+    /// Example: hollow shell `from_predicate(2, |o| o.abs().max_element() == 2)`
+    pub fn from_predicate(radius: i32, include: impl Fn(IVec3) -> bool) -> Self {
+        let offsets = moore_offsets(radius).into_iter().filter(|&offset| include(offset)).collect();
+        NeighborMethod::Custom(offsets)
+    }
+
+    /// Parse custom offsets written as "C[1,0,0;-1,0,0;0,2,0]"
+    fn parse_custom(s: &str) -> Result<Self, RuleParseError> {
+        let offsets: Vec<IVec3> = parse_offset_rows::<3>(s)?
+            .into_iter()
+            .map(|[x, y, z]| ivec3(x, y, z))
+            .collect();
+
+        if offsets.is_empty() {
+            return Err(RuleParseError::EmptyNeighborhood);
+        }
+        if offsets.len() > MAX_NEIGHBORS as usize {
+            return Err(RuleParseError::TooManyOffsets(offsets.len()));
+        }
+        Ok(NeighborMethod::Custom(offsets))
+    }
+
+    /// Parse weighted offsets written as "W[1,0,0,2;-1,0,0,2;0,3,0,1]" (x,y,z,weight)
+    fn parse_weighted(s: &str) -> Result<Self, RuleParseError> {
+        let mut entries = Vec::new();
+        for [x, y, z, weight] in parse_offset_rows::<4>(s)? {
+            let weight = u16::try_from(weight)
+                .map_err(|_| RuleParseError::InvalidOffset(format!("{},{},{},{}", x, y, z, weight)))?;
+            entries.push((ivec3(x, y, z), weight));
+        }
+
+        // Zero weights are dropped, so a kernel of only zero weights has no offsets left
+        let kernel = WeightedKernel::new(entries);
+        if kernel.offsets().is_empty() {
+            return Err(RuleParseError::EmptyNeighborhood);
+        }
+        if kernel.offsets().len() > MAX_NEIGHBORS as usize {
+            return Err(RuleParseError::TooManyOffsets(kernel.offsets().len()));
+        }
+        if kernel.total_weight() > MAX_NEIGHBORS as u32 {
+            return Err(RuleParseError::KernelTooHeavy(kernel.total_weight()));
+        }
+        Ok(NeighborMethod::Weighted(kernel))
+    }
+}
+
+/// Parse the bracketed rows of an offset neighborhood ("C[1,0,0;-1,0,0]") into rows of N numbers
+fn parse_offset_rows<const N: usize>(s: &str) -> Result<Vec<[i32; N]>, RuleParseError> {
+    let body = s
+        .get(1..)
+        .and_then(|rest| rest.trim().strip_prefix('['))
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| RuleParseError::UnknownMethod(s.to_string()))?;
+
+    body.split(';')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let invalid = || RuleParseError::InvalidOffset(token.to_string());
+            let values: Vec<i32> = token
+                .split(',')
+                .map(|c| c.trim().parse::<i32>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid())?;
+            values.try_into().map_err(|_| invalid())
+        })
+        .collect()
+}
+
+/// Neighborhood where each offset contributes an integer weight to the neighbor sum
+/// Survival/birth counts are then matched against the weighted sum, which must stay within MAX_NEIGHBORS
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(from = "Vec<[i32; 4]>", into = "Vec<[i32; 4]>")]
+pub struct WeightedKernel {
+    offsets: Vec<IVec3>,
+    weights: Vec<u16>,
+}
+
+impl WeightedKernel {
+    /// Build from (offset, weight) pairs, dropping zero weights
+    pub fn new(entries: impl IntoIterator<Item = (IVec3, u16)>) -> Self {
+        let (offsets, weights) = entries.into_iter().filter(|&(_, weight)| weight > 0).unzip();
+        Self { offsets, weights }
+    }
+
+    /// Weight every offset within `radius` (cube) with `weight`, e.g. a falloff by distance
+    pub fn from_fn(radius: i32, weight: impl Fn(IVec3) -> u16) -> Self {
+        Self::new(moore_offsets(radius).into_iter().map(|offset| (offset, weight(offset))))
+    }
+
+    pub fn offsets(&self) -> &[IVec3] {
+        &self.offsets
+    }
+
+    pub fn weights(&self) -> &[u16] {
+        &self.weights
+    }
+
+    /// Sum of all weights, the largest neighbor sum the kernel can produce
+    pub fn total_weight(&self) -> u32 {
+        self.weights.iter().map(|&weight| weight as u32).sum()
+    }
+}
+
+impl From<Vec<[i32; 4]>> for WeightedKernel {
+    fn from(rows: Vec<[i32; 4]>) -> Self {
+        Self::new(rows.into_iter().map(|[x, y, z, w]| (ivec3(x, y, z), w.clamp(0, u16::MAX as i32) as u16)))
+    }
+}
+
+impl From<WeightedKernel> for Vec<[i32; 4]> {
+    fn from(kernel: WeightedKernel) -> Self {
+        kernel
+            .offsets
+            .iter()
+            .zip(&kernel.weights)
+            .map(|(offset, &weight)| [offset.x, offset.y, offset.z, weight as i32])
+            .collect()
+    }
+}
+
+/// Serde helper storing offsets as [x, y, z] arrays
+mod offset_list {
+    use bevy::math::IVec3;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(offsets: &[IVec3], serializer: S) -> Result<S::Ok, S::Error> {
+        let arrays: Vec<[i32; 3]> = offsets.iter().map(|offset| offset.to_array()).collect();
+        arrays.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<IVec3>, D::Error> {
+        let arrays = Vec::<[i32; 3]>::deserialize(deserializer)?;
+        Ok(arrays.into_iter().map(IVec3::from_array).collect())
+    }
+}
+
+/// Serde helper storing per-count chances with string keys ({ "6" = 0.4 }), as TOML requires
+mod chance_map {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(chances: &BTreeMap<u16, f32>, serializer: S) -> Result<S::Ok, S::Error> {
+        let keyed: BTreeMap<String, f32> = chances.iter().map(|(count, &chance)| (count.to_string(), chance)).collect();
+        keyed.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<u16, f32>, D::Error> {
+        BTreeMap::<String, f32>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(count, chance)| {
+                let parsed = count.trim().parse::<u16>().map_err(|_| {
+                    serde::de::Error::custom(format!("invalid neighbor count '{}' in chances", count))
+                })?;
+                Ok((parsed, chance.clamp(0.0, 1.0)))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for NeighborMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NeighborMethod::Moore => "M",
+            NeighborMethod::VonNeumann => "VN",
+            NeighborMethod::MooreR2 => "M2",
+            NeighborMethod::MooreR3 => "M3",
+            NeighborMethod::VonNeumannR2 => "VN2",
+            NeighborMethod::Corners => "D",
+            NeighborMethod::Knight => "K",
+            NeighborMethod::Custom(offsets) => {
+                let coords: Vec<String> = offsets
+                    .iter()
+                    .map(|o| format!("{},{},{}", o.x, o.y, o.z))
+                    .collect();
+                return write!(f, "C[{}]", coords.join(";"));
+            }
+            NeighborMethod::Weighted(kernel) => {
+                let rows: Vec<String> = kernel
+                    .offsets()
+                    .iter()
+                    .zip(kernel.weights())
+                    .map(|(o, weight)| format!("{},{},{},{}", o.x, o.y, o.z, weight))
+                    .collect();
+                return write!(f, "W[{}]", rows.join(";"));
+            }
+        })
+    }
+}
+
+impl FromStr for NeighborMethod {
+    type Err = RuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with(['C', 'c']) {
+            return Self::parse_custom(s);
+        }
+        if s.starts_with(['W', 'w']) {
+            return Self::parse_weighted(s);
+        }
+        match s.to_ascii_uppercase().as_str() {
+            "M" => Ok(NeighborMethod::Moore),
+            "VN" | "N" => Ok(NeighborMethod::VonNeumann),
+            "M2" => Ok(NeighborMethod::MooreR2),
+            "M3" => Ok(NeighborMethod::MooreR3),
+            "VN2" | "N2" => Ok(NeighborMethod::VonNeumannR2),
+            "D" => Ok(NeighborMethod::Corners),
+            "K" => Ok(NeighborMethod::Knight),
+            _ => Err(RuleParseError::UnknownMethod(s.to_string())),
         }
     }
 }
@@ -34,6 +287,46 @@ pub static VON_NEUMANN_NEIGHBORS: [IVec3; 6] = [
     ivec3( 0,  0, -1),
 ];
 
+/// Extended Von Neumann neighborhood: 12 cells, 1 and 2 steps along each axis
+pub static VON_NEUMANN_R2_NEIGHBORS: [IVec3; 12] = [
+    ivec3( 1,  0,  0),
+    ivec3(-1,  0,  0),
+    ivec3( 2,  0,  0),
+    ivec3(-2,  0,  0),
+    ivec3( 0,  1,  0),
+    ivec3( 0, -1,  0),
+    ivec3( 0,  2,  0),
+    ivec3( 0, -2,  0),
+    ivec3( 0,  0,  1),
+    ivec3( 0,  0, -1),
+    ivec3( 0,  0,  2),
+    ivec3( 0,  0, -2),
+];
+
+/// Corner neighborhood: the 8 diagonal corners of the 3x3x3 cube
+pub static CORNER_NEIGHBORS: [IVec3; 8] = [
+    ivec3(-1, -1, -1),
+    ivec3( 1, -1, -1),
+    ivec3(-1,  1, -1),
+    ivec3( 1,  1, -1),
+    ivec3(-1, -1,  1),
+    ivec3( 1, -1,  1),
+    ivec3(-1,  1,  1),
+    ivec3( 1,  1,  1),
+];
+
+/// Knight neighborhood: 24 cells reached by a chess knight move in any axis plane
+pub static KNIGHT_NEIGHBORS: LazyLock<Vec<IVec3>> = LazyLock::new(|| {
+    moore_offsets(2)
+        .into_iter()
+        .filter(|offset| {
+            let mut steps = offset.abs().to_array();
+            steps.sort_unstable();
+            steps == [0, 1, 2]
+        })
+        .collect()
+});
+
 /// Moore neighborhood: 26 surrounding cells (3x3x3 minus center)
 pub static MOORE_NEIGHBORS: [IVec3; 26] = [
     // Bottom layer (z = -1)
@@ -68,58 +361,225 @@ pub static MOORE_NEIGHBORS: [IVec3; 26] = [
     ivec3( 1,  1,  1),
 ];
 
+/// Largest neighbor count any built-in neighborhood can produce (radius-3 Moore: 7x7x7 - 1)
+pub const MAX_NEIGHBORS: u16 = 342;
+
+/// Mutants `Rule::mutate` draws before giving up on finding a valid one
+const MUTATION_ATTEMPTS: usize = 16;
+
+/// Number of u64 words needed to hold a bit for every count 0..=MAX_NEIGHBORS
+const RULE_WORDS: usize = (MAX_NEIGHBORS as usize + 1).div_ceil(64);
+
+/// Radius-2 Moore neighborhood: 124 cells (5x5x5 minus center)
+pub static MOORE_R2_NEIGHBORS: LazyLock<Vec<IVec3>> = LazyLock::new(|| moore_offsets(2));
+
+/// Radius-3 Moore neighborhood: 342 cells (7x7x7 minus center)
+pub static MOORE_R3_NEIGHBORS: LazyLock<Vec<IVec3>> = LazyLock::new(|| moore_offsets(3));
+
+/// All offsets in a (2r+1)^3 cube except the center, in the same z/y/x order as MOORE_NEIGHBORS
+fn moore_offsets(radius: i32) -> Vec<IVec3> {
+    let mut offsets = Vec::new();
+    for z in -radius..=radius {
+        for y in -radius..=radius {
+            for x in -radius..=radius {
+                if (x, y, z) != (0, 0, 0) {
+                    offsets.push(ivec3(x, y, z));
+                }
+            }
+        }
+    }
+    offsets
+}
+
 /// Rule value - efficient lookup table for neighbor counts using bit manipulation
-/// Uses a u32 as a bitmask where bit N represents whether neighbor count N matches
-/// This is more cache-friendly than a 27-element bool array (4 bytes vs 27 bytes)
+/// Uses a small bitset where bit N represents whether neighbor count N matches
+/// 48 bytes covers every count up to radius-3 Moore while keeping the check a single bit test
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct RuleValue {
-    // Bitmask where bit N is set if neighbor count N matches the rule
-    // Bits 0-26 are used (27 bits total for Moore neighborhood, 0-6 for Von Neumann)
-    bitmask: u32,
+    // Bit N (word N / 64, bit N % 64) is set if neighbor count N matches the rule
+    bits: [u64; RULE_WORDS],
 }
 
 impl RuleValue {
+    const EMPTY: Self = Self { bits: [0; RULE_WORDS] };
+
     /// Create a rule value from specific neighbor counts
-    pub fn new(counts: &[u8]) -> Self {
-        let mut bitmask = 0u32;
+    pub fn new(counts: &[u16]) -> Self {
+        let mut value = Self::EMPTY;
         for &count in counts {
-            if count < 27 {
-                bitmask |= 1 << count;
-            }
+            value.set(count);
         }
-        Self { bitmask }
+        value
     }
 
     /// Create a rule value from a range of neighbor counts
-    pub fn from_range(min: u8, max: u8) -> Self {
-        let mut bitmask = 0u32;
-        for count in min..=max.min(26) {
-            bitmask |= 1 << count;
+    pub fn from_range(min: u16, max: u16) -> Self {
+        let mut value = Self::EMPTY;
+        for count in min..=max.min(MAX_NEIGHBORS) {
+            value.set(count);
+        }
+        value
+    }
+
+    #[inline]
+    fn set(&mut self, count: u16) {
+        if count <= MAX_NEIGHBORS {
+            self.bits[count as usize / 64] |= 1 << (count % 64);
         }
-        Self { bitmask }
     }
 
     /// Combine multiple rule values with OR (union of conditions)
     /// Example: "5-10, 12, 14" = from_range(5,10).or(new(&[12, 14]))
-    pub fn or(self, other: Self) -> Self {
-        Self {
-            bitmask: self.bitmask | other.bitmask,
+    pub fn or(mut self, other: Self) -> Self {
+        for (word, other_word) in self.bits.iter_mut().zip(other.bits) {
+            *word |= other_word;
+        }
+        self
+    }
+
+    /// Flip whether a neighbor count matches
+    pub fn toggle(mut self, count: u16) -> Self {
+        if count <= MAX_NEIGHBORS {
+            self.bits[count as usize / 64] ^= 1 << (count % 64);
+        }
+        self
+    }
+
+    /// True if no neighbor count matches
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&word| word == 0)
+    }
+
+    /// Counts in 0..=max that don't match
+    pub fn complement(&self, max: u16) -> Self {
+        let mut value = Self::EMPTY;
+        for count in (0..=max.min(MAX_NEIGHBORS)).filter(|&count| !self.matches(count)) {
+            value.set(count);
+        }
+        value
+    }
+
+    /// Mirror counts around the neighborhood size: N matches when max - N matched (counts above max are dropped)
+    pub fn mirrored(&self, max: u16) -> Self {
+        let mut value = Self::EMPTY;
+        for count in (0..=max.min(MAX_NEIGHBORS)).filter(|&count| self.matches(count)) {
+            value.set(max - count);
+        }
+        value
+    }
+
+    /// Move every count by `offset`: N matches when N - offset matched (counts leaving 0..=max are dropped)
+    pub fn shifted(&self, offset: i32, max: u16) -> Self {
+        let mut value = Self::EMPTY;
+        for count in (0..=max.min(MAX_NEIGHBORS)).filter(|&count| self.matches(count)) {
+            if let Ok(shifted) = u16::try_from(count as i32 + offset) {
+                if shifted <= max {
+                    value.set(shifted);
+                }
+            }
         }
+        value
+    }
+
+    /// Highest matching neighbor count, None if no count matches
+    pub fn max_count(&self) -> Option<u16> {
+        (0..=MAX_NEIGHBORS).rev().find(|&count| self.matches(count))
+    }
+
+    /// Parse a comma separated list of counts and ranges, e.g. "4-7,12"
+    /// `part` names the notation section for error messages, `max` is the largest allowed count
+    fn parse(part: &'static str, spec: &str, max: u16) -> Result<Self, RuleParseError> {
+        let mut value = Self::EMPTY;
+        for token in spec.split(',').map(str::trim) {
+            if token.is_empty() {
+                // Allow empty sections ("/2/3/M") and trailing commas
+                continue;
+            }
+
+            let parse_count = |text: &str| -> Result<u16, RuleParseError> {
+                let count = text.trim().parse::<u16>().map_err(|_| RuleParseError::InvalidCount {
+                    part,
+                    token: token.to_string(),
+                })?;
+                if count > max {
+                    return Err(RuleParseError::CountOutOfRange { part, count, max });
+                }
+                Ok(count)
+            };
+
+            value = match token.split_once('-') {
+                Some((min, max_text)) => {
+                    let (min, max_count) = (parse_count(min)?, parse_count(max_text)?);
+                    if min > max_count {
+                        return Err(RuleParseError::InvalidRange { part, token: token.to_string() });
+                    }
+                    value.or(Self::from_range(min, max_count))
+                }
+                None => value.or(Self::new(&[parse_count(token)?])),
+            };
+        }
+        Ok(value)
     }
 
     /// Check if a neighbor count matches this rule
     /// This is a single bit check - extremely fast!
     #[inline]
-    pub fn matches(&self, count: u8) -> bool {
-        if count >= 27 {
+    pub fn matches(&self, count: u16) -> bool {
+        if count > MAX_NEIGHBORS {
             return false;
         }
-        (self.bitmask & (1 << count)) != 0
+        (self.bits[count as usize / 64] & (1 << (count % 64))) != 0
+    }
+}
+
+impl fmt::Display for RuleValue {
+    /// Formats as a comma separated list, collapsing consecutive counts into ranges ("4-7,12")
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut count = 0u16;
+        while count <= MAX_NEIGHBORS {
+            if !self.matches(count) {
+                count += 1;
+                continue;
+            }
+
+            let start = count;
+            while count < MAX_NEIGHBORS && self.matches(count + 1) {
+                count += 1;
+            }
+
+            if !first {
+                f.write_str(",")?;
+            }
+            first = false;
+
+            if start == count {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, count)?;
+            }
+            count += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Stored as its notation string ("4-7,12") so rule files stay readable
+impl Serialize for RuleValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RuleValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spec = String::deserialize(deserializer)?;
+        RuleValue::parse("neighbor", &spec, MAX_NEIGHBORS).map_err(serde::de::Error::custom)
     }
 }
 
 /// Cellular automata rule definition
-#[derive(Clone, PartialEq, Debug, Resource)]
+#[derive(Clone, PartialEq, Debug, Resource, Serialize, Deserialize)]
 pub struct Rule {
     /// Which neighbor counts keep a cell alive at max_state
     pub survival: RuleValue,
@@ -129,247 +589,230 @@ pub struct Rule {
     pub states: u8,
     /// Neighborhood type
     pub neighbor_method: NeighborMethod,
+    /// Non-totalistic patterns checked instead of survival/birth counts (not part of the notation)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<PatternRule>,
+    /// Chance (0.0-1.0) that a matching survival count keeps the cell, counts not listed always do
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", with = "chance_map")]
+    pub survival_chance: BTreeMap<u16, f32>,
+    /// Chance (0.0-1.0) that a matching birth count spawns a cell, counts not listed always do
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", with = "chance_map")]
+    pub birth_chance: BTreeMap<u16, f32>,
+    /// Count every living cell as a neighbor instead of only cells at max_state
+    /// Many published 3D rules use this convention
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub count_decaying: bool,
+    /// Generations each decaying state lasts, starting at state 1 (missing entries last 1 generation)
+    /// A 0 skips the state, so all zeros make cells die as soon as they leave max_state
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub decay: Vec<u8>,
+    /// Kill or decay cells after they've lived this long, whatever their neighbors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<MaxAge>,
+    /// State newly born cells start in, max_state when not set
+    /// Cells survive in this state, so a lower birth state leaves the states above it unused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birth_state: Option<u8>,
+    /// Lowest state that counts as a neighbor, overriding count_decaying when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub neighbor_threshold: Option<u8>,
+    /// Separate face/outer neighbor conditions checked instead of survival/birth counts (not part of the notation)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shells: Option<ShellRule>,
+}
+
+/// Birth/survival on two separate counts: face-adjacent neighbors (the 6 Von Neumann offsets)
+/// and all other neighbors in the neighborhood (the edges and corners of the Moore shell)
+/// Both counts must match, e.g. survive with 2-3 face AND 5-9 outer neighbors
+/// Species in an ecosystem count per species and ignore shells
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ShellRule {
+    pub face_survival: RuleValue,
+    pub face_birth: RuleValue,
+    pub outer_survival: RuleValue,
+    pub outer_birth: RuleValue,
+}
+
+impl ShellRule {
+    /// Check if a cell at max_state with these counts survives
+    #[inline]
+    pub fn survives(&self, faces: u8, outer: u16) -> bool {
+        self.face_survival.matches(faces as u16) && self.outer_survival.matches(outer)
+    }
+
+    /// Check if a dead cell with these counts is born
+    #[inline]
+    pub fn births(&self, faces: u8, outer: u16) -> bool {
+        self.face_birth.matches(faces as u16) && self.outer_birth.matches(outer)
+    }
+}
+
+/// Check if a neighbor offset shares a face with the cell
+#[inline]
+pub fn is_face_offset(offset: IVec3) -> bool {
+    offset.abs().element_sum() == 1
+}
+
+/// What happens to a living cell once it reaches its rule's max age
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum AgeAction {
+    /// Die immediately, skipping the decaying states
+    Die,
+    /// Stop surviving and decay through the remaining states as usual
+    Decay,
+}
+
+/// Age limit of a rule: cells alive for `generations` generations since birth undergo `action`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MaxAge {
+    pub generations: u16,
+    pub action: AgeAction,
 }
 
 impl Rule {
+    /// Look up a preset by name in the built-in rule registry (assets/rules.toml)
+    pub fn preset(name: &str) -> Option<Self> {
+        RuleRegistry::builtin().get(name).cloned()
+    }
+
+    fn builtin(name: &str) -> Self {
+        Self::preset(name).unwrap_or_else(|| panic!("built-in catalog is missing preset '{}'", name))
+    }
+
     /// Create the "445" rule (4/4/5/M)
     pub fn rule_445() -> Self {
-        Self {
-            survival: RuleValue::new(&[4]),
-            birth: RuleValue::new(&[4]),
-            states: 5,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("rule_445")
     }
 
     /// "Builder" - Creates complex expanding structures
     pub fn builder() -> Self {
-        Self {
-            survival: RuleValue::new(&[2, 6, 9]),
-            birth: RuleValue::new(&[4, 6, 8, 9, 10]),
-            states: 10,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("builder")
     }
 
     /// "Fancy Snancy" - Complex chaotic patterns
     pub fn fancy_snancy() -> Self {
-        Self {
-            survival: RuleValue::new(&[0,1,2,3,7,8,9,11,13,18,21,22,24,26]),
-            birth: RuleValue::new(&[4,13,17,20,21,22,23,24,26]),
-            states: 4,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("fancy_snancy")
     }
 
     /// "Pretty Crystals" - Forms crystalline structures
     pub fn pretty_crystals() -> Self {
-        Self {
-            survival: RuleValue::new(&[5,6,7,8]),
-            birth: RuleValue::new(&[6,7,9]),
-            states: 10,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("pretty_crystals")
     }
 
     /// "Slowly Expanding Blob" - Gradually growing structure
     pub fn expanding_blob() -> Self {
-        Self {
-            survival: RuleValue::from_range(9, 26),
-            birth: RuleValue::new(&[5,6,7,12,13,15]),
-            states: 20,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("expanding_blob")
     }
 
     /// "Clouds 1" - Cloud-like wispy structures (13-26/13-14,17-19/2/M)
     pub fn clouds_1() -> Self {
-        Self {
-            survival: RuleValue::from_range(13, 26),
-            birth: RuleValue::from_range(13, 14).or(RuleValue::from_range(17, 19)),
-            states: 2,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("clouds_1")
     }
 
     /// "Amoeba" - Slowly morphing blob-like organism (9-26/5-7,12-13,15/5/M)
     pub fn amoeba() -> Self {
-        Self {
-            survival: RuleValue::from_range(9, 26),
-            birth: RuleValue::from_range(5, 7)
-                .or(RuleValue::from_range(12, 13))
-                .or(RuleValue::new(&[15])),
-            states: 5,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("amoeba")
     }
 
     /// "Architecture" - Builds architectural-looking structures (4-6/3/2/M)
     pub fn architecture() -> Self {
-        Self {
-            survival: RuleValue::from_range(4, 6),
-            birth: RuleValue::new(&[3]),
-            states: 2,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("architecture")
     }
 
     /// "Brain" - Cellular structures resembling brain tissue (4/2/3/M)
     pub fn brain() -> Self {
-        Self {
-            survival: RuleValue::new(&[4]),
-            birth: RuleValue::new(&[2]),
-            states: 3,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("brain")
     }
 
     /// "Builder 2" - Another builder variant (5-7/1/2/M)
     pub fn builder_2() -> Self {
-        Self {
-            survival: RuleValue::from_range(5, 7),
-            birth: RuleValue::new(&[1]),
-            states: 2,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("builder_2")
     }
 
     /// "Coral" - Coral-like branching structures (5-8/6-7,9,12/8/M)
     pub fn coral() -> Self {
-        Self {
-            survival: RuleValue::from_range(5, 8),
-            birth: RuleValue::from_range(6, 7)
-                .or(RuleValue::new(&[9, 12])),
-            states: 8,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("coral")
     }
 
     /// "Crystal Growth 1" - Growing crystal formations (0-6/1,3/2/M)
     pub fn crystal_growth_1() -> Self {
-        Self {
-            survival: RuleValue::from_range(0, 6),
-            birth: RuleValue::new(&[1, 3]),
-            states: 2,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("crystal_growth_1")
     }
 
     /// "Diamond Growth" - Diamond-like crystal formations (5-6/7-8/10/M)
     pub fn diamond_growth() -> Self {
-        Self {
-            survival: RuleValue::from_range(5, 6),
-            birth: RuleValue::from_range(7, 8),
-            states: 10,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("diamond_growth")
     }
 
     /// "Pulse Waves" - Creates wave-like pulse patterns (3-8/3-7/3/M)
     pub fn pulse_waves() -> Self {
-        Self {
-            survival: RuleValue::from_range(3, 8),
-            birth: RuleValue::from_range(3, 7),
-            states: 3,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("pulse_waves")
     }
 
     /// "Pyroclastic" - Explosive volcanic-like patterns (4-7/6-8/10/M)
     pub fn pyroclastic() -> Self {
-        Self {
-            survival: RuleValue::from_range(4, 7),
-            birth: RuleValue::from_range(6, 8),
-            states: 10,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("pyroclastic")
     }
 
     /// "Spiky Growth" - Creates spiky protrusions (5-6/4/3/M)
     pub fn spiky_growth() -> Self {
-        Self {
-            survival: RuleValue::from_range(5, 6),
-            birth: RuleValue::new(&[4]),
-            states: 3,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("spiky_growth")
     }
 
     /// "Shells" - Shell-like layered structures (4-5/3/3/M)
     pub fn shells() -> Self {
-        Self {
-            survival: RuleValue::from_range(4, 5),
-            birth: RuleValue::new(&[3]),
-            states: 3,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("shells")
     }
 
     /// "VN Pyramid" - Von Neumann pyramid structure (0-6/1,3/2/V)
     pub fn vn_pyramid() -> Self {
-        Self {
-            survival: RuleValue::from_range(0, 6),
-            birth: RuleValue::new(&[1, 3]),
-            states: 2,
-            neighbor_method: NeighborMethod::VonNeumann,
-        }
+        Self::builtin("vn_pyramid")
     }
 
     /// "Swapping Structures" - Constantly morphing patterns (3,6,9/4,8,10/20/M)
     pub fn swapping_structures() -> Self {
-        Self {
-            survival: RuleValue::new(&[3, 6, 9]),
-            birth: RuleValue::new(&[4, 8, 10]),
-            states: 20,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("swapping_structures")
     }
 
     /// "Expand Then Die" - Explosive growth followed by collapse (4/3/20/M)
     pub fn expand_then_die() -> Self {
-        Self {
-            survival: RuleValue::new(&[4]),
-            birth: RuleValue::new(&[3]),
-            states: 20,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("expand_then_die")
     }
 
     /// "Spikey Growth" - Creates complex spikey patterns (6-7/4,6,9-11/6/M)
     pub fn spikey_growth_complex() -> Self {
-        Self {
-            survival: RuleValue::from_range(6, 7),
-            birth: RuleValue::new(&[4, 6, 9, 10, 11]),
-            states: 6,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("spikey_growth_complex")
     }
 
     /// "Large Lines" - Creates large linear structures (5/4,6,9-11,16-24/35/M)
     pub fn large_lines() -> Self {
-        Self {
-            survival: RuleValue::new(&[5]),
-            birth: RuleValue::new(&[4, 6, 9, 10, 11, 16, 17, 18, 19, 20, 21, 22, 23, 24]),
-            states: 35,
-            neighbor_method: NeighborMethod::Moore,
-        }
+        Self::builtin("large_lines")
     }
 
     /// Create a custom rule
-    pub fn new(survival: &[u8], birth: &[u8], states: u8, neighbor_method: NeighborMethod) -> Self {
+    pub fn new(survival: &[u16], birth: &[u16], states: u8, neighbor_method: NeighborMethod) -> Self {
         Self {
             survival: RuleValue::new(survival),
             birth: RuleValue::new(birth),
             states,
             neighbor_method,
+            pattern: None,
+            survival_chance: BTreeMap::new(),
+            birth_chance: BTreeMap::new(),
+            count_decaying: false,
+            decay: Vec::new(),
+            max_age: None,
+            birth_state: None,
+            neighbor_threshold: None,
+            shells: None,
         }
     }
 
     /// Create a rule from ranges
     /// Example: survival 4-7, birth 6-8, 10 states, Moore
     pub fn from_ranges(
-        survival_min: u8, survival_max: u8,
-        birth_min: u8, birth_max: u8,
+        survival_min: u16, survival_max: u16,
+        birth_min: u16, birth_max: u16,
         states: u8,
         neighbor_method: NeighborMethod
     ) -> Self {
@@ -378,18 +821,638 @@ impl Rule {
             birth: RuleValue::from_range(birth_min, birth_max),
             states,
             neighbor_method,
+            pattern: None,
+            survival_chance: BTreeMap::new(),
+            birth_chance: BTreeMap::new(),
+            count_decaying: false,
+            decay: Vec::new(),
+            max_age: None,
+            birth_state: None,
+            neighbor_threshold: None,
+            shells: None,
+        }
+    }
+
+    /// Parse a rule from survival/birth/states/method notation
+    /// Example: "4-7,12/6-8/10/M" or "0-6/1,3/2/VN"
+    pub fn from_notation(notation: &str) -> Result<Self, RuleParseError> {
+        let sections: Vec<&str> = notation.trim().split('/').collect();
+        let [survival, birth, states, method] = sections[..] else {
+            return Err(RuleParseError::WrongSectionCount(sections.len()));
+        };
+
+        // Parse the method first so counts can be checked against the neighborhood size
+        let neighbor_method: NeighborMethod = method.parse()?;
+        let max = neighbor_method.max_neighbors();
+
+        let states = states
+            .trim()
+            .parse::<u8>()
+            .map_err(|_| RuleParseError::InvalidStates(states.trim().to_string()))?;
+
+        let rule = Self {
+            survival: RuleValue::parse("survival", survival, max)?,
+            birth: RuleValue::parse("birth", birth, max)?,
+            states,
+            neighbor_method,
+            pattern: None,
+            survival_chance: BTreeMap::new(),
+            birth_chance: BTreeMap::new(),
+            count_decaying: false,
+            decay: Vec::new(),
+            max_age: None,
+            birth_state: None,
+            neighbor_threshold: None,
+            shells: None,
+        };
+        rule.validate()?;
+        Ok(rule)
+    }
+
+    /// Check that the rule can actually run: at least 2 states, something to be born on, a neighborhood whose
+    /// counts fit, and no survival/birth counts beyond what the neighborhood can produce
+    pub fn validate(&self) -> Result<(), RuleError> {
+        if self.states < 2 {
+            return Err(RuleError::TooFewStates(self.states));
+        }
+        for (part, state) in [("birth state", self.birth_state), ("neighbor threshold", self.neighbor_threshold)] {
+            if let Some(state) = state.filter(|&state| state == 0 || state > self.states) {
+                return Err(RuleError::StateOutOfRange { part, state, states: self.states });
+            }
+        }
+
+        // Pattern and two-shell rules are born on their own conditions, their birth counts are ignored
+        let no_birth = match (&self.pattern, &self.shells) {
+            (Some(pattern), _) => pattern.birth.is_empty(),
+            (None, Some(shells)) => shells.face_birth.is_empty() || shells.outer_birth.is_empty(),
+            (None, None) => self.birth.is_empty(),
+        };
+        if no_birth {
+            return Err(RuleError::EmptyBirth);
+        }
+        // Shells count cells, so the outer count can't be derived from a weighted sum
+        if self.shells.is_some() && self.neighbor_method.weights().is_some() {
+            return Err(RuleError::WeightedShells);
+        }
+
+        // Offsets from config files don't go through the notation parser's checks
+        let offsets = self.neighbor_method.get_neighbors().len();
+        if offsets == 0 {
+            return Err(RuleError::EmptyNeighborhood);
+        }
+        if offsets > MAX_NEIGHBORS as usize {
+            return Err(RuleError::TooManyOffsets(offsets));
         }
+        // Weighted sums are cached per cell in a u16, so heavier kernels would overflow it
+        if let NeighborMethod::Weighted(kernel) = &self.neighbor_method {
+            if kernel.total_weight() > MAX_NEIGHBORS as u32 {
+                return Err(RuleError::KernelTooHeavy(kernel.total_weight()));
+            }
+        }
+
+        let max = self.neighbor_method.max_neighbors();
+        for (part, value) in [("survival", &self.survival), ("birth", &self.birth)] {
+            if let Some(count) = value.max_count().filter(|&count| count > max) {
+                return Err(RuleError::CountOutOfRange { part, count, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// Switch to non-totalistic evaluation: births and survivals match neighbor patterns, not counts
+    pub fn with_pattern(mut self, pattern: PatternRule) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// Make survival on `counts` happen only with probability `chance`
+    pub fn with_survival_chance(mut self, counts: &[u16], chance: f32) -> Self {
+        let chance = chance.clamp(0.0, 1.0);
+        self.survival_chance.extend(counts.iter().map(|&count| (count, chance)));
+        self
+    }
+
+    /// Make birth on `counts` happen only with probability `chance`
+    /// Example: `with_birth_chance(&[6], 0.4)` births on 6 neighbors only 40% of the time
+    pub fn with_birth_chance(mut self, counts: &[u16], chance: f32) -> Self {
+        let chance = chance.clamp(0.0, 1.0);
+        self.birth_chance.extend(counts.iter().map(|&count| (count, chance)));
+        self
+    }
+
+    /// Count decaying cells as neighbors too, not only cells at max_state
+    pub fn with_decaying_neighbors(mut self) -> Self {
+        self.count_decaying = true;
+        self
+    }
+
+    /// Set how many generations each decaying state lasts, starting at state 1
+    /// Example: `with_decay(&[20])` leaves a long-lived trail in state 1 before cells die
+    pub fn with_decay(mut self, durations: &[u8]) -> Self {
+        self.decay = durations.to_vec();
+        self
+    }
+
+    /// Kill cells as soon as they leave max_state, skipping all decaying states
+    pub fn with_instant_death(mut self) -> Self {
+        self.decay = vec![0; self.states.saturating_sub(1) as usize];
+        self
+    }
+
+    /// Start newborn cells in `state` (1..=states) instead of max_state
+    pub fn with_birth_state(mut self, state: u8) -> Self {
+        self.birth_state = Some(state);
+        self
+    }
+
+    /// Count cells in `threshold` or any higher state as neighbors
+    pub fn with_neighbor_threshold(mut self, threshold: u8) -> Self {
+        self.neighbor_threshold = Some(threshold);
+        self
+    }
+
+    /// Switch to two-shell evaluation: births and survivals match face and outer neighbor counts separately
+    /// Example: `with_shells(ShellRule { face_survival: RuleValue::from_range(2, 3), outer_survival: RuleValue::from_range(5, 9), .. })`
+    pub fn with_shells(mut self, shells: ShellRule) -> Self {
+        self.shells = Some(shells);
+        self
+    }
+
+    /// Make cells die or decay once they've been alive for `generations`, limiting how far growth spreads
+    /// Example: `with_max_age(40, AgeAction::Decay)` lets every structure fade after 40 generations
+    pub fn with_max_age(mut self, generations: u16, action: AgeAction) -> Self {
+        self.max_age = Some(MaxAge { generations, action });
+        self
+    }
+
+    /// Dual rule with alive and dead swapped (black/white reversal): a cell is born where the
+    /// original would have let it die, and survives where the original wouldn't have spawned it
+    /// Exact for 2-state rules, an approximation once decaying states are involved
+    /// Non-totalistic patterns are kept, per-count chances are dropped since their counts no longer apply
+    pub fn complement(&self) -> Self {
+        let max = self.neighbor_method.max_neighbors();
+        Self {
+            survival: self.birth.complement(max).mirrored(max),
+            birth: self.survival.complement(max).mirrored(max),
+            survival_chance: BTreeMap::new(),
+            birth_chance: BTreeMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Rule that counts empty neighbors instead of counted ones: every count N becomes max_neighbors - N
+    /// Non-totalistic patterns are kept, per-count chances move with their counts
+    pub fn inverted_counts(&self) -> Self {
+        let max = self.neighbor_method.max_neighbors();
+        let mirror = |chances: &BTreeMap<u16, f32>| -> BTreeMap<u16, f32> {
+            chances
+                .iter()
+                .filter(|&(&count, _)| count <= max)
+                .map(|(&count, &chance)| (max - count, chance))
+                .collect()
+        };
+        Self {
+            survival: self.survival.mirrored(max),
+            birth: self.birth.mirrored(max),
+            survival_chance: mirror(&self.survival_chance),
+            birth_chance: mirror(&self.birth_chance),
+            ..self.clone()
+        }
+    }
+
+    /// Rule with its survival counts moved by `survival` and birth counts by `birth`, e.g. (1, 0) makes
+    /// survival need one more neighbor. Per-count chances move with their counts
+    pub fn shifted(&self, survival: i32, birth: i32) -> Self {
+        let max = self.neighbor_method.max_neighbors();
+        let shift = |chances: &BTreeMap<u16, f32>, offset: i32| -> BTreeMap<u16, f32> {
+            chances
+                .iter()
+                .filter_map(|(&count, &chance)| {
+                    let shifted = u16::try_from(count as i32 + offset).ok()?;
+                    (shifted <= max).then_some((shifted, chance))
+                })
+                .collect()
+        };
+        Self {
+            survival: self.survival.shifted(survival, max),
+            birth: self.birth.shifted(birth, max),
+            survival_chance: shift(&self.survival_chance, survival),
+            birth_chance: shift(&self.birth_chance, birth),
+            ..self.clone()
+        }
+    }
+
+    /// Create a nearby rule by randomly flipping survival/birth counts and nudging the state count
+    /// `strength` (0.0-1.0) is the chance of each individual change, so 0.05 flips about 1 in 20 counts
+    /// Mutants that fail `validate` are drawn again; if none passes, the rule comes back unchanged
+    pub fn mutate(&self, rng: &mut impl Rng, strength: f32) -> Self {
+        for _ in 0..MUTATION_ATTEMPTS {
+            let rule = self.mutate_once(rng, strength);
+            if rule.validate().is_ok() {
+                return rule;
+            }
+        }
+        self.clone()
+    }
+
+    fn mutate_once(&self, rng: &mut impl Rng, strength: f32) -> Self {
+        let strength = strength.clamp(0.0, 1.0);
+        let max = self.neighbor_method.max_neighbors();
+        let mut rule = self.clone();
+        // Without neighbors there are no counts to flip (`validate` rejects such rules anyway)
+        if max == 0 {
+            return rule;
+        }
+
+        for count in 0..=max {
+            if rng.random::<f32>() < strength {
+                rule.survival = rule.survival.toggle(count);
+            }
+            // Birth on 0 neighbors fills all empty space, never mutate into it
+            if count > 0 && rng.random::<f32>() < strength {
+                rule.birth = rule.birth.toggle(count);
+            }
+        }
+
+        if rng.random::<f32>() < strength {
+            let max_step = ((self.states as f32 * strength).ceil() as i32).max(1);
+            let step = rng.random_range(1..=max_step) * if rng.random() { 1 } else { -1 };
+            rule = rule.with_states((self.states as i32 + step).clamp(2, u8::MAX as i32) as u8);
+        }
+
+        // Always change something, and keep at least one birth count so the rule can grow
+        if rule == *self {
+            rule.birth = rule.birth.toggle(rng.random_range(1..=max));
+        }
+        if rule.birth.is_empty() {
+            rule.birth = RuleValue::new(&[rng.random_range(1..=max)]);
+        }
+
+        rule
+    }
+
+    /// Change the number of states, pulling a fixed birth state or neighbor threshold down into the new range
+    pub fn with_states(mut self, states: u8) -> Self {
+        self.states = states;
+        self.birth_state = self.birth_state.map(|state| state.min(states));
+        self.neighbor_threshold = self.neighbor_threshold.map(|threshold| threshold.min(states));
+        self
+    }
+
+    /// True if both rules count the same cells with the same neighborhood, so cached neighbor
+    /// counts stay valid when switching between them
+    pub fn counts_like(&self, other: &Rule) -> bool {
+        self.neighbor_method == other.neighbor_method
+            && self.states == other.states
+            && self.neighbor_threshold() == other.neighbor_threshold()
+            && self.shells.is_some() == other.shells.is_some()
+    }
+
+    /// State newborn cells start (and survive) in
+    #[inline]
+    pub fn birth_state(&self) -> u8 {
+        self.birth_state.unwrap_or(self.states)
+    }
+
+    /// Lowest state that counts as a neighbor
+    #[inline]
+    pub fn neighbor_threshold(&self) -> u8 {
+        match self.neighbor_threshold {
+            Some(threshold) => threshold,
+            None if self.count_decaying => 1,
+            None => self.birth_state(),
+        }
+    }
+
+    /// Check if a cell in `state` adds to its neighbors' counts
+    #[inline]
+    pub fn counts_as_neighbor(&self, state: u8) -> bool {
+        state > 0 && state >= self.neighbor_threshold()
+    }
+
+    /// What happens to a living cell of `age` generations, None while it's younger than max_age
+    #[inline]
+    pub fn expired(&self, age: u16) -> Option<AgeAction> {
+        self.max_age.filter(|limit| age >= limit.generations).map(|limit| limit.action)
+    }
+
+    /// Generations a cell spends in decaying `state` (1..states) before moving on
+    #[inline]
+    pub fn decay_duration(&self, state: u8) -> u8 {
+        self.decay.get(state as usize - 1).copied().unwrap_or(1)
+    }
+
+    /// State a cell decays into from `state`, skipping states with a duration of 0
+    #[inline]
+    pub fn next_decay_state(&self, state: u8) -> u8 {
+        if self.decay.is_empty() {
+            return state - 1;
+        }
+        (1..state).rev().find(|&lower| self.decay_duration(lower) > 0).unwrap_or(0)
     }
 
     /// Check if a cell should survive
     #[inline]
-    pub fn should_survive(&self, neighbors: u8) -> bool {
+    pub fn should_survive(&self, neighbors: u16) -> bool {
         self.survival.matches(neighbors)
     }
 
     /// Check if a cell should be born
     #[inline]
-    pub fn should_birth(&self, neighbors: u8) -> bool {
+    pub fn should_birth(&self, neighbors: u16) -> bool {
         self.birth.matches(neighbors)
     }
+
+    /// Check if a cell should survive given its total and face-adjacent neighbor counts
+    /// Two-shell rules check both shells, other rules only the total
+    #[inline]
+    pub fn should_survive_counts(&self, neighbors: u16, faces: u8) -> bool {
+        match &self.shells {
+            Some(shells) => shells.survives(faces, neighbors - faces as u16),
+            None => self.should_survive(neighbors),
+        }
+    }
+
+    /// Check if a cell should be born given its total and face-adjacent neighbor counts
+    #[inline]
+    pub fn should_birth_counts(&self, neighbors: u16, faces: u8) -> bool {
+        match &self.shells {
+            Some(shells) => shells.births(faces, neighbors - faces as u16),
+            None => self.should_birth(neighbors),
+        }
+    }
+
+    /// Probability that a cell matching the survival rule with `neighbors` actually survives
+    #[inline]
+    pub fn survival_probability(&self, neighbors: u16) -> f32 {
+        self.survival_chance.get(&neighbors).copied().unwrap_or(1.0)
+    }
+
+    /// Probability that a cell matching the birth rule with `neighbors` is actually born
+    #[inline]
+    pub fn birth_probability(&self, neighbors: u16) -> f32 {
+        self.birth_chance.get(&neighbors).copied().unwrap_or(1.0)
+    }
+
+    /// Check if a cell should survive given which neighbors are alive (non-totalistic rules)
+    #[inline]
+    pub fn should_survive_pattern(&self, pattern: u32) -> bool {
+        self.pattern.as_ref().is_some_and(|rule| rule.survives(pattern))
+    }
+
+    /// Check if a cell should be born given which neighbors are alive (non-totalistic rules)
+    #[inline]
+    pub fn should_birth_pattern(&self, pattern: u32) -> bool {
+        self.pattern.as_ref().is_some_and(|rule| rule.births(pattern))
+    }
+}
+
+impl fmt::Display for Rule {
+    /// Formats in survival/birth/states/method notation, e.g. "4-7,12/6-8/10/M"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}/{}", self.survival, self.birth, self.states, self.neighbor_method)
+    }
+}
+
+/// Reason a rule can't run, returned by `Rule::validate`
+#[derive(Clone, PartialEq, Debug)]
+pub enum RuleError {
+    /// Fewer than 2 states (dead plus at least one living state)
+    TooFewStates(u8),
+    /// No birth counts (or birth patterns), so no cell can ever be born
+    EmptyBirth,
+    /// A survival/birth count larger than the neighborhood can produce
+    CountOutOfRange { part: &'static str, count: u16, max: u16 },
+    /// A birth state or neighbor threshold outside 1..=states
+    StateOutOfRange { part: &'static str, state: u8, states: u8 },
+    /// A two-shell rule on a weighted neighborhood
+    WeightedShells,
+    /// A custom or weighted neighborhood without any offsets, so no cell ever has a neighbor
+    EmptyNeighborhood,
+    /// A custom or weighted neighborhood with more offsets than neighbor counts can hold
+    TooManyOffsets(usize),
+    /// A weighted kernel whose weights sum past what neighbor counts can hold
+    KernelTooHeavy(u32),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::TooFewStates(states) => {
+                write!(f, "rule has {} states, at least 2 are needed (dead and alive)", states)
+            }
+            RuleError::EmptyBirth => write!(f, "rule has no birth counts, so no cell can ever be born"),
+            RuleError::CountOutOfRange { part, count, max } => write!(
+                f,
+                "{} count {} exceeds the neighborhood maximum of {}", part, count, max
+            ),
+            RuleError::StateOutOfRange { part, state, states } => {
+                write!(f, "{} {} is outside the rule's states 1-{}", part, state, states)
+            }
+            RuleError::WeightedShells => write!(f, "two-shell rules need an unweighted neighborhood"),
+            RuleError::EmptyNeighborhood => write!(f, "neighborhood has no offsets (or only zero weights)"),
+            RuleError::TooManyOffsets(count) => write!(
+                f,
+                "neighborhood has {} offsets, at most {} are supported", count, MAX_NEIGHBORS
+            ),
+            RuleError::KernelTooHeavy(total) => write!(
+                f,
+                "weighted kernel sums to {}, at most {} is supported", total, MAX_NEIGHBORS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Error returned when parsing rule notation fails
+#[derive(Clone, PartialEq, Debug)]
+pub enum RuleParseError {
+    /// Notation didn't have exactly 4 '/'-separated sections
+    WrongSectionCount(usize),
+    /// A survival/birth token wasn't a number or range
+    InvalidCount { part: &'static str, token: String },
+    /// A range with min > max, e.g. "8-4"
+    InvalidRange { part: &'static str, token: String },
+    /// A count larger than the neighborhood allows
+    CountOutOfRange { part: &'static str, count: u16, max: u16 },
+    /// The states section wasn't a number in 0..=255
+    InvalidStates(String),
+    /// Unknown neighborhood suffix
+    UnknownMethod(String),
+    /// A custom neighborhood offset that isn't "x,y,z"
+    InvalidOffset(String),
+    /// A custom or weighted neighborhood without offsets ("C[]", or only zero weights)
+    EmptyNeighborhood,
+    /// A custom neighborhood with more offsets than the rule bitset can count
+    TooManyOffsets(usize),
+    /// A weighted kernel whose total weight exceeds what the rule bitset can count
+    KernelTooHeavy(u32),
+    /// The notation parsed but describes a rule that can't run
+    Invalid(RuleError),
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleParseError::WrongSectionCount(n) => write!(
+                f,
+                "expected 4 sections (survival/birth/states/method), found {}", n
+            ),
+            RuleParseError::InvalidCount { part, token } => {
+                write!(f, "invalid {} count '{}': expected a number or range like 4-7", part, token)
+            }
+            RuleParseError::InvalidRange { part, token } => {
+                write!(f, "invalid {} range '{}': start is greater than end", part, token)
+            }
+            RuleParseError::CountOutOfRange { part, count, max } => write!(
+                f,
+                "{} count {} exceeds the neighborhood maximum of {}", part, count, max
+            ),
+            RuleParseError::InvalidStates(token) => {
+                write!(f, "invalid state count '{}': expected a number between 2 and 255", token)
+            }
+            RuleParseError::UnknownMethod(token) => {
+                write!(f, "unknown neighborhood '{}': expected M, M2, M3 (Moore radius 1-3), VN, VN2 (Von Neumann, radius-2 cross), D (corners), K (knight), C[x,y,z;...] (custom) or W[x,y,z,weight;...] (weighted)", token)
+            }
+            RuleParseError::InvalidOffset(token) => write!(
+                f,
+                "invalid neighborhood offset '{}': expected x,y,z (or x,y,z,weight with a weight of 0-65535)", token
+            ),
+            RuleParseError::EmptyNeighborhood => {
+                write!(f, "custom neighborhood has no offsets (or only zero weights)")
+            }
+            RuleParseError::TooManyOffsets(count) => write!(
+                f,
+                "custom neighborhood has {} offsets, at most {} are supported", count, MAX_NEIGHBORS
+            ),
+            RuleParseError::KernelTooHeavy(total) => write!(
+                f,
+                "weighted kernel sums to {}, at most {} is supported", total, MAX_NEIGHBORS
+            ),
+            RuleParseError::Invalid(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RuleParseError {}
+
+impl From<RuleError> for RuleParseError {
+    fn from(e: RuleError) -> Self {
+        RuleParseError::Invalid(e)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_notation(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn parse_error(notation: &str) -> RuleParseError {
+        Rule::from_notation(notation).expect_err(notation)
+    }
+
+    #[test]
+    fn malformed_sections_are_rejected() {
+        assert_eq!(parse_error("4/4"), RuleParseError::WrongSectionCount(2));
+        assert_eq!(parse_error("4/4/5/M/M"), RuleParseError::WrongSectionCount(5));
+        assert_eq!(parse_error("4,x/4/5/M"), RuleParseError::InvalidCount { part: "survival", token: "x".to_string() });
+        assert_eq!(parse_error("4/4-/5/M"), RuleParseError::InvalidCount { part: "birth", token: "4-".to_string() });
+        assert_eq!(parse_error("4/8-4/5/M"), RuleParseError::InvalidRange { part: "birth", token: "8-4".to_string() });
+        assert_eq!(parse_error("4/27/5/M"), RuleParseError::CountOutOfRange { part: "birth", count: 27, max: 26 });
+        assert_eq!(parse_error("7/4/5/VN"), RuleParseError::CountOutOfRange { part: "survival", count: 7, max: 6 });
+        assert_eq!(parse_error("4/4/many/M"), RuleParseError::InvalidStates("many".to_string()));
+        assert_eq!(parse_error("4/4/256/M"), RuleParseError::InvalidStates("256".to_string()));
+    }
+
+    #[test]
+    fn malformed_neighborhoods_are_rejected() {
+        assert_eq!(parse_error("4/4/5/Q"), RuleParseError::UnknownMethod("Q".to_string()));
+        assert_eq!(parse_error("4/4/5/C[1,0,0"), RuleParseError::UnknownMethod("C[1,0,0".to_string()));
+        assert_eq!(parse_error("4/4/5/C[1,0]"), RuleParseError::InvalidOffset("1,0".to_string()));
+        assert_eq!(parse_error("4/4/5/C[1,0,0,0]"), RuleParseError::InvalidOffset("1,0,0,0".to_string()));
+        assert_eq!(parse_error("1/1/5/C[1,a,0]"), RuleParseError::InvalidOffset("1,a,0".to_string()));
+        assert_eq!(parse_error("1/1/5/W[1,0,0]"), RuleParseError::InvalidOffset("1,0,0".to_string()));
+        assert_eq!(parse_error("1/1/5/W[1,0,0,-2]"), RuleParseError::InvalidOffset("1,0,0,-2".to_string()));
+        assert_eq!(parse_error("1/1/5/W[1,0,0,400]"), RuleParseError::KernelTooHeavy(400));
+        assert_eq!(parse_error("0/0/2/C[]"), RuleParseError::EmptyNeighborhood);
+        assert_eq!(parse_error("0/0/2/C[ ; ]"), RuleParseError::EmptyNeighborhood);
+        assert_eq!(parse_error("0/0/2/W[1,0,0,0;0,1,0,0]"), RuleParseError::EmptyNeighborhood);
+
+        let offsets: Vec<String> = (0..=MAX_NEIGHBORS as i32).map(|x| format!("{},0,0", x + 1)).collect();
+        let notation = format!("1/1/5/C[{}]", offsets.join(";"));
+        assert_eq!(parse_error(&notation), RuleParseError::TooManyOffsets(MAX_NEIGHBORS as usize + 1));
+    }
+
+    #[test]
+    fn rules_that_cannot_run_are_rejected() {
+        assert_eq!(parse_error("4/4/0/M"), RuleParseError::Invalid(RuleError::TooFewStates(0)));
+        assert_eq!(parse_error("4/4/1/M"), RuleParseError::Invalid(RuleError::TooFewStates(1)));
+        assert_eq!(parse_error("4//5/M"), RuleParseError::Invalid(RuleError::EmptyBirth));
+
+        // Rules from config files skip the notation parser, so validate catches empty neighborhoods itself
+        let empty = Rule::new(&[], &[0], 2, NeighborMethod::Custom(Vec::new()));
+        assert_eq!(empty.validate(), Err(RuleError::EmptyNeighborhood));
+        let weightless = Rule::new(&[], &[0], 2, NeighborMethod::Weighted(WeightedKernel::new([(IVec3::X, 0)])));
+        assert_eq!(weightless.validate(), Err(RuleError::EmptyNeighborhood));
+    }
+
+    #[test]
+    fn notation_round_trips_for_every_neighborhood() {
+        for notation in [
+            "4/4/5/M",
+            "0-6/1,3/2/VN",
+            "4-7,12/6-8/10/M",
+            "4-7,12/6-8/10/M2",
+            "10-40,100/30-45/4/M3",
+            "1-3/2,4/3/VN2",
+            "2/1-3/2/D",
+            "3-5/4/6/K",
+            "1,3/2/3/C[1,0,0;-1,0,0;0,2,0;0,-2,0;1,1,1]",
+            "2-5/3,5/4/W[1,0,0,2;-1,0,0,2;0,3,0,1]",
+        ] {
+            let rule = Rule::from_notation(notation).unwrap_or_else(|e| panic!("{}: {}", notation, e));
+            let written = rule.to_string();
+            assert_eq!(written, notation);
+            assert_eq!(written.parse::<Rule>(), Ok(rule));
+        }
+    }
+
+    #[test]
+    fn notation_is_written_in_canonical_form() {
+        for (notation, canonical) in [
+            (" 7,4,5,6, / 8,9,10 / 3 / m ", "4-7/8-10/3/M"),
+            ("4/4/5/n", "4/4/5/VN"),
+            ("4/4/5/N2", "4/4/5/VN2"),
+            ("1/1/2/c[ 1,0,0 ; -1,0,0 ]", "1/1/2/C[1,0,0;-1,0,0]"),
+            ("1/1/2/W[1,0,0,2;0,1,0,0;-1,0,0,1]", "1/1/2/W[1,0,0,2;-1,0,0,1]"),
+        ] {
+            let rule = Rule::from_notation(notation).unwrap_or_else(|e| panic!("{}: {}", notation, e));
+            assert_eq!(rule.to_string(), canonical);
+            assert_eq!(canonical.parse::<Rule>(), Ok(rule));
+        }
+    }
+
+    #[test]
+    fn mutants_stay_valid() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let rule = Rule::builder().with_birth_state(10).with_neighbor_threshold(9);
+        for _ in 0..200 {
+            let mutant = rule.mutate(&mut rng, 1.0);
+            assert_eq!(mutant.validate(), Ok(()), "{}", mutant);
+        }
+
+        // No counts to flip, but mutating must not panic either
+        let empty = Rule::new(&[], &[0], 2, NeighborMethod::Custom(Vec::new()));
+        assert_eq!(empty.mutate(&mut rng, 1.0), empty);
+    }
 }
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::grid::Grid;
+use crate::rule::Rule;
+
+/// A rule that takes over at a given generation
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ScheduledRule {
+    pub generation: u64,
+    pub rule: Rule,
+}
+
+/// Switches the active rule as generations pass, e.g. grow with builder() then erode with expand_then_die()
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RuleSchedule {
+    /// Timeline of rules, each active from its generation until the next entry starts
+    pub entries: Vec<ScheduledRule>,
+    /// Restart the timeline every this many generations, None stays on the last rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<u64>,
+    /// Index of the entry currently applied, so manual rule changes (M key) stick until the next switch
+    #[serde(skip)]
+    active: Option<usize>,
+}
+
+impl RuleSchedule {
+    /// Play a timeline of (start generation, rule) once, staying on the last rule
+    pub fn timeline(entries: impl IntoIterator<Item = (u64, Rule)>) -> Self {
+        let mut entries: Vec<ScheduledRule> = entries
+            .into_iter()
+            .map(|(generation, rule)| ScheduledRule { generation, rule })
+            .collect();
+        entries.sort_by_key(|entry| entry.generation);
+        Self { entries, period: None, active: None }
+    }
+
+    /// Cycle through `rules` forever, switching every `interval` generations
+    pub fn every(interval: u64, rules: impl IntoIterator<Item = Rule>) -> Self {
+        let interval = interval.max(1);
+        let mut schedule = Self::timeline(rules.into_iter().enumerate().map(|(i, rule)| (i as u64 * interval, rule)));
+        schedule.period = Some(schedule.entries.len() as u64 * interval);
+        schedule
+    }
+
+    /// Index of the entry active at `generation`, None before the first entry starts
+    pub fn entry_at(&self, generation: u64) -> Option<usize> {
+        let generation = match self.period {
+            Some(period) if period > 0 => generation % period,
+            _ => generation,
+        };
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.generation <= generation)
+            .max_by_key(|(_, entry)| entry.generation)
+            .map(|(index, _)| index)
+    }
+
+    /// Rule active at `generation`
+    pub fn rule_at(&self, generation: u64) -> Option<&Rule> {
+        self.entry_at(generation).map(|index| &self.entries[index].rule)
+    }
+}
+
+/// Swap in the scheduled rule when the grid reaches its generation (see `Grid::swap_rule`)
+pub fn apply_rule_schedule(
+    mut schedule: ResMut<RuleSchedule>,
+    mut grid: ResMut<Grid>,
+    mut rule: ResMut<Rule>,
+) {
+    let generation = grid.generation();
+    let Some(index) = schedule.entry_at(generation) else {
+        return;
+    };
+    if schedule.active == Some(index) {
+        return;
+    }
+
+    schedule.active = Some(index);
+    grid.swap_rule(&mut rule, schedule.entries[index].rule.clone());
+    println!("Generation {}: switched to rule {}", generation, *rule);
+}
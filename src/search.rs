@@ -0,0 +1,233 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::Path;
+use crate::catalog::{CatalogEntry, CatalogError, RuleCatalog};
+use crate::grid::Grid;
+use crate::rule::Rule;
+use crate::structure::Structure;
+
+/// Settings for the genetic rule search
+#[derive(Clone, Debug)]
+pub struct SearchConfig {
+    /// Rules evaluated per generation
+    pub population: usize,
+    /// Number of evolution rounds
+    pub generations: usize,
+    /// Best rules copied unchanged into the next generation
+    pub elite: usize,
+    /// Simulation steps used to score each rule
+    pub sim_steps: usize,
+    /// Edge length of the headless grid
+    pub grid_size: i32,
+    /// Per-change probability passed to `Rule::mutate`
+    pub mutation_strength: f32,
+    /// Number of best rules written to the output catalog
+    pub keep: usize,
+    pub seed: u64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            population: 32,
+            generations: 20,
+            elite: 4,
+            sim_steps: 120,
+            grid_size: 32,
+            mutation_strength: 0.05,
+            keep: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// Metrics collected from a short headless run of a rule
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fitness {
+    /// Fraction of the grid alive at the end of the run
+    pub density: f32,
+    /// Average (spawns + deaths) per living cell over the second half of the run
+    pub activity: f32,
+    /// 1.0 when the population is flat over the second half, towards 0.0 when it swings wildly
+    pub stability: f32,
+    /// Combined score, higher is more interesting
+    pub score: f32,
+    /// Shape of the living cells at the end of the run, e.g. telling crystals from clouds (not scored)
+    pub structure: Structure,
+}
+
+impl Fitness {
+    /// Score favors rules that neither die out nor fill the grid, keep changing and don't oscillate wildly
+    fn combine(density: f32, activity: f32, stability: f32) -> Self {
+        // Structure size: peaks at 15% density, zero when extinct or at half the grid
+        let size_score = if density <= 0.15 {
+            density / 0.15
+        } else {
+            (1.0 - (density - 0.15) / 0.35).max(0.0)
+        };
+        // Activity: frozen structures score low, but so does pure noise
+        let activity_score = (activity / (activity + 0.05)) * (1.0 - activity.min(1.0));
+
+        Self {
+            density,
+            activity,
+            stability,
+            score: size_score * (0.5 + 0.5 * activity_score) * (0.5 + 0.5 * stability),
+            structure: Structure::default(),
+        }
+    }
+}
+
+/// Run a rule headlessly from a random center cluster and measure it
+pub fn evaluate(rule: &Rule, config: &SearchConfig) -> Fitness {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut grid = Grid::new(config.grid_size);
+    let radius = (config.grid_size / 6).max(1);
+    let cluster_volume = ((radius * 2 + 1).pow(3)) as usize;
+    grid.spawn_center_cluster(rule, rule.states, radius, cluster_volume / 2, &mut rng);
+
+    let total = (config.grid_size as f32).powi(3);
+    let measure_from = config.sim_steps / 2;
+    let mut populations = Vec::with_capacity(config.sim_steps - measure_from);
+    let mut activity = 0.0;
+
+    for step in 0..config.sim_steps {
+        let changes = grid.step(rule, &mut rng);
+        if step < measure_from {
+            continue;
+        }
+
+        let alive = grid.cell_count();
+        if alive == 0 {
+            return Fitness::default();
+        }
+        populations.push(alive as f32);
+        activity += (changes.spawns.len() + changes.deaths.len()) as f32 / alive as f32;
+    }
+
+    let samples = populations.len().max(1) as f32;
+    let mean = populations.iter().sum::<f32>() / samples;
+    let variance = populations.iter().map(|p| (p - mean).powi(2)).sum::<f32>() / samples;
+    let stability = 1.0 / (1.0 + variance.sqrt() / mean.max(1.0));
+    let density = populations.last().copied().unwrap_or(0.0) / total;
+
+    Fitness { structure: Structure::measure(&grid), ..Fitness::combine(density, activity / samples, stability) }
+}
+
+/// A scored rule from the search
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub rule: Rule,
+    pub fitness: Fitness,
+}
+
+/// Evolve rules starting from `seeds`, returning the best candidates sorted by score
+pub fn evolve(seeds: &[Rule], config: &SearchConfig) -> Vec<Candidate> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut population: Vec<Rule> = (0..config.population)
+        .map(|i| seeds[i % seeds.len()].mutate(&mut rng, config.mutation_strength))
+        .collect();
+    let mut best: Vec<Candidate> = Vec::new();
+
+    for generation in 0..config.generations {
+        let mut scored = evaluate_all(&population, config);
+        scored.sort_by(|a, b| b.fitness.score.total_cmp(&a.fitness.score));
+
+        println!(
+            "Generation {:3}: best {:.3} ({}), median {:.3}",
+            generation,
+            scored[0].fitness.score,
+            scored[0].rule,
+            scored[scored.len() / 2].fitness.score,
+        );
+
+        for candidate in &scored {
+            if !best.iter().any(|b| b.rule == candidate.rule) {
+                best.push(candidate.clone());
+            }
+        }
+        best.sort_by(|a, b| b.fitness.score.total_cmp(&a.fitness.score));
+        best.truncate(config.keep);
+
+        // Next generation: elites survive, the rest are mutated crossovers of tournament winners
+        let elite = config.elite.min(scored.len());
+        population = scored[..elite].iter().map(|c| c.rule.clone()).collect();
+        while population.len() < config.population {
+            let a = tournament(&scored, &mut rng);
+            let b = tournament(&scored, &mut rng);
+            population.push(crossover(a, b, &mut rng).mutate(&mut rng, config.mutation_strength));
+        }
+    }
+
+    best
+}
+
+/// Score every rule, spreading the headless runs across all CPU cores
+fn evaluate_all(rules: &[Rule], config: &SearchConfig) -> Vec<Candidate> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = rules.len().div_ceil(threads).max(1);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = rules
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|rule| Candidate { rule: rule.clone(), fitness: evaluate(rule, config) })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("rule evaluation thread panicked"))
+            .collect()
+    })
+}
+
+/// Pick the better of two random candidates
+fn tournament<'a>(scored: &'a [Candidate], rng: &mut impl Rng) -> &'a Rule {
+    let a = &scored[rng.random_range(0..scored.len())];
+    let b = &scored[rng.random_range(0..scored.len())];
+    if a.fitness.score >= b.fitness.score { &a.rule } else { &b.rule }
+}
+
+/// Combine the survival counts of one parent with the birth counts of the other
+fn crossover(a: &Rule, b: &Rule, rng: &mut impl Rng) -> Rule {
+    let mut child = a.clone();
+    if a.neighbor_method == b.neighbor_method {
+        child.birth = b.birth;
+    }
+    if rng.random() {
+        child = child.with_states(b.states);
+    }
+    child
+}
+
+/// Run a search starting from `seeds` and write the best rules to the catalog file `output`
+pub fn run_search(seeds: &[Rule], config: &SearchConfig, output: impl AsRef<Path>) -> Result<(), CatalogError> {
+    let best = evolve(seeds, config);
+
+    let mut found = RuleCatalog::default();
+    for (i, candidate) in best.iter().enumerate() {
+        let fitness = candidate.fitness;
+        found.insert(CatalogEntry {
+            name: format!("search_{:03}", i + 1),
+            description: format!(
+                "score {:.3} (density {:.3}, activity {:.3}, stability {:.3}, entropy {:.3}, surface/volume {:.2})",
+                fitness.score,
+                fitness.density,
+                fitness.activity,
+                fitness.stability,
+                fitness.structure.entropy,
+                fitness.structure.surface_to_volume,
+            ),
+            rule: candidate.rule.clone(),
+        });
+    }
+
+    found.save(&output)?;
+    println!("Wrote {} rules to {}", found.entries.len(), output.as_ref().display());
+    Ok(())
+}
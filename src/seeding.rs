@@ -0,0 +1,338 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{CompressedImageFormats, Image, ImageSampler, ImageType};
+use bevy::math::{IVec3, Vec3};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::grid::Grid;
+use crate::immigration::Region;
+use crate::rule::Rule;
+use crate::zones::Axis;
+
+/// Starting cells of a run, different rules need very different ones to show their character (see `Grid::seed`)
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SeedPattern {
+    /// `amount` random cells within `radius` cells of the center along each axis
+    CenterCluster { radius: i32, amount: usize },
+    /// Shell of a ball around the grid center, radius a fraction of half the grid size, `thickness` cells thick
+    HollowSphere { radius: f32, thickness: f32 },
+    /// Ball around the grid center, radius a fraction of half the grid size
+    SolidSphere { radius: f32 },
+    /// One cell thick layer across `axis` through the center, each cell alive with chance `density`
+    Plane { axis: Axis, density: f32 },
+    /// One living cell in the center
+    SingleCell,
+    /// A `CenterCluster` at each corner of a cube half the grid size around the center
+    CornerClusters { radius: i32, amount: usize },
+    /// Every cell alive with chance `density`
+    RandomFill { density: f32 },
+    /// Exactly `fraction` of the cells in `region` alive, see `Grid::seed_density`
+    Density { region: Region, fraction: f32 },
+    /// Cells alive where Perlin noise exceeds `threshold` (noise is roughly -1..1, 0.0 fills about half),
+    /// giving organic blobs. `frequency` is the number of noise features per grid side, each of the
+    /// `octaves` adds finer detail at twice the frequency and half the strength. Tiles across wrapping edges
+    Noise { frequency: u32, octaves: u32, threshold: f32 },
+    /// `pattern` plus its mirrored and rotated copies, so deterministic rules grow symmetric shapes
+    Symmetric { pattern: Box<SeedPattern>, symmetry: Symmetry },
+}
+
+/// Copies that make a seed symmetric, all taken around the grid center
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Symmetry {
+    /// Axes to mirror across, each doubling the copies
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirror: Vec<Axis>,
+    /// Axis for 4-fold rotational symmetry: every copy also appears turned by each quarter turn around it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate: Option<Axis>,
+}
+
+impl Symmetry {
+    /// `pos` and all its copies in a grid of `size` cells per side, repeats included
+    pub fn images(&self, pos: IVec3, size: i32) -> Vec<IVec3> {
+        let last = size - 1;
+        let mut images = vec![pos];
+        for axis in &self.mirror {
+            for i in 0..images.len() {
+                let mut image = images[i];
+                match axis {
+                    Axis::X => image.x = last - image.x,
+                    Axis::Y => image.y = last - image.y,
+                    Axis::Z => image.z = last - image.z,
+                }
+                images.push(image);
+            }
+        }
+        if let Some(axis) = self.rotate {
+            for i in 0..images.len() {
+                let mut image = images[i];
+                for _ in 0..3 {
+                    image = match axis {
+                        Axis::X => IVec3::new(image.x, last - image.z, image.y),
+                        Axis::Y => IVec3::new(last - image.z, image.y, image.x),
+                        Axis::Z => IVec3::new(last - image.y, image.x, image.z),
+                    };
+                    images.push(image);
+                }
+            }
+        }
+        images
+    }
+}
+
+impl Default for SeedPattern {
+    fn default() -> Self {
+        SeedPattern::CenterCluster { radius: 6, amount: 12 * 12 * 12 }
+    }
+}
+
+impl SeedPattern {
+    /// Hand every position of the pattern in a grid of `size` cells per side to `place`
+    /// Cluster positions may fall past the edges and repeat, the grid wraps and skips them
+    pub fn place(&self, size: i32, rng: &mut impl Rng, mut place: impl FnMut(IVec3)) {
+        let center = Vec3::splat((size - 1) as f32 * 0.5);
+        let half = size as f32 * 0.5;
+        let mut cluster = |middle: IVec3, radius: i32, amount: usize, rng: &mut dyn rand::RngCore| {
+            for _ in 0..amount {
+                place(middle + IVec3::new(
+                    rng.random_range(-radius..=radius),
+                    rng.random_range(-radius..=radius),
+                    rng.random_range(-radius..=radius),
+                ));
+            }
+        };
+        match self {
+            SeedPattern::CenterCluster { radius, amount } => cluster(IVec3::splat(size / 2), *radius, *amount, rng),
+            SeedPattern::CornerClusters { radius, amount } => {
+                for corner in 0..8 {
+                    let quarter = |bit: i32| if corner >> bit & 1 == 0 { size / 4 } else { size * 3 / 4 };
+                    cluster(IVec3::new(quarter(0), quarter(1), quarter(2)), *radius, *amount, rng);
+                }
+            }
+            SeedPattern::HollowSphere { radius, thickness } => {
+                let (radius, thickness) = (radius * half, thickness * 0.5);
+                for_each_pos(size, |pos| {
+                    if (pos.as_vec3().distance(center) - radius).abs() <= thickness {
+                        place(pos);
+                    }
+                });
+            }
+            SeedPattern::SolidSphere { radius } => {
+                for_each_pos(size, |pos| {
+                    if pos.as_vec3().distance(center) <= radius * half {
+                        place(pos);
+                    }
+                });
+            }
+            SeedPattern::Plane { axis, density } => {
+                let layer = size / 2;
+                for u in 0..size {
+                    for v in 0..size {
+                        if rng.random_bool(density.clamp(0.0, 1.0) as f64) {
+                            place(match axis {
+                                Axis::X => IVec3::new(layer, u, v),
+                                Axis::Y => IVec3::new(u, layer, v),
+                                Axis::Z => IVec3::new(u, v, layer),
+                            });
+                        }
+                    }
+                }
+            }
+            SeedPattern::SingleCell => place(IVec3::splat(size / 2)),
+            SeedPattern::Noise { frequency, octaves, threshold } => {
+                let noise = Perlin::new(rng);
+                for_each_pos(size, |pos| {
+                    let point = (pos.as_vec3() + 0.5) / size as f32;
+                    if noise.octaves(point, (*frequency).max(1), (*octaves).max(1)) > *threshold {
+                        place(pos);
+                    }
+                });
+            }
+            SeedPattern::Symmetric { pattern, symmetry } => {
+                // Copies are taken inside the grid, so cluster cells past an edge wrap first
+                let mut copies = |pos: IVec3| {
+                    for image in symmetry.images(pos.rem_euclid(IVec3::splat(size)), size) {
+                        place(image);
+                    }
+                };
+                // Behind dyn so nested patterns don't instantiate `place` forever
+                pattern.place(size, rng, &mut copies as &mut dyn FnMut(IVec3));
+            }
+            SeedPattern::Density { region, fraction } => {
+                let mut positions = Vec::new();
+                for_each_pos(size, |pos| {
+                    if region.contains(pos, size) {
+                        positions.push(pos);
+                    }
+                });
+                let amount = (positions.len() as f32 * fraction.clamp(0.0, 1.0)).round() as usize;
+                for chosen in rand::seq::index::sample(rng, positions.len(), amount) {
+                    place(positions[chosen]);
+                }
+            }
+            SeedPattern::RandomFill { density } => {
+                let density = density.clamp(0.0, 1.0) as f64;
+                for_each_pos(size, |pos| {
+                    if rng.random_bool(density) {
+                        place(pos);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Picture extruded into the grid as starting cells, so photos and logos can seed a run
+/// The image is stretched over the x-y face (top row at the top) and extruded along z towards the camera,
+/// in a slab `depth` cells deep through the grid center
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ImageSeed {
+    /// Image file, any format Bevy can decode (PNG out of the box)
+    pub path: PathBuf,
+    pub depth: i32,
+    /// Brightness is each slab cell's chance to be alive instead of how deep its pixel is extruded
+    #[serde(default)]
+    pub density: bool,
+}
+
+impl ImageSeed {
+    /// Load the image and bring its cells to life at their zone rule's top state
+    pub fn seed(&self, grid: &mut Grid, rules: &[Rule], rng: &mut impl Rng) -> io::Result<()> {
+        let map = Heightmap::load(&self.path)?;
+        let size = grid.size;
+        let depth = self.depth.clamp(1, size);
+        let front = (size - depth) / 2;
+        grid.seed_zoned_with(rules, |pos| {
+            let layer = pos.z - front;
+            if !(0..depth).contains(&layer) {
+                return 0;
+            }
+            let brightness = map.brightness((pos.x as f32 + 0.5) / size as f32, 1.0 - (pos.y as f32 + 0.5) / size as f32);
+            let alive = if self.density {
+                rng.random_bool(brightness as f64)
+            } else {
+                // Columns grow from the back of the slab, so bright pixels stand out towards the camera
+                layer >= depth - (brightness * depth as f32).round() as i32
+            };
+            if alive { u8::MAX } else { 0 }
+        });
+        Ok(())
+    }
+}
+
+/// Brightness of every pixel of an image, 0 black or transparent to 1 white
+pub struct Heightmap {
+    width: u32,
+    height: u32,
+    brightness: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Decode the image at `path`, its format picked by extension
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        let image = Image::from_buffer(
+            &bytes,
+            ImageType::Extension(extension),
+            CompressedImageFormats::NONE,
+            false,
+            ImageSampler::Default,
+            RenderAssetUsages::default(),
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let (width, height) = (image.width(), image.height());
+        let mut brightness = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let color = image
+                    .get_color_at(x, y)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+                    .to_linear();
+                // Luma of the stored values, faded out by transparency so logos on clear backgrounds stay clean
+                brightness.push((0.299 * color.red + 0.587 * color.green + 0.114 * color.blue) * color.alpha);
+            }
+        }
+        Ok(Self { width, height, brightness })
+    }
+
+    /// Brightness of the pixel at `u` across and `v` down the image, both 0..1
+    pub fn brightness(&self, u: f32, v: f32) -> f32 {
+        let x = ((u * self.width as f32) as u32).min(self.width - 1);
+        let y = ((v * self.height as f32) as u32).min(self.height - 1);
+        self.brightness[(y * self.width + x) as usize].clamp(0.0, 1.0)
+    }
+}
+
+/// Gradient noise on a lattice shuffled by an RNG, so each seed gives a different field
+struct Perlin {
+    permutation: [u8; 256],
+}
+
+impl Perlin {
+    fn new(rng: &mut impl Rng) -> Self {
+        let mut permutation = [0; 256];
+        for (i, entry) in permutation.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+        permutation.shuffle(rng);
+        Self { permutation }
+    }
+
+    /// Sum of `octaves` layers of noise over the unit cube at `point`, the first with `frequency`
+    /// lattice cells per side, normalized back to roughly -1..1
+    fn octaves(&self, point: Vec3, frequency: u32, octaves: u32) -> f32 {
+        let (mut sum, mut strength, mut total) = (0.0, 1.0, 0.0);
+        for octave in 0..octaves.min(16) {
+            let period = frequency << octave;
+            sum += strength * self.sample(point * period as f32, period);
+            total += strength;
+            strength *= 0.5;
+        }
+        sum / total
+    }
+
+    /// Noise at `point` on a lattice repeating every `period` cells
+    fn sample(&self, point: Vec3, period: u32) -> f32 {
+        let base = point.floor();
+        let local = point - base;
+        let fade = local * local * local * (local * (local * 6.0 - 15.0) + 10.0);
+        let corner = |offset: IVec3| {
+            let lattice = (base.as_ivec3() + offset).rem_euclid(IVec3::splat(period as i32));
+            let hash = [lattice.x, lattice.y, lattice.z]
+                .iter()
+                .fold(0u8, |hash, &coord| self.permutation[(hash as usize + coord as usize) & 255]);
+            gradient(hash, local - offset.as_vec3())
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let x = |y: i32, z: i32| lerp(corner(IVec3::new(0, y, z)), corner(IVec3::new(1, y, z)), fade.x);
+        let y = |z: i32| lerp(x(0, z), x(1, z), fade.y);
+        lerp(y(0), y(1), fade.z)
+    }
+}
+
+/// Dot product of `offset` with one of Perlin's 12 edge gradients picked by `hash`
+fn gradient(hash: u8, offset: Vec3) -> f32 {
+    let (u, v) = match hash % 12 {
+        0..4 => (offset.x, offset.y),
+        4..8 => (offset.x, offset.z),
+        _ => (offset.y, offset.z),
+    };
+    let u = if hash & 1 == 0 { u } else { -u };
+    let v = if hash & 2 == 0 { v } else { -v };
+    u + v
+}
+
+/// Every position of a grid of `size` cells per side
+fn for_each_pos(size: i32, mut f: impl FnMut(IVec3)) {
+    for z in 0..size {
+        for y in 0..size {
+            for x in 0..size {
+                f(IVec3::new(x, y, z));
+            }
+        }
+    }
+}
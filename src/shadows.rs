@@ -0,0 +1,31 @@
+use bevy::light::CascadeShadowConfigBuilder;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Directional light the cells cast shadows from and onto each other, darkening the faces turned away from
+/// it and the ones under overhangs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CellShadows {
+    /// Direction the light shines in
+    pub direction: Vec3,
+    /// Distance from the camera shadows reach, best covering the whole grid
+    pub distance: f32,
+}
+
+impl Default for CellShadows {
+    fn default() -> Self {
+        Self { direction: Vec3::new(-0.4, -1.0, -0.3), distance: 300.0 }
+    }
+}
+
+impl CellShadows {
+    /// Light to spawn, which the cell shader and the shadow pass of the instances both read
+    pub fn light(&self) -> impl Bundle {
+        (
+            DirectionalLight { shadows_enabled: true, ..default() },
+            Transform::default().looking_to(self.direction, Vec3::Y),
+            CascadeShadowConfigBuilder { maximum_distance: self.distance, ..default() }.build(),
+        )
+    }
+}
@@ -0,0 +1,247 @@
+use bevy::asset::{LoadState, RenderAssetUsages};
+use bevy::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::rendering::InstanceMaterialData;
+
+/// Shape every cell is drawn as, one unit across
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum CellMeshKind {
+    #[default]
+    Cube,
+    UvSphere,
+    Icosphere,
+    Octahedron,
+    /// Cube with its edges and corners rounded off
+    RoundedCube,
+    /// A single pixel at each cell's center, cheap enough for tens of millions of cells. Cells don't
+    /// shrink with their scale and don't hide each other's outlines
+    Point,
+}
+
+impl CellMeshKind {
+    pub const ALL: [CellMeshKind; 6] = [
+        CellMeshKind::Cube,
+        CellMeshKind::UvSphere,
+        CellMeshKind::Icosphere,
+        CellMeshKind::Octahedron,
+        CellMeshKind::RoundedCube,
+        CellMeshKind::Point,
+    ];
+
+    /// The shape after this one, back to the cube after the last
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&kind| kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Mesh of the shape, centered on the origin. Spheres stay low-poly, there can be millions of them
+    pub fn mesh(self) -> Mesh {
+        match self {
+            CellMeshKind::Cube => Cuboid::new(1.0, 1.0, 1.0).into(),
+            CellMeshKind::UvSphere => Sphere::new(0.5).mesh().uv(12, 8),
+            CellMeshKind::Icosphere => Sphere::new(0.5).mesh().ico(1).expect("a low subdivision count"),
+            CellMeshKind::Octahedron => octahedron(),
+            CellMeshKind::RoundedCube => rounded_cube(0.15, 3),
+            CellMeshKind::Point => point(),
+        }
+    }
+}
+
+/// Octahedron with its corners half a unit out along each axis and flat shaded faces
+fn octahedron() -> Mesh {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    for octant in 0..8 {
+        let sign = Vec3::new(
+            if octant & 1 == 0 { 1.0 } else { -1.0 },
+            if octant & 2 == 0 { 1.0 } else { -1.0 },
+            if octant & 4 == 0 { 1.0 } else { -1.0 },
+        );
+        let corners = [Vec3::X * sign.x * 0.5, Vec3::Y * sign.y * 0.5, Vec3::Z * sign.z * 0.5];
+        // Counter-clockwise seen from outside, which flips in octants with an odd number of negative axes
+        let order = if sign.x * sign.y * sign.z > 0.0 { [0, 1, 2] } else { [0, 2, 1] };
+        for corner in order {
+            positions.push(corners[corner].to_array());
+            normals.push(sign.normalize().to_array());
+        }
+    }
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
+
+/// One point at the origin, with the normal and UV the cell shader's vertex layout expects
+fn point() -> Mesh {
+    Mesh::new(PrimitiveTopology::PointList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0f32, 1.0, 0.0]])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0f32; 2]])
+}
+
+/// Unit cube with edges rounded to `radius`, each face split into a grid of `2 * segments + 1` squares
+/// per side whose outer rows bend around the edges
+fn rounded_cube(radius: f32, segments: u32) -> Mesh {
+    let inner = 0.5 - radius;
+    let side = 2 * segments + 2; // Vertices per face side
+    // Face grid coordinate from -0.5 to 0.5: the middle square spans the flat part, the rest the rounding
+    let coordinate = |step: u32| match step {
+        step if step <= segments => -0.5 + radius * step as f32 / segments as f32,
+        step => 0.5 - radius * (side - 1 - step) as f32 / segments as f32,
+    };
+    let faces = [
+        (Vec3::X, Vec3::Y, Vec3::Z),
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+        (Vec3::Y, Vec3::Z, Vec3::X),
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+        (Vec3::Z, Vec3::X, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::Y, Vec3::X),
+    ];
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    for (normal, u_axis, v_axis) in faces {
+        let start = positions.len() as u32;
+        for v in 0..side {
+            for u in 0..side {
+                let point = normal * 0.5 + u_axis * coordinate(u) + v_axis * coordinate(v);
+                // Points past the flat inner box are pushed out onto the rounding
+                let core = point.clamp(Vec3::splat(-inner), Vec3::splat(inner));
+                let outward = (point - core).normalize_or(normal);
+                positions.push((core + outward * radius).to_array());
+                normals.push(outward.to_array());
+                uvs.push([u as f32 / (side - 1) as f32, v as f32 / (side - 1) as f32]);
+            }
+        }
+        for v in 0..side - 1 {
+            for u in 0..side - 1 {
+                let corner = start + v * side + u;
+                indices.extend([corner, corner + 1, corner + side + 1, corner, corner + side + 1, corner + side]);
+            }
+        }
+    }
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+/// Mesh from the assets folder every cell is drawn as instead of the built-in shapes, e.g. crystals or
+/// low-poly rocks
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CustomCellMesh {
+    /// OBJ file or glTF primitive (e.g. "models/rock.glb#Mesh0/Primitive0"), relative to the assets folder
+    pub path: String,
+    /// Length of the mesh's longest side in cells, 1 to fill a cell
+    #[serde(default = "one")]
+    pub size: f32,
+}
+
+/// Mesh the asset server is still loading to draw the cells as, any `Handle<Mesh>` will do
+#[derive(Resource)]
+pub struct PendingCellMesh {
+    pub handle: Handle<Mesh>,
+    /// Length of the mesh's longest side in cells
+    pub size: f32,
+}
+
+impl PendingCellMesh {
+    pub fn load(mesh: &CustomCellMesh, asset_server: &AssetServer) -> Self {
+        Self { handle: asset_server.load(&mesh.path), size: mesh.size }
+    }
+}
+
+/// Marks cells drawn as a custom mesh rather than their `CellMeshKind`, until K picks a shape again
+#[derive(Resource)]
+pub struct CustomCellMeshDrawn;
+
+/// Copy of a triangle list `mesh` centered on the origin with its longest side `size` long, keeping only
+/// the positions, normals and UVs the cell shader reads (flat normals and zero UVs when it has none)
+pub fn fit_mesh(mesh: &Mesh, size: f32) -> Option<Mesh> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let mut mesh = mesh.clone();
+    if mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_none() {
+        mesh.duplicate_vertices();
+        mesh.compute_flat_normals();
+    }
+    let positions: Vec<Vec3> = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?.iter().map(|&p| Vec3::from(p)).collect();
+    let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL)? {
+        VertexAttributeValues::Float32x3(normals) => normals.clone(),
+        _ => return None,
+    };
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) => uvs.clone(),
+        _ => vec![[0.0, 0.0]; positions.len()],
+    };
+
+    let min = positions.iter().copied().reduce(Vec3::min)?;
+    let max = positions.iter().copied().reduce(Vec3::max)?;
+    let center = (min + max) * 0.5;
+    let scale = size / (max - min).max_element().max(f32::EPSILON);
+    let positions: Vec<[f32; 3]> = positions.iter().map(|&p| ((p - center) * scale).to_array()).collect();
+
+    let mut fitted = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    if let Some(indices) = mesh.indices() {
+        fitted.insert_indices(Indices::U32(indices.iter().map(|i| i as u32).collect()));
+    }
+    Some(fitted)
+}
+
+/// Draw the cells as the pending mesh once it's loaded, keeping the current shape if it fails
+pub fn use_cell_mesh(
+    mut commands: Commands,
+    pending: Res<PendingCellMesh>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut cells_query: Query<&mut Mesh3d, With<InstanceMaterialData>>,
+) {
+    if let Some(LoadState::Failed(error)) = asset_server.get_load_state(pending.handle.id()) {
+        eprintln!("Failed to load the cell mesh: {}", error);
+        commands.remove_resource::<PendingCellMesh>();
+        return;
+    }
+    let Some(mesh) = meshes.get(&pending.handle) else { return };
+    commands.remove_resource::<PendingCellMesh>();
+    let Some(fitted) = fit_mesh(mesh, pending.size) else {
+        eprintln!("The cell mesh has no triangles to draw");
+        return;
+    };
+    let handle = meshes.add(fitted);
+    for mut cells in &mut cells_query {
+        cells.0 = handle.clone();
+    }
+    commands.insert_resource(CustomCellMeshDrawn);
+}
+
+/// Press K to cycle through the cell shapes
+pub fn cycle_cell_mesh(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut kind: ResMut<CellMeshKind>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut cells_query: Query<&mut Mesh3d, With<InstanceMaterialData>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyK) {
+        return;
+    }
+    *kind = kind.next();
+    commands.remove_resource::<CustomCellMeshDrawn>();
+    let mesh = meshes.add(kind.mesh());
+    for mut cells in &mut cells_query {
+        cells.0 = mesh.clone();
+    }
+    println!("Cells drawn as {:?}", *kind);
+}
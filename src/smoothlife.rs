@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::continuous::{ContinuousGrid, Kernel, Simulation};
+
+/// SmoothLife: a continuous Game of Life where each cell compares the filling of its inner ball
+/// (how alive it is) with the filling of the surrounding shell (its neighbors)
+/// See Rafler, "Generalization of Conway's Game of Life to a continuous domain - SmoothLife"
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SmoothLifeRule {
+    /// Radius of the inner ball in cells
+    pub inner_radius: i32,
+    /// Outer radius of the neighbor shell in cells
+    pub outer_radius: i32,
+    /// Shell filling range (min, max) in which a dead cell is born
+    pub birth: (f32, f32),
+    /// Shell filling range (min, max) in which a living cell survives
+    pub survival: (f32, f32),
+    /// Smoothness of the birth/survival interval edges
+    pub alpha_n: f32,
+    /// Smoothness of the dead/alive transition of the inner filling
+    pub alpha_m: f32,
+    /// Fraction of the way each cell moves towards its transition value per step (1.0 = replace)
+    pub dt: f32,
+}
+
+impl SmoothLifeRule {
+    /// Wobbling smooth blobs (inner radius 2, shell radius 6)
+    pub fn blobs() -> Self {
+        Self {
+            inner_radius: 2,
+            outer_radius: 6,
+            birth: (0.18, 0.28),
+            survival: (0.16, 0.39),
+            alpha_n: 0.028,
+            alpha_m: 0.147,
+            dt: 0.3,
+        }
+    }
+
+    /// Transition value (0..1) for a cell with shell filling `n` and inner filling `m`
+    pub fn transition(&self, n: f32, m: f32) -> f32 {
+        let alive = sigmoid(m, 0.5, self.alpha_m);
+        let low = self.birth.0 * (1.0 - alive) + self.survival.0 * alive;
+        let high = self.birth.1 * (1.0 - alive) + self.survival.1 * alive;
+        sigmoid(n, low, self.alpha_n) * (1.0 - sigmoid(n, high, self.alpha_n))
+    }
+}
+
+impl Default for SmoothLifeRule {
+    fn default() -> Self {
+        Self::blobs()
+    }
+}
+
+/// Smooth step from 0 to 1 around `a`, `alpha` wide
+#[inline]
+fn sigmoid(x: f32, a: f32, alpha: f32) -> f32 {
+    1.0 / (1.0 + (-(x - a) * 4.0 / alpha).exp())
+}
+
+/// The active SmoothLife rule with its inner ball and shell kernels built once
+#[derive(Resource, Clone, Debug)]
+pub struct SmoothLife {
+    pub rule: SmoothLifeRule,
+    inner: Kernel,
+    shell: Kernel,
+}
+
+impl SmoothLife {
+    pub fn new(rule: SmoothLifeRule) -> Self {
+        let inner = Kernel::from_fn(rule.inner_radius, |_| 1.0);
+        let ratio = rule.inner_radius as f32 / rule.outer_radius.max(1) as f32;
+        let shell = Kernel::from_fn(rule.outer_radius, |distance| if distance > ratio { 1.0 } else { 0.0 });
+        Self { rule, inner, shell }
+    }
+}
+
+impl Simulation for SmoothLife {
+    /// Every cell moves dt of the way towards transition(shell filling, inner filling)
+    fn step(&self, grid: &mut ContinuousGrid) {
+        let m = grid.convolve(&self.inner);
+        let n = grid.convolve(&self.shell);
+        grid.update(|index, value| value + self.rule.dt * (self.rule.transition(n[index], m[index]) - value));
+    }
+
+    fn kernel_size(&self) -> usize {
+        self.inner.offsets().len() + self.shell.offsets().len()
+    }
+}
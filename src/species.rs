@@ -0,0 +1,80 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use crate::rule::{Rule, MAX_NEIGHBORS};
+
+/// Most species one grid can hold (species ids are stored per cell as a u8)
+pub const MAX_SPECIES: usize = u8::MAX as usize + 1;
+
+/// A cell species with its own rule, sharing the grid with other species
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Species {
+    pub name: String,
+    pub rule: Rule,
+}
+
+/// Several species living in one grid
+/// Every living cell belongs to one species, which decides its birth/survival rule, state count
+/// and neighborhood. Each species sees an interaction-weighted mix of all species' neighbor counts
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Ecosystem {
+    pub species: Vec<Species>,
+    /// interactions[a][b]: what one neighbor of species a adds to species b's neighbor count
+    /// Missing entries are 1.0 on the diagonal and 0.0 elsewhere, negative values inhibit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interactions: Vec<Vec<f32>>,
+}
+
+impl Ecosystem {
+    /// Independent species that only compete for empty cells
+    pub fn new(species: impl IntoIterator<Item = (String, Rule)>) -> Self {
+        let species: Vec<Species> = species
+            .into_iter()
+            .map(|(name, rule)| Species { name, rule })
+            .collect();
+        assert!(species.len() <= MAX_SPECIES, "at most {} species are supported", MAX_SPECIES);
+        Self { species, interactions: Vec::new() }
+    }
+
+    /// Make each neighbor of species `from` add `weight` to species `to`'s neighbor count
+    /// Example: `with_interaction(0, 1, -1.0)` lets A's neighbors inhibit B's birth and survival
+    pub fn with_interaction(mut self, from: usize, to: usize, weight: f32) -> Self {
+        let count = self.species.len();
+        // Expand to a full matrix so missing entries keep their defaults
+        self.interactions.resize_with(count, Vec::new);
+        for (a, row) in self.interactions.iter_mut().enumerate() {
+            let len = row.len();
+            row.extend((len..count).map(|b| if a == b { 1.0 } else { 0.0 }));
+        }
+        self.interactions[from][to] = weight;
+        self
+    }
+
+    /// What one neighbor of species `from` adds to species `to`'s neighbor count
+    #[inline]
+    pub fn interaction(&self, from: usize, to: usize) -> f32 {
+        match self.interactions.get(from).and_then(|row| row.get(to)) {
+            Some(&weight) => weight,
+            None if from == to => 1.0,
+            None => 0.0,
+        }
+    }
+
+    /// Neighbor count species `to` sees, given the (weighted) count of each species around a cell
+    #[inline]
+    pub fn effective_count(&self, to: usize, counts: &[u16]) -> u16 {
+        if self.interactions.is_empty() {
+            return counts[to];
+        }
+        let total: f32 = counts
+            .iter()
+            .enumerate()
+            .map(|(from, &count)| count as f32 * self.interaction(from, to))
+            .sum();
+        total.round().clamp(0.0, MAX_NEIGHBORS as f32) as u16
+    }
+
+    /// Highest state count of all species, the state that renders as fully alive
+    pub fn max_states(&self) -> u8 {
+        self.species.iter().map(|species| species.rule.states).max().unwrap_or(1)
+    }
+}
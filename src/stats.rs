@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::components::Components;
+use crate::grid::Grid;
+use crate::structure::Structure;
+
+/// What one generation of a run looked like
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub generation: u64,
+    /// Living cells
+    pub population: usize,
+    /// Cells that came alive since the generation before
+    pub births: usize,
+    /// Cells that died since the generation before
+    pub deaths: usize,
+    /// Living cells at the highest state, with decaying rules the ones still counting as neighbors
+    pub max_state: usize,
+    /// Disconnected living structures (see `Components`)
+    pub components: usize,
+    /// Living cells of the largest structure
+    pub largest_component: usize,
+    /// Entropy, surface and surface-to-volume ratio of the living cells (see `Structure`)
+    pub entropy: f32,
+    pub surface: usize,
+    pub surface_to_volume: f32,
+    /// Lowest and highest corner of the box around the living cells, None once they're gone
+    pub bounds: Option<(IVec3, IVec3)>,
+    /// Average position of the living cells, None once they're gone
+    pub centroid: Option<Vec3>,
+}
+
+/// Per-generation numbers of the run, for graphs, exports and automated analysis
+/// The oldest generations are dropped past the limit
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimStats {
+    /// Most generations kept
+    pub limit: usize,
+    #[serde(skip)]
+    recorded: VecDeque<GenerationStats>,
+    /// States of the last recorded generation in position order (see `Grid::states`), to count births
+    /// and deaths against
+    #[serde(skip)]
+    previous: Vec<u8>,
+    /// Living cells of each structure of the last recorded generation
+    #[serde(skip)]
+    component_sizes: Vec<usize>,
+}
+
+impl Default for SimStats {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+impl SimStats {
+    pub fn new(limit: usize) -> Self {
+        Self { limit, recorded: VecDeque::new(), previous: Vec::new(), component_sizes: Vec::new() }
+    }
+
+    /// Recorded generations, oldest first
+    pub fn generations(&self) -> impl DoubleEndedIterator<Item = &GenerationStats> + ExactSizeIterator + '_ {
+        self.recorded.iter()
+    }
+
+    /// Newest recorded generation
+    pub fn latest(&self) -> Option<&GenerationStats> {
+        self.recorded.back()
+    }
+
+    /// Living cells of each disconnected structure of the newest recorded generation
+    pub fn component_sizes(&self) -> &[usize] {
+        &self.component_sizes
+    }
+
+    /// Drop every recorded generation
+    pub fn clear(&mut self) {
+        self.recorded.clear();
+        self.previous.clear();
+        self.component_sizes.clear();
+    }
+
+    /// Record the grid's generation unless it's recorded already, `max_state` being the highest state of
+    /// the active mode. Going back in generations, e.g. loading a snapshot, starts over, and the first
+    /// generation recorded counts every living cell as born
+    pub fn record(&mut self, grid: &Grid, max_state: u8) {
+        let generation = grid.generation();
+        match self.recorded.back() {
+            Some(newest) if generation < newest.generation => self.clear(),
+            Some(newest) if generation == newest.generation => return,
+            _ => {}
+        }
+
+        let states = grid.states();
+        if self.previous.len() != states.len() {
+            self.previous = vec![0; states.len()];
+        }
+        let components = Components::from_states(&states, grid.size, grid.boundaries());
+        let structure = Structure::from_states(&states, grid.size, grid.boundaries());
+        let mut stats = GenerationStats {
+            generation,
+            population: grid.population(),
+            births: 0,
+            deaths: 0,
+            max_state: 0,
+            components: components.count(),
+            largest_component: components.largest(),
+            entropy: structure.entropy,
+            surface: structure.surface,
+            surface_to_volume: structure.surface_to_volume,
+            bounds: grid.bounds(),
+            centroid: grid.centroid(),
+        };
+        for (&now, &before) in states.iter().zip(&self.previous) {
+            match (before, now) {
+                (0, 0) => {}
+                (_, 0) => stats.deaths += 1,
+                (0, _) => stats.births += 1,
+                _ => {}
+            }
+            if now > 0 && now >= max_state {
+                stats.max_state += 1;
+            }
+        }
+
+        self.previous = states;
+        self.component_sizes = components.sizes().to_vec();
+        self.recorded.push_back(stats);
+        while self.recorded.len() > self.limit.max(1) {
+            self.recorded.pop_front();
+        }
+    }
+}
+
+/// Streams the generations `SimStats` records to a file for external tools like pandas or gnuplot
+/// A `.csv` file gets a header row and one row per generation, a `.json` file one object per line
+/// (JSON Lines, e.g. `pandas.read_json(path, lines=True)`), so a run stopped at any point leaves a readable file
+#[derive(Resource)]
+pub struct StatsExport {
+    file: BufWriter<File>,
+    csv: bool,
+    /// Last generation written
+    written: Option<u64>,
+}
+
+impl StatsExport {
+    /// Create the file, its format picked by the extension
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let csv = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => true,
+            Some("json" | "jsonl") => false,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "stats export needs a .csv or .json file")),
+        };
+        let mut file = BufWriter::new(File::create(path)?);
+        if csv {
+            writeln!(file, "generation,population,births,deaths,max_state,components,largest_component,entropy,surface,surface_to_volume,min_x,min_y,min_z,max_x,max_y,max_z,centroid_x,centroid_y,centroid_z")?;
+            file.flush()?;
+        }
+        Ok(Self { file, csv, written: None })
+    }
+
+    /// Append the generations recorded since the last write. A run going back in generations, e.g. on
+    /// loading a snapshot or starting over, carries on in the same file
+    pub fn write(&mut self, stats: &SimStats) -> io::Result<()> {
+        if stats.latest().zip(self.written).is_some_and(|(latest, written)| latest.generation < written) {
+            self.written = None;
+        }
+        let written = self.written;
+        for generation in stats.generations().filter(|generation| written.is_none_or(|written| generation.generation > written)) {
+            if self.csv {
+                let (min, max) = match generation.bounds {
+                    Some((min, max)) => (format!("{},{},{}", min.x, min.y, min.z), format!("{},{},{}", max.x, max.y, max.z)),
+                    None => (",,".to_string(), ",,".to_string()),
+                };
+                let centroid = generation.centroid.map_or(",,".to_string(), |centroid| format!("{},{},{}", centroid.x, centroid.y, centroid.z));
+                writeln!(
+                    self.file,
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                    generation.generation,
+                    generation.population,
+                    generation.births,
+                    generation.deaths,
+                    generation.max_state,
+                    generation.components,
+                    generation.largest_component,
+                    generation.entropy,
+                    generation.surface,
+                    generation.surface_to_volume,
+                    min,
+                    max,
+                    centroid
+                )?;
+            } else {
+                serde_json::to_writer(&mut self.file, generation)?;
+                writeln!(self.file)?;
+            }
+            self.written = Some(generation.generation);
+        }
+        self.file.flush()
+    }
+}
+
+/// Write the generations the simulation reached this frame to the stats file
+pub fn export_stats(mut export: ResMut<StatsExport>, stats: Res<SimStats>, mut commands: Commands) {
+    if let Err(e) = export.write(&stats) {
+        eprintln!("Failed to write stats, stopping the export: {}", e);
+        commands.remove_resource::<StatsExport>();
+    }
+}
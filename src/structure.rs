@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use crate::grid::{Boundaries, Grid};
+use crate::rule::VON_NEUMANN_NEIGHBORS;
+
+/// Quantitative signature of the living cells' shape: orderly crystals have low entropy and little
+/// surface for their volume, clouds of scattered cells a lot of both
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Structure {
+    /// Shannon entropy of the 2x2x2 block patterns tiling the grid, from 0 (every block alike) to 1
+    /// (all 256 patterns equally likely)
+    pub entropy: f32,
+    /// Faces of living cells that touch a dead cell or a dead edge
+    pub surface: usize,
+    /// Exposed faces per living cell, from about 0 for a large solid block to 6 for lone cells
+    pub surface_to_volume: f32,
+}
+
+impl Structure {
+    /// Measure the grid's living cells
+    pub fn measure(grid: &Grid) -> Self {
+        Self::from_states(&grid.states(), grid.size, grid.boundaries())
+    }
+
+    /// Measure the living cells of `states` in position order of a grid of `size` with `boundaries`
+    pub fn from_states(states: &[u8], size: i32, boundaries: Boundaries) -> Self {
+        let side = size as usize;
+        let alive = |pos: IVec3| {
+            match (boundaries.x.resolve(pos.x, size), boundaries.y.resolve(pos.y, size), boundaries.z.resolve(pos.z, size)) {
+                (Some(x), Some(y), Some(z)) => states[x as usize + y as usize * side + z as usize * side * side] != 0,
+                _ => false,
+            }
+        };
+
+        let mut population = 0;
+        let mut surface = 0;
+        for (index, _) in states.iter().enumerate().filter(|(_, &state)| state != 0) {
+            let pos = IVec3::new((index % side) as i32, (index / side % side) as i32, (index / side / side) as i32);
+            population += 1;
+            surface += VON_NEUMANN_NEIGHBORS.iter().filter(|&&face| !alive(pos + face)).count();
+        }
+
+        // Odd grids leave their last layer out of the blocks
+        let blocks = size / 2;
+        let mut patterns = [0usize; 256];
+        for z in 0..blocks {
+            for y in 0..blocks {
+                for x in 0..blocks {
+                    let corner = IVec3::new(x, y, z) * 2;
+                    let pattern = (0..8).fold(0, |pattern, bit| {
+                        let offset = IVec3::new(bit & 1, bit >> 1 & 1, bit >> 2);
+                        pattern | (alive(corner + offset) as usize) << bit
+                    });
+                    patterns[pattern] += 1;
+                }
+            }
+        }
+        let total = blocks.pow(3) as f32;
+        let entropy = patterns
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f32 / total;
+                p * (1.0 / p).log2()
+            })
+            .sum::<f32>()
+            / 8.0;
+
+        Self {
+            entropy,
+            surface,
+            surface_to_volume: if population > 0 { surface as f32 / population as f32 } else { 0.0 },
+        }
+    }
+}
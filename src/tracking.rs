@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use crate::grid::Grid;
+
+/// Where the living cells are, taken from the grid's running tally whenever it changes, for camera
+/// targeting, stats and fitting the view. Positions are in cells, see `world_centroid` for where they render
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Default)]
+pub struct LiveRegion {
+    pub population: usize,
+    /// Average position of the living cells
+    pub centroid: Option<Vec3>,
+    /// Lowest and highest corner of the box around the living cells
+    pub bounds: Option<(IVec3, IVec3)>,
+    /// Grid size the positions are in
+    pub size: i32,
+}
+
+impl LiveRegion {
+    pub fn of(grid: &Grid) -> Self {
+        Self { population: grid.population(), centroid: grid.centroid(), bounds: grid.bounds(), size: grid.size }
+    }
+
+    /// Offset from cell positions to where the cells render, around the origin
+    fn world_offset(&self) -> Vec3 {
+        Vec3::splat((self.size - 1) as f32 * 0.5)
+    }
+
+    /// Centroid where the cells render
+    pub fn world_centroid(&self) -> Option<Vec3> {
+        self.centroid.map(|centroid| centroid - self.world_offset())
+    }
+
+    /// Box around the living cells where they render, from the outer faces of the outermost cells
+    pub fn world_bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.bounds.map(|(min, max)| (min.as_vec3() - self.world_offset() - 0.5, max.as_vec3() - self.world_offset() + 0.5))
+    }
+
+    /// Radius of the sphere around `world_bounds`, how far a camera has to back off to fit the cells in
+    pub fn radius(&self) -> f32 {
+        self.world_bounds().map_or(0.0, |(min, max)| (max - min).length() * 0.5)
+    }
+}
+
+/// Refresh the live region after anything changed the grid
+pub fn track_live_region(grid: Res<Grid>, mut region: ResMut<LiveRegion>) {
+    if grid.is_changed() {
+        *region = LiveRegion::of(&grid);
+    }
+}
@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use crate::cyclic::CyclicRule;
+use crate::grid::{max_state, CellColors, Grid};
+use crate::rule::Rule;
+use crate::species::Ecosystem;
+use crate::zones::ZonedRules;
+
+/// Voxels of a MagicaVoxel `.vox` file in grid axes: MagicaVoxel's z up becomes y, its y into the screen -z
+/// Every model in the file is read at the origin, scene transforms are ignored
+pub struct VoxModel {
+    /// Cells per axis of the bounding box of all models
+    pub size: IVec3,
+    /// Position and palette index (1-255) of every voxel
+    pub voxels: Vec<(IVec3, u8)>,
+}
+
+impl VoxModel {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    /// Read the models of a `.vox` file, see MagicaVoxel's file format notes
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        if bytes.len() < 8 || &bytes[0..4] != b"VOX " {
+            return Err(invalid("not a .vox file"));
+        }
+
+        // Chunks are an id, content and children byte counts, then both; models are children of MAIN
+        let mut offset = 8;
+        let (id, content, _) = read_chunk(bytes, &mut offset).ok_or_else(|| invalid("missing MAIN chunk"))?;
+        if id != b"MAIN" {
+            return Err(invalid("missing MAIN chunk"));
+        }
+        offset += content.len();
+
+        let mut models = Vec::new();
+        let mut size = None;
+        while offset < bytes.len() {
+            let (id, content, children) = read_chunk(bytes, &mut offset).ok_or_else(|| invalid("truncated chunk"))?;
+            offset += content.len() + children;
+            match id {
+                b"SIZE" => size = Some(read_ivec3(content).ok_or_else(|| invalid("truncated SIZE chunk"))?),
+                b"XYZI" => {
+                    let model_size = size.take().ok_or_else(|| invalid("XYZI chunk without SIZE"))?;
+                    let count = read_i32(content, 0).ok_or_else(|| invalid("truncated XYZI chunk"))?.max(0) as usize;
+                    let voxels = content.get(4..4 + count * 4).ok_or_else(|| invalid("truncated XYZI chunk"))?;
+                    models.push((model_size, voxels));
+                }
+                _ => {}
+            }
+        }
+
+        let size = models.iter().fold(IVec3::ZERO, |size, (model_size, _)| {
+            size.max(IVec3::new(model_size.x, model_size.z, model_size.y))
+        });
+        let voxels = models
+            .iter()
+            .flat_map(|(_, voxels)| voxels.chunks_exact(4))
+            .map(|voxel| {
+                let (x, y, z) = (voxel[0] as i32, voxel[1] as i32, voxel[2] as i32);
+                (IVec3::new(x, z, size.z - 1 - y), voxel[3])
+            })
+            .collect();
+        Ok(Self { size, voxels })
+    }
+
+    /// Living cells of the grid, each cell's state its palette index
+    pub fn from_grid(grid: &Grid) -> Self {
+        Self { size: IVec3::splat(grid.size), voxels: grid.iter_alive().collect() }
+    }
+
+    /// Write as a `.vox` file, `palette[i]` the RGBA color of palette index `i + 1`
+    pub fn save(&self, path: impl AsRef<Path>, palette: &[[u8; 4]]) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes(palette))
+    }
+
+    /// `.vox` file bytes of a single model, MagicaVoxel models are at most 256 cells per side
+    pub fn to_bytes(&self, palette: &[[u8; 4]]) -> Vec<u8> {
+        let size = self.size.clamp(IVec3::ONE, IVec3::splat(256));
+        let mut xyzi = (self.voxels.len() as i32).to_le_bytes().to_vec();
+        for &(pos, index) in &self.voxels {
+            if pos.cmpge(IVec3::ZERO).all() && pos.cmplt(size).all() {
+                xyzi.extend([pos.x as u8, (size.z - 1 - pos.z) as u8, pos.y as u8, index.max(1)]);
+            }
+        }
+        // Voxels past the size were skipped, so the count is rewritten from what's left
+        let count = (xyzi.len() as i32 - 4) / 4;
+        xyzi[0..4].copy_from_slice(&count.to_le_bytes());
+
+        let mut rgba = Vec::with_capacity(256 * 4);
+        for i in 0..256 {
+            rgba.extend(palette.get(i).copied().unwrap_or([0, 0, 0, 255]));
+        }
+
+        let mut children = Vec::new();
+        write_chunk(&mut children, b"SIZE", &[size.x, size.z, size.y].map(i32::to_le_bytes).concat(), &[]);
+        write_chunk(&mut children, b"XYZI", &xyzi, &[]);
+        write_chunk(&mut children, b"RGBA", &rgba, &[]);
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend(150i32.to_le_bytes());
+        write_chunk(&mut bytes, b"MAIN", &[], &children);
+        bytes
+    }
+
+    /// Bring the model's voxels to life at their zone rule's top state, centered in the grid
+    /// Voxels past the grid edges are left out
+    pub fn seed(&self, grid: &mut Grid, rules: &[Rule]) {
+        let corner = (IVec3::splat(grid.size) - self.size) / 2;
+        let cells: HashSet<IVec3> = self.voxels.iter().map(|(pos, _)| corner + *pos).collect();
+        grid.seed_zoned_with(rules, |pos| if cells.contains(&pos) { u8::MAX } else { 0 });
+    }
+}
+
+/// Press V to save the living cells as `generation_<n>.vox`, colored like the StateLerp color method
+pub fn export_vox(
+    keys: Res<ButtonInput<KeyCode>>,
+    grid: Res<Grid>,
+    rule: Res<Rule>,
+    colors: Res<CellColors>,
+    zones: Option<Res<ZonedRules>>,
+    ecosystem: Option<Res<Ecosystem>>,
+    cyclic: Option<Res<CyclicRule>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let max_state = max_state(rules, ecosystem.as_deref(), cyclic.as_deref());
+    let palette: Vec<[u8; 4]> = (1..=255u8)
+        .map(|state| colors.lerp_color(state.min(max_state) as f32 / max_state as f32).to_srgba().to_u8_array())
+        .collect();
+    let model = VoxModel::from_grid(&grid);
+    let path = format!("generation_{}.vox", grid.generation());
+    match model.save(&path, &palette) {
+        Ok(()) => println!("Saved {} cells to {}", model.voxels.len(), path),
+        Err(e) => eprintln!("Failed to save {}: {}", path, e),
+    }
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    bytes.extend(id);
+    bytes.extend((content.len() as i32).to_le_bytes());
+    bytes.extend((children.len() as i32).to_le_bytes());
+    bytes.extend(content);
+    bytes.extend(children);
+}
+
+/// Id, content and children byte count of the chunk at `offset`, moving it past the chunk header
+fn read_chunk<'a>(bytes: &'a [u8], offset: &mut usize) -> Option<(&'a [u8], &'a [u8], usize)> {
+    let id = bytes.get(*offset..*offset + 4)?;
+    let content_len = read_i32(bytes, *offset + 4)?.max(0) as usize;
+    let children = read_i32(bytes, *offset + 8)?.max(0) as usize;
+    *offset += 12;
+    let content = bytes.get(*offset..*offset + content_len)?;
+    Some((id, content, children))
+}
+
+fn read_i32(bytes: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_ivec3(bytes: &[u8]) -> Option<IVec3> {
+    Some(IVec3::new(read_i32(bytes, 0)?, read_i32(bytes, 4)?, read_i32(bytes, 8)?))
+}
@@ -0,0 +1,346 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, LoadState, RenderAssetUsages};
+use bevy::gltf::{Gltf, GltfMesh, GltfNode};
+use bevy::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io;
+use crate::grid::{Grid, SimRng};
+use crate::reset::AutoReset;
+use crate::rule::Rule;
+use crate::seeding::SeedPattern;
+use crate::vox::VoxModel;
+use crate::zones::ZonedRules;
+
+/// Mesh rasterized into the grid center as starting cells, e.g. coral grown over a bunny
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MeshSeed {
+    /// glTF (.gltf/.glb) or OBJ file, relative to the assets folder
+    pub path: String,
+    /// Cells along the longest side of the mesh
+    pub resolution: i32,
+    /// Fill the inside of closed meshes instead of only the surface
+    #[serde(default)]
+    pub solid: bool,
+}
+
+/// Registers the OBJ loader and seeds the grid from a `PendingMeshSeed` once its meshes are in
+pub struct MeshSeedPlugin;
+
+impl Plugin for MeshSeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset_loader::<ObjLoader>().add_systems(
+            Update,
+            seed_from_mesh.run_if(resource_exists::<PendingMeshSeed>).run_if(resource_exists::<Grid>),
+        );
+    }
+}
+
+/// Mesh seed the asset server is still loading, `fallback` seeds the grid if it fails
+#[derive(Resource)]
+pub struct PendingMeshSeed {
+    seed: MeshSeed,
+    fallback: SeedPattern,
+    source: MeshSource,
+}
+
+enum MeshSource {
+    Gltf(Handle<Gltf>),
+    Mesh(Handle<Mesh>),
+}
+
+impl PendingMeshSeed {
+    pub fn load(seed: MeshSeed, fallback: SeedPattern, asset_server: &AssetServer) -> Self {
+        let source = if seed.path.ends_with(".gltf") || seed.path.ends_with(".glb") {
+            MeshSource::Gltf(asset_server.load(&seed.path))
+        } else {
+            MeshSource::Mesh(asset_server.load(&seed.path))
+        };
+        Self { seed, fallback, source }
+    }
+}
+
+/// Rasterize the pending mesh into the grid once loaded, or seed the fallback pattern if loading failed
+#[allow(clippy::too_many_arguments)]
+pub fn seed_from_mesh(
+    mut commands: Commands,
+    pending: Res<PendingMeshSeed>,
+    asset_server: Res<AssetServer>,
+    gltfs: Res<Assets<Gltf>>,
+    gltf_nodes: Res<Assets<GltfNode>>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    meshes: Res<Assets<Mesh>>,
+    mut grid: ResMut<Grid>,
+    rule: Res<Rule>,
+    zones: Option<Res<ZonedRules>>,
+    mut rng: ResMut<SimRng>,
+    mut auto_reset: Option<ResMut<AutoReset>>,
+) {
+    let rules = zones.as_deref().map_or(std::slice::from_ref(&*rule), |zones| zones.rules.as_slice());
+    let id = match &pending.source {
+        MeshSource::Gltf(handle) => handle.id().untyped(),
+        MeshSource::Mesh(handle) => handle.id().untyped(),
+    };
+    if let Some(LoadState::Failed(error)) = asset_server.get_load_state(id) {
+        eprintln!("Failed to load seed mesh {}: {}, using the seed pattern", pending.seed.path, error);
+        grid.seed_zoned(&pending.fallback, rules, &mut rng.0);
+        if let Some(auto_reset) = auto_reset.as_deref_mut() {
+            auto_reset.remember_cells(&grid);
+        }
+        commands.remove_resource::<PendingMeshSeed>();
+        return;
+    }
+    if !asset_server.is_loaded_with_dependencies(id) {
+        return;
+    }
+
+    let mut triangles = Vec::new();
+    match &pending.source {
+        MeshSource::Mesh(handle) => {
+            if let Some(mesh) = meshes.get(handle) {
+                add_triangles(mesh, &Transform::IDENTITY, &mut triangles);
+            }
+        }
+        MeshSource::Gltf(handle) => {
+            let Some(gltf) = gltfs.get(handle) else { return };
+            // Meshes are placed by their nodes, so walk the node tree from its roots
+            let children: HashSet<AssetId<GltfNode>> =
+                gltf_nodes.iter().flat_map(|(_, node)| node.children.iter().map(Handle::id)).collect();
+            let mut stack: Vec<(Transform, &Handle<GltfNode>)> = gltf
+                .nodes
+                .iter()
+                .filter(|node| !children.contains(&node.id()))
+                .map(|node| (Transform::IDENTITY, node))
+                .collect();
+            while let Some((parent, handle)) = stack.pop() {
+                let Some(node) = gltf_nodes.get(handle) else { continue };
+                let transform = parent.mul_transform(node.transform);
+                let primitives = node.mesh.as_ref().and_then(|mesh| gltf_meshes.get(mesh));
+                for primitive in primitives.iter().flat_map(|mesh| &mesh.primitives) {
+                    if let Some(mesh) = meshes.get(&primitive.mesh) {
+                        add_triangles(mesh, &transform, &mut triangles);
+                    }
+                }
+                stack.extend(node.children.iter().map(|child| (transform, child)));
+            }
+        }
+    }
+
+    let model = voxelize(&triangles, pending.seed.resolution, pending.seed.solid);
+    println!("Seeding {} voxels from {} ({} triangles)", model.voxels.len(), pending.seed.path, triangles.len());
+    model.seed(&mut grid, rules);
+    // Starting over restores the mesh's cells
+    if let Some(auto_reset) = auto_reset.as_deref_mut() {
+        auto_reset.remember_cells(&grid);
+    }
+    commands.remove_resource::<PendingMeshSeed>();
+}
+
+/// Triangles of a triangle list mesh, moved by `transform`
+fn add_triangles(mesh: &Mesh, transform: &Transform, triangles: &mut Vec<[Vec3; 3]>) {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return;
+    }
+    let Some(positions) = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(VertexAttributeValues::as_float3) else {
+        return;
+    };
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    for corners in indices.chunks_exact(3) {
+        if corners.iter().all(|&i| i < positions.len()) {
+            triangles.push([corners[0], corners[1], corners[2]].map(|i| transform.transform_point(Vec3::from(positions[i]))));
+        }
+    }
+}
+
+/// Cells touched by the triangles, scaled so the longest side of their bounds spans `resolution` cells
+/// A solid fill also takes the cells inside, found by crossing parity along y, so the mesh should be closed
+pub fn voxelize(triangles: &[[Vec3; 3]], resolution: i32, solid: bool) -> VoxModel {
+    let resolution = resolution.max(1);
+    let (min, max) = triangles.iter().flatten().fold((Vec3::MAX, Vec3::MIN), |(min, max), &p| (min.min(p), max.max(p)));
+    if triangles.is_empty() {
+        return VoxModel { size: IVec3::ONE, voxels: Vec::new() };
+    }
+    let scale = resolution as f32 / (max - min).max_element().max(f32::EPSILON);
+    let size = ((max - min) * scale).ceil().as_ivec3().clamp(IVec3::ONE, IVec3::splat(resolution));
+    let cell = |p: Vec3| p.floor().as_ivec3().clamp(IVec3::ZERO, size - 1);
+    let triangles: Vec<[Vec3; 3]> = triangles.iter().map(|corners| corners.map(|p| (p - min) * scale)).collect();
+
+    // Surface: sample each triangle at under half a cell spacing
+    let mut cells = HashSet::new();
+    for &[a, b, c] in &triangles {
+        let steps = ((b - a).length().max((c - a).length()).max((c - b).length()) * 2.0).ceil().max(1.0) as i32;
+        for i in 0..=steps {
+            for j in 0..=steps - i {
+                let (u, v) = (i as f32 / steps as f32, j as f32 / steps as f32);
+                cells.insert(cell(a + (b - a) * u + (c - a) * v));
+            }
+        }
+    }
+
+    // Inside: along each column through cell centers, cells between odd and even crossings
+    if solid {
+        let mut crossings = vec![Vec::new(); (size.x * size.z) as usize];
+        for &[a, b, c] in &triangles {
+            let (low, high) = (a.min(b).min(c), a.max(b).max(c));
+            for z in (low.z - 0.5).ceil().max(0.0) as i32..=((high.z - 0.5).floor() as i32).min(size.z - 1) {
+                for x in (low.x - 0.5).ceil().max(0.0) as i32..=((high.x - 0.5).floor() as i32).min(size.x - 1) {
+                    if let Some(y) = column_crossing([a, b, c], Vec2::new(x as f32 + 0.5, z as f32 + 0.5)) {
+                        crossings[(x + z * size.x) as usize].push(y);
+                    }
+                }
+            }
+        }
+        for (column, ys) in crossings.iter_mut().enumerate() {
+            let (x, z) = (column as i32 % size.x, column as i32 / size.x);
+            ys.sort_by(f32::total_cmp);
+            for pair in ys.chunks_exact(2) {
+                for y in (pair[0] - 0.5).ceil().max(0.0) as i32..=((pair[1] - 0.5).floor() as i32).min(size.y - 1) {
+                    cells.insert(IVec3::new(x, y, z));
+                }
+            }
+        }
+    }
+
+    VoxModel { size, voxels: cells.into_iter().map(|pos| (pos, 1)).collect() }
+}
+
+/// Height at which the vertical line through `point` (x, z) crosses the triangle, if it does
+/// Points on a shared edge count for one side only, so closed meshes cross an even number of times
+fn column_crossing([a, b, c]: [Vec3; 3], point: Vec2) -> Option<f32> {
+    let (a2, b2, c2) = (a.xz(), b.xz(), c.xz());
+    let area = (b2 - a2).perp_dot(c2 - a2);
+    if area == 0.0 {
+        return None;
+    }
+    let edge = |from: Vec2, to: Vec2| {
+        // Measured from the edge's lower end, so both triangles sharing it round the same way
+        let forward = (from.x, from.y) < (to.x, to.y);
+        let (start, end) = if forward { (from, to) } else { (to, from) };
+        let cross = (end - start).perp_dot(point - start);
+        let side = if forward { cross } else { -cross } * area.signum();
+        // Points exactly on the edge go to the triangle on one side only
+        side > 0.0 || (side == 0.0 && forward == (area > 0.0))
+    };
+    if !(edge(b2, c2) && edge(c2, a2) && edge(a2, b2)) {
+        return None;
+    }
+    let u = (c2 - b2).perp_dot(point - b2) / area;
+    let v = (a2 - c2).perp_dot(point - c2) / area;
+    Some(a.y * u + b.y * v + c.y * (1.0 - u - v))
+}
+
+/// Loads Wavefront OBJ files as meshes, positions and faces only
+#[derive(Default, TypePath)]
+pub struct ObjLoader;
+
+impl AssetLoader for ObjLoader {
+    type Asset = Mesh;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), _context: &mut LoadContext<'_>) -> io::Result<Mesh> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        parse_obj(&String::from_utf8_lossy(&bytes))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["obj"]
+    }
+}
+
+/// Triangle list mesh of an OBJ file's vertices and faces, polygons fanned into triangles
+pub fn parse_obj(text: &str) -> io::Result<Mesh> {
+    let invalid =
+        |number: usize, message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", number, message));
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices = Vec::new();
+    for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("v") => {
+                let coords: Vec<f32> = parts
+                    .take(3)
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e: std::num::ParseFloatError| invalid(number, &e.to_string()))?;
+                let [x, y, z] = coords[..] else { return Err(invalid(number, "expected `v x y z`")) };
+                positions.push([x, y, z]);
+            }
+            Some("f") => {
+                // Vertices are `v`, `v/vt`, `v/vt/vn` or `v//vn`, 1-based or negative from the end
+                let corners: Vec<u32> = parts
+                    .map(|corner| {
+                        let index: i64 = corner
+                            .split('/')
+                            .next()
+                            .unwrap_or_default()
+                            .parse()
+                            .map_err(|_| invalid(number, "bad face index"))?;
+                        let index = if index < 0 { positions.len() as i64 + index } else { index - 1 };
+                        if !(0..positions.len() as i64).contains(&index) {
+                            return Err(invalid(number, "face index out of range"));
+                        }
+                        Ok(index as u32)
+                    })
+                    .collect::<io::Result<_>>()?;
+                for i in 1..corners.len().saturating_sub(1) {
+                    indices.extend([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_indices(Indices::U32(indices)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+
+    fn indices(text: &str) -> Vec<u32> {
+        match parse_obj(text).expect(text).indices() {
+            Some(Indices::U32(indices)) => indices.clone(),
+            other => panic!("expected u32 indices, got {:?}", other),
+        }
+    }
+
+    fn parse_error(text: &str) -> String {
+        let error = parse_obj(text).expect_err(text);
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        error.to_string()
+    }
+
+    #[test]
+    fn faces_index_vertices() {
+        assert_eq!(indices(&format!("{}f 1 2 3\n", TRIANGLE)), [0, 1, 2]);
+        assert_eq!(indices(&format!("{}f 1/1 2/2/2 3//3\n", TRIANGLE)), [0, 1, 2]);
+        assert_eq!(indices(&format!("{}f -3 -2 -1\n", TRIANGLE)), [0, 1, 2]);
+        assert_eq!(indices(&format!("{}v 1 1 0\nf 1 2 4 3\n", TRIANGLE)), [0, 1, 3, 0, 3, 2]);
+    }
+
+    #[test]
+    fn bad_face_indices_are_rejected() {
+        assert_eq!(parse_error(&format!("{}f 1 2 4\n", TRIANGLE)), "line 4: face index out of range");
+        assert_eq!(parse_error(&format!("{}f 0 1 2\n", TRIANGLE)), "line 4: face index out of range");
+        assert_eq!(parse_error(&format!("{}f -4 1 2\n", TRIANGLE)), "line 4: face index out of range");
+        assert_eq!(parse_error(&format!("{}f 1 two 3\n", TRIANGLE)), "line 4: bad face index");
+        assert_eq!(parse_error(&format!("{}f /1 2 3\n", TRIANGLE)), "line 4: bad face index");
+        // Faces can only use vertices defined above them
+        assert_eq!(parse_error("v 0 0 0\nf 1 2 3\nv 1 0 0\nv 0 1 0\n"), "line 2: face index out of range");
+    }
+
+    #[test]
+    fn bad_vertices_are_rejected() {
+        assert_eq!(parse_error("v 0 0\n"), "line 1: expected `v x y z`");
+        assert!(parse_error("v 0 zero 0\n").starts_with("line 1: "));
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::math::{IVec3, Vec3};
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use crate::rule::Rule;
+
+/// Grid axis used to split the grid into slabs
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// Index of the axis' component in vectors
+    pub fn index(self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+
+    /// Where `pos` in a box of `size` cells ends up after `turns` quarter turns about the axis,
+    /// counterclockwise looking down it, and the size of the turned box
+    pub fn turn(self, mut pos: IVec3, mut size: IVec3, turns: i32) -> (IVec3, IVec3) {
+        // The other two axes in cyclic order, a quarter turn takes the first onto the second
+        let (a, b) = ((self.index() + 1) % 3, (self.index() + 2) % 3);
+        for _ in 0..turns.rem_euclid(4) {
+            (pos[a], pos[b]) = (size[b] - 1 - pos[b], pos[a]);
+            (size[a], size[b]) = (size[b], size[a]);
+        }
+        (pos, size)
+    }
+}
+
+/// How grid positions map to zones
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ZoneLayout {
+    /// Slabs along `axis`, each bound a fraction (0..1) of the grid size where the next zone starts
+    /// Example: `bounds: [0.5]` splits the grid in half, zone 0 below the middle and zone 1 above
+    Slabs { axis: Axis, bounds: Vec<f32> },
+    /// Spherical shells around the grid center, each radius a fraction of half the grid size
+    /// Example: `radii: [0.5]` gives a core (zone 0) and an outer shell (zone 1)
+    Shells { radii: Vec<f32> },
+}
+
+impl ZoneLayout {
+    /// Zone of the cell at `pos` in a grid of `size` cells per side
+    pub fn zone_at(&self, pos: IVec3, size: i32) -> u8 {
+        let (value, bounds) = match self {
+            ZoneLayout::Slabs { axis, bounds } => {
+                let coord = match axis {
+                    Axis::X => pos.x,
+                    Axis::Y => pos.y,
+                    Axis::Z => pos.z,
+                };
+                ((coord as f32 + 0.5) / size as f32, bounds)
+            }
+            ZoneLayout::Shells { radii } => {
+                let center = Vec3::splat((size - 1) as f32 * 0.5);
+                (pos.as_vec3().distance(center) / (size as f32 * 0.5), radii)
+            }
+        };
+        bounds.iter().filter(|&&bound| value >= bound).count().min(u8::MAX as usize) as u8
+    }
+}
+
+/// Direction a rule gradient runs in, from t = 0 to t = 1
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GradientDirection {
+    /// From the low to the high end of an axis
+    Along(Axis),
+    /// From the grid center out to the middle of the faces (corners stay at t = 1)
+    Radial,
+}
+
+impl GradientDirection {
+    /// Layout splitting the direction into `steps` equal bands, zone i covering t in i/steps..(i+1)/steps
+    pub fn layout(self, steps: usize) -> ZoneLayout {
+        let bounds = (1..steps).map(|i| i as f32 / steps as f32).collect();
+        match self {
+            GradientDirection::Along(axis) => ZoneLayout::Slabs { axis, bounds },
+            GradientDirection::Radial => ZoneLayout::Shells { radii: bounds },
+        }
+    }
+}
+
+/// Config spec for rule parameters that vary across the grid: the base rule's survival and birth
+/// counts are shifted linearly from 0 at t = 0 to the given shift at t = 1
+/// Example: `survival_shift: 3` with `direction: Radial` makes survival stricter towards the edges
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RuleGradient {
+    pub direction: GradientDirection,
+    /// Number of bands the gradient is split into (1-256), more bands give a smoother change
+    pub steps: usize,
+    #[serde(default)]
+    pub survival_shift: i32,
+    #[serde(default)]
+    pub birth_shift: i32,
+}
+
+impl RuleGradient {
+    /// Zoned rules of `rule` shifted along the gradient
+    pub fn zones(&self, rule: &Rule) -> ZonedRules {
+        ZonedRules::gradient(self.direction, self.steps, |t| {
+            let shift = |total: i32| (total as f32 * t).round() as i32;
+            rule.shifted(shift(self.survival_shift), shift(self.birth_shift))
+        })
+    }
+}
+
+/// Different rules in different regions of the grid
+/// Each cell stores its zone and follows that zone's rule. A living cell adds to its neighbors'
+/// counts with its own zone's neighborhood, so cells near a border see both zones
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ZonedRules {
+    pub layout: ZoneLayout,
+    /// Rule of each zone, zones past the end use the last rule
+    pub rules: Vec<Rule>,
+}
+
+impl ZonedRules {
+    pub fn new(layout: ZoneLayout, rules: impl IntoIterator<Item = Rule>) -> Self {
+        let rules: Vec<Rule> = rules.into_iter().collect();
+        assert!(!rules.is_empty(), "zoned rules need at least one rule");
+        Self { layout, rules }
+    }
+
+    /// `left` rule for the lower half along X, `right` rule for the upper half
+    pub fn split_x(left: Rule, right: Rule) -> Self {
+        Self::new(ZoneLayout::Slabs { axis: Axis::X, bounds: vec![0.5] }, [left, right])
+    }
+
+    /// `core` rule within `radius` (fraction of half the grid size) of the center, `shell` rule outside
+    pub fn core_and_shell(core: Rule, shell: Rule, radius: f32) -> Self {
+        Self::new(ZoneLayout::Shells { radii: vec![radius] }, [core, shell])
+    }
+
+    /// Rules varying across the grid: `steps` bands along `direction` (clamped to 1-256, one zone
+    /// each), band i following `rule_at(t)` with t = i / (steps - 1) running from 0 to 1
+    /// Example: `gradient(GradientDirection::Radial, 8, |t| Rule::builder().shifted((t * 3.0) as i32, 0))`
+    pub fn gradient(direction: GradientDirection, steps: usize, rule_at: impl Fn(f32) -> Rule) -> Self {
+        let steps = steps.clamp(1, u8::MAX as usize + 1);
+        let rules = (0..steps).map(|i| rule_at(i as f32 / (steps - 1).max(1) as f32));
+        Self::new(direction.layout(steps), rules)
+    }
+
+    /// Highest state count of all zones, the state that renders as fully alive
+    pub fn max_states(&self) -> u8 {
+        self.rules.iter().map(|rule| rule.states).max().unwrap_or(1)
+    }
+}
+
+/// Rule for cells in `zone`, the last rule for zones past the end (`rules` must not be empty)
+#[inline]
+pub fn zone_rule(rules: &[Rule], zone: u8) -> &Rule {
+    &rules[(zone as usize).min(rules.len() - 1)]
+}